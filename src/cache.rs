@@ -0,0 +1,140 @@
+//! On-disk cache of lookup table addresses and mint decimals, so repeated runs over the same
+//! window do not fetch them again.
+//!
+//! Both are keyed by pubkey in one JSON file per cluster, named after its genesis hash so mainnet
+//! and devnet entries never mix. Lookup tables are append-only: a cached table stays valid, and
+//! [`crate::resolve_accounts`] fetches it again when a transaction indexes past its cached end.
+
+use crate::{LookupCache, MintRegistry, ParseSummary};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+
+/// Layout of a cache file, with pubkeys as base58 strings
+#[derive(Default, Serialize, Deserialize)]
+struct CacheFile {
+    #[serde(default)]
+    mint_decimals: BTreeMap<String, u8>,
+    #[serde(default)]
+    lookup_tables: BTreeMap<String, Vec<String>>,
+}
+
+/// Lookup tables and mint decimals of one cluster, loaded from and saved to a cache file
+#[derive(Debug, Default)]
+pub struct DiskCache {
+    path: PathBuf,
+    pub mint_decimals: HashMap<Pubkey, u8>,
+    pub lookup_tables: LookupCache,
+}
+
+impl DiskCache {
+    /// `$XDG_CACHE_HOME/solana-kamino-rs`, else `~/.cache/solana-kamino-rs`
+    pub fn default_dir() -> Option<PathBuf> {
+        let base = match env::var_os("XDG_CACHE_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+        };
+        Some(base.join("solana-kamino-rs"))
+    }
+
+    /// Loads the cache of the client's cluster from `dir`. A missing file is an empty cache, and an
+    /// unreadable one is replaced on the next save with a warning.
+    pub async fn open(client: &RpcClient, dir: &Path) -> Result<Self> {
+        let genesis_hash = client.get_genesis_hash().await.context("failed to identify the cluster for the cache")?;
+        Ok(Self::load(dir.join(format!("{}.json", genesis_hash))))
+    }
+
+    /// Loads the cache file at `path`, see [`DiskCache::open`]
+    pub fn load(path: PathBuf) -> Self {
+        let file = match fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str::<CacheFile>(&json) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("Ignoring invalid cache file {}: {}", path.display(), e);
+                    CacheFile::default()
+                }
+            },
+            Err(e) => {
+                debug!("No cache loaded from {}: {}", path.display(), e);
+                CacheFile::default()
+            }
+        };
+        let pubkey = |value: &str| Pubkey::from_str(value).ok();
+        let mint_decimals = file
+            .mint_decimals
+            .iter()
+            .filter_map(|(mint, decimals)| Some((pubkey(mint)?, *decimals)))
+            .collect();
+        let lookup_tables = file
+            .lookup_tables
+            .iter()
+            .filter_map(|(table, addresses)| Some((pubkey(table)?, addresses.iter().map(|address| pubkey(address)).collect::<Option<_>>()?)))
+            .collect();
+        let cache = Self { path, mint_decimals, lookup_tables };
+        debug!(
+            mints = cache.mint_decimals.len(), lookup_tables = cache.lookup_tables.len(),
+            "Loaded cache from {}", cache.path.display()
+        );
+        cache
+    }
+
+    /// Adds the cached decimals to `mints`, so they are not fetched again
+    pub fn seed_mints(&self, mints: &mut MintRegistry) {
+        for (mint, decimals) in &self.mint_decimals {
+            mints.insert(*mint, *decimals);
+        }
+    }
+
+    /// Takes in the decimals and lookup tables resolved during a scan. Of two copies of a table,
+    /// the longer one is the more recent.
+    pub fn update(&mut self, summary: &ParseSummary) {
+        self.mint_decimals.extend(summary.mints.known_decimals());
+        for (table, addresses) in &summary.lookup_tables {
+            let cached = self.lookup_tables.entry(*table).or_default();
+            if addresses.len() > cached.len() {
+                cached.clone_from(addresses);
+            }
+        }
+    }
+
+    /// Writes the cache file, replacing the previous one atomically so concurrent runs never read a
+    /// partial file
+    pub fn save(&self) -> Result<()> {
+        let file = CacheFile {
+            mint_decimals: self.mint_decimals.iter().map(|(mint, decimals)| (mint.to_string(), *decimals)).collect(),
+            lookup_tables: self
+                .lookup_tables
+                .iter()
+                .map(|(table, addresses)| (table.to_string(), addresses.iter().map(Pubkey::to_string).collect()))
+                .collect(),
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("failed to create cache directory {}", dir.display()))?;
+        }
+        let temp_path = self.path.with_extension(format!("{}.tmp", std::process::id()));
+        let write = || -> Result<()> {
+            let mut temp = File::create(&temp_path)?;
+            temp.write_all(serde_json::to_string(&file)?.as_bytes())?;
+            temp.sync_all()?;
+            fs::rename(&temp_path, &self.path)?;
+            Ok(())
+        };
+        if let Err(e) = write() {
+            fs::remove_file(&temp_path).ok();
+            return Err(e.context(format!("failed to write cache file {}", self.path.display())));
+        }
+        info!(
+            mints = self.mint_decimals.len(), lookup_tables = self.lookup_tables.len(),
+            "Saved cache to {}", self.path.display()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! Parquet sink for parsed records, for loading full scans into DuckDB, Spark and the like.
+//!
+//! The columns are those of `--format csv` plus `instruction_index`, strongly typed: block times
+//! are second timestamps, and instruction types and mints are dictionary encoded since a scan
+//! only ever has a handful of distinct ones.
+
+use crate::{KaminoTransaction, ParseSummary, TransactionType};
+use anyhow::Result;
+use arrow_array::builder::StringDictionaryBuilder;
+use arrow_array::types::Int32Type;
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, TimestampSecondArray, UInt32Array, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::io::Write;
+use std::sync::Arc;
+
+// Rows per record batch, bounding the memory the columns take on top of the records
+const BATCH_SIZE: usize = 65_536;
+
+/// Schema of the Parquet output
+pub fn parquet_schema() -> Schema {
+    let dictionary = DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8));
+    Schema::new(vec![
+        Field::new("signature", DataType::Utf8, false),
+        Field::new("slot", DataType::UInt64, false),
+        Field::new("block_time", DataType::Timestamp(TimeUnit::Second, Some("UTC".into())), true),
+        Field::new("instruction_type", dictionary.clone(), false),
+        Field::new("instruction_index", DataType::UInt32, false),
+        Field::new("inner_index", DataType::UInt32, true),
+        Field::new("reserve_mint", dictionary, true),
+        Field::new("raw_amount", DataType::UInt64, true),
+        Field::new("ui_amount", DataType::Float64, true),
+        Field::new("principal_amount", DataType::UInt64, true),
+        Field::new("interest_amount", DataType::UInt64, true),
+        Field::new("failed", DataType::Boolean, false),
+    ])
+}
+
+/// Writes the summary's records to `writer` as one Snappy-compressed Parquet file
+pub fn write_parquet<W: Write + Send>(writer: W, summary: &ParseSummary) -> Result<()> {
+    let schema = Arc::new(parquet_schema());
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(writer, schema.clone(), Some(properties))?;
+    for records in summary.records.chunks(BATCH_SIZE) {
+        writer.write(&record_batch(schema.clone(), records, summary)?)?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+fn record_batch(schema: Arc<Schema>, records: &[KaminoTransaction], summary: &ParseSummary) -> Result<RecordBatch> {
+    let split = |record: &KaminoTransaction| match record.transaction_type {
+        TransactionType::Repay { split, .. } => split,
+        _ => None,
+    };
+    let ui_amount = |record: &KaminoTransaction| {
+        let decimals = record.reserve_token.and_then(|mint| summary.mints.get(&mint))?;
+        Some(record.transaction_type.amount()? as f64 / 10f64.powi(decimals as i32))
+    };
+    let mut instruction_types = StringDictionaryBuilder::<Int32Type>::new();
+    let mut reserve_mints = StringDictionaryBuilder::<Int32Type>::new();
+    for record in records {
+        instruction_types.append_value(record.transaction_type.name());
+        reserve_mints.append_option(record.reserve_token.map(|mint| mint.to_string()));
+    }
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(records.iter().map(|record| &record.signature))),
+        Arc::new(UInt64Array::from_iter_values(records.iter().map(|record| record.slot))),
+        Arc::new(TimestampSecondArray::from_iter(records.iter().map(|record| record.block_time)).with_timezone("UTC")),
+        Arc::new(instruction_types.finish()),
+        Arc::new(UInt32Array::from_iter_values(records.iter().map(|record| record.instruction_index as u32))),
+        Arc::new(UInt32Array::from_iter(records.iter().map(|record| record.inner_index.map(|index| index as u32)))),
+        Arc::new(reserve_mints.finish()),
+        Arc::new(UInt64Array::from_iter(records.iter().map(|record| record.transaction_type.amount()))),
+        Arc::new(Float64Array::from_iter(records.iter().map(ui_amount))),
+        Arc::new(UInt64Array::from_iter(records.iter().map(|record| split(record).map(|split| split.principal)))),
+        Arc::new(UInt64Array::from_iter(records.iter().map(|record| split(record).map(|split| split.interest)))),
+        Arc::new(BooleanArray::from_iter(records.iter().map(|record| Some(record.failed)))),
+    ];
+    Ok(RecordBatch::try_new(schema, columns)?)
+}
@@ -0,0 +1,115 @@
+//! SQLite sink for parsed records, so repeated runs build up an incremental index.
+
+use crate::KaminoTransaction;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use tracing::warn;
+
+// inner_index is -1 for top-level instructions, so it can be part of the primary key
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS kamino_transactions (
+    signature TEXT NOT NULL,
+    instruction_index INTEGER NOT NULL,
+    inner_index INTEGER NOT NULL DEFAULT -1,
+    slot INTEGER NOT NULL,
+    block_time INTEGER,
+    type TEXT NOT NULL,
+    mint TEXT,
+    amount INTEGER,
+    failed INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (signature, instruction_index, inner_index)
+);
+CREATE INDEX IF NOT EXISTS kamino_transactions_slot ON kamino_transactions (slot);
+";
+
+/// Parsed records keyed by signature and instruction index, so re-inserting a transaction that
+/// is already stored is a no-op
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    /// Opens (or creates) the database at `path` and makes sure the schema exists
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).with_context(|| format!("failed to open database {}", path.display()))?;
+        migrate_inner_index(&conn)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Inserts records that are not stored yet and returns how many were new
+    pub fn insert_records<'a>(&mut self, records: impl IntoIterator<Item = &'a KaminoTransaction>) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT OR IGNORE INTO kamino_transactions
+                 (signature, instruction_index, inner_index, slot, block_time, type, mint, amount, failed)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for record in records {
+                // SQLite integers are signed 64-bit; amounts beyond that are stored as NULL
+                let amount = record.transaction_type.amount().and_then(|amount| match i64::try_from(amount) {
+                    Ok(amount) => Some(amount),
+                    Err(_) => {
+                        warn!(signature = %record.signature, "Amount {} does not fit in SQLite, storing NULL", amount);
+                        None
+                    }
+                });
+                inserted += insert.execute(params![
+                    record.signature,
+                    record.instruction_index as i64,
+                    record.inner_index.map_or(-1, |inner_index| inner_index as i64),
+                    record.slot as i64,
+                    record.block_time,
+                    record.transaction_type.name(),
+                    record.reserve_token.map(|mint| mint.to_string()),
+                    amount,
+                    record.failed,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Highest slot stored so far, used to resume a scan
+    pub fn latest_slot(&self) -> Result<Option<u64>> {
+        let slot: Option<i64> = self
+            .conn
+            .query_row("SELECT MAX(slot) FROM kamino_transactions", [], |row| row.get(0))
+            .optional()?
+            .flatten();
+        Ok(slot.map(|slot| slot as u64))
+    }
+}
+
+/// Databases created before CPI instructions were parsed key rows by (signature,
+/// instruction_index) only; rebuilds their table with `inner_index` in the key
+fn migrate_inner_index(conn: &Connection) -> Result<()> {
+    let has_table: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'kamino_transactions'",
+        [],
+        |row| row.get(0),
+    )?;
+    let has_inner_index: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('kamino_transactions') WHERE name = 'inner_index'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_table || has_inner_index {
+        return Ok(());
+    }
+    conn.execute_batch(&format!(
+        "BEGIN;
+         ALTER TABLE kamino_transactions RENAME TO kamino_transactions_old;
+         DROP INDEX IF EXISTS kamino_transactions_slot;
+         {SCHEMA}
+         INSERT INTO kamino_transactions (signature, instruction_index, slot, block_time, type, mint, amount, failed)
+             SELECT signature, instruction_index, slot, block_time, type, mint, amount, failed FROM kamino_transactions_old;
+         DROP TABLE kamino_transactions_old;
+         COMMIT;"
+    ))?;
+    Ok(())
+}
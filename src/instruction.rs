@@ -0,0 +1,705 @@
+//! Discriminators and decoding of individual Kamino Lend instructions.
+
+use crate::state::RepaySplit;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::{v0, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::VersionedTransaction;
+use std::collections::{HashMap, VecDeque};
+use tracing::{debug, warn};
+
+// Instruction discriminators are Anchor's: the first 8 bytes of sha256("global:<instruction_name>"),
+// matched in full; see DISCRIMINATORS. Instructions renamed across klend versions accept every
+// variant through their `_DATA_SIGS` list, current variant first. The v2 variants only append farm
+// accounts (and, for repay, the market authority) after the v1 ones, so the account positions read
+// below hold for both.
+pub const FLASH_LOAN_DATA_BORROW_SIG: [u8; 8] = [0x87, 0xe7, 0x34, 0xa7, 0x07, 0x34, 0xd4, 0xc1];
+pub const FLASH_LOAN_TOKEN_ACCOUNT_KEY: usize = 4;
+
+// Repays the matching flash borrow in the same transaction; the excess over the borrow is the fee
+pub const FLASH_LOAN_REPAY_SIG: [u8; 8] = [0xb9, 0x75, 0x00, 0xcb, 0x60, 0xf5, 0xb4, 0xba];
+pub const FLASH_LOAN_REPAY_TOKEN_ACCOUNT_KEY: usize = 4;
+
+// The owner signs the borrow and must match the obligation's owner, so the borrower is known
+// without fetching the obligation account
+pub const BORROW_OBLIGATION_DATA_SIG: [u8; 8] = [0xa1, 0x80, 0x8f, 0xf5, 0xab, 0xc7, 0xc2, 0x06];
+pub const BORROW_OBLIGATION_V1_DATA_SIG: [u8; 8] = [0x79, 0x7f, 0x12, 0xcc, 0x49, 0xf5, 0xe1, 0x41];
+pub const BORROW_OBLIGATION_DATA_SIGS: [[u8; 8]; 2] = [BORROW_OBLIGATION_DATA_SIG, BORROW_OBLIGATION_V1_DATA_SIG];
+pub const BORROW_OBLIGATION_OWNER_ACCOUNT_KEY: usize = 0;
+pub const BORROW_OBLIGATION_OBLIGATION_ACCOUNT_KEY: usize = 1;
+pub const BORROW_OBLIGATION_TOKEN_ACCOUNT_KEY: usize = 5;
+// referrer_token_state is an optional account: borrows without a referrer pass the program id in
+// its place, and older clients may leave it out entirely
+pub const BORROW_OBLIGATION_REFERRER_ACCOUNT_KEY: usize = 9;
+
+pub const REPAY_OBLIGATION_DATA_SIG: [u8; 8] = [0x74, 0xae, 0xd5, 0x4c, 0xb4, 0x35, 0xd2, 0x90];
+pub const REPAY_OBLIGATION_V1_DATA_SIG: [u8; 8] = [0x91, 0xb2, 0x0d, 0xe1, 0x4c, 0xf0, 0x93, 0x48];
+pub const REPAY_OBLIGATION_DATA_SIGS: [[u8; 8]; 2] = [REPAY_OBLIGATION_DATA_SIG, REPAY_OBLIGATION_V1_DATA_SIG];
+pub const REPAY_OBLIGATION_OBLIGATION_ACCOUNT_KEY: usize = 1;
+pub const REPAY_OBLIGATION_RESERVE_ACCOUNT_KEY: usize = 3;
+pub const REPAY_OBLIGATION_TOKEN_ACCOUNT_KEY: usize = 4;
+
+pub const DEPOSIT_RESERVE_LIQUIDITY_DATA_SIG: [u8; 8] = [0xa9, 0xc9, 0x1e, 0x7e, 0x06, 0xcd, 0x66, 0x44];
+pub const DEPOSIT_RESERVE_LIQUIDITY_TOKEN_ACCOUNT_KEY: usize = 4;
+
+// deposit_obligation_collateral moves cTokens the user already holds into the obligation and, like
+// withdrawals, carries no mint account. The v2 variant appends farm accounts after the same ones.
+pub const DEPOSIT_OBLIGATION_COLLATERAL_DATA_SIG: [u8; 8] = [0x6c, 0xd1, 0x04, 0x48, 0x15, 0x16, 0x76, 0x85];
+pub const DEPOSIT_OBLIGATION_COLLATERAL_V2_DATA_SIG: [u8; 8] = [0x89, 0x91, 0x97, 0x5e, 0xa7, 0x71, 0x04, 0x91];
+pub const DEPOSIT_OBLIGATION_COLLATERAL_DATA_SIGS: [[u8; 8]; 2] =
+    [DEPOSIT_OBLIGATION_COLLATERAL_V2_DATA_SIG, DEPOSIT_OBLIGATION_COLLATERAL_DATA_SIG];
+pub const DEPOSIT_OBLIGATION_COLLATERAL_OBLIGATION_ACCOUNT_KEY: usize = 1;
+pub const DEPOSIT_OBLIGATION_COLLATERAL_RESERVE_ACCOUNT_KEY: usize = 3;
+
+// deposit_reserve_liquidity_and_obligation_collateral deposits liquidity and posts the minted
+// cTokens as collateral in one go; the amount is in liquidity units. Same v2 layout as above.
+pub const DEPOSIT_AND_COLLATERALIZE_DATA_SIG: [u8; 8] = [0x81, 0xc7, 0x04, 0x02, 0xde, 0x27, 0x1a, 0x2e];
+pub const DEPOSIT_AND_COLLATERALIZE_V2_DATA_SIG: [u8; 8] = [0xd8, 0xe0, 0xbf, 0x1b, 0xcc, 0x97, 0x66, 0xaf];
+pub const DEPOSIT_AND_COLLATERALIZE_DATA_SIGS: [[u8; 8]; 2] = [DEPOSIT_AND_COLLATERALIZE_V2_DATA_SIG, DEPOSIT_AND_COLLATERALIZE_DATA_SIG];
+pub const DEPOSIT_AND_COLLATERALIZE_OBLIGATION_ACCOUNT_KEY: usize = 1;
+pub const DEPOSIT_AND_COLLATERALIZE_RESERVE_ACCOUNT_KEY: usize = 4;
+pub const DEPOSIT_AND_COLLATERALIZE_TOKEN_ACCOUNT_KEY: usize = 5;
+
+// withdraw_obligation_collateral carries no mint account, only the reserve it withdraws from
+pub const WITHDRAW_OBLIGATION_COLLATERAL_DATA_SIG: [u8; 8] = [0xca, 0xf9, 0x75, 0x72, 0xe7, 0xc0, 0x2f, 0x8a];
+pub const WITHDRAW_OBLIGATION_COLLATERAL_V1_DATA_SIG: [u8; 8] = [0x25, 0x74, 0xcd, 0x67, 0xf3, 0xc0, 0x5c, 0xc6];
+pub const WITHDRAW_OBLIGATION_COLLATERAL_DATA_SIGS: [[u8; 8]; 2] =
+    [WITHDRAW_OBLIGATION_COLLATERAL_DATA_SIG, WITHDRAW_OBLIGATION_COLLATERAL_V1_DATA_SIG];
+pub const WITHDRAW_OBLIGATION_COLLATERAL_OBLIGATION_ACCOUNT_KEY: usize = 1;
+pub const WITHDRAW_OBLIGATION_COLLATERAL_RESERVE_ACCOUNT_KEY: usize = 4;
+
+pub const REDEEM_RESERVE_COLLATERAL_DATA_SIG: [u8; 8] = [0xea, 0x75, 0xb5, 0x7d, 0xb9, 0x8e, 0xdc, 0x1d];
+pub const REDEEM_RESERVE_COLLATERAL_TOKEN_ACCOUNT_KEY: usize = 4;
+// Redeems have no obligation, so the market comes right after the owner instead of at
+// LENDING_MARKET_ACCOUNT_KEY
+pub const REDEEM_RESERVE_COLLATERAL_LENDING_MARKET_ACCOUNT_KEY: usize = 1;
+
+// liquidate_obligation_and_redeem_reserve_collateral: the debt side (repay reserve at 4, its mint at
+// 5) comes before the collateral side (withdraw reserve at 7, its liquidity mint at 8)
+pub const LIQUIDATE_OBLIGATION_DATA_SIG: [u8; 8] = [0xa2, 0xa1, 0x23, 0x8f, 0x1e, 0xbb, 0xb9, 0x67];
+pub const LIQUIDATE_OBLIGATION_V1_DATA_SIG: [u8; 8] = [0xb1, 0x47, 0x9a, 0xbc, 0xe2, 0x85, 0x4a, 0x37];
+pub const LIQUIDATE_OBLIGATION_DATA_SIGS: [[u8; 8]; 2] = [LIQUIDATE_OBLIGATION_DATA_SIG, LIQUIDATE_OBLIGATION_V1_DATA_SIG];
+pub const LIQUIDATE_OBLIGATION_LIQUIDATOR_ACCOUNT_KEY: usize = 0;
+pub const LIQUIDATE_OBLIGATION_REPAY_TOKEN_ACCOUNT_KEY: usize = 5;
+pub const LIQUIDATE_OBLIGATION_WITHDRAW_TOKEN_ACCOUNT_KEY: usize = 8;
+// Second argument, after liquidity_amount: min_acceptable_received_liquidity_amount
+pub const LIQUIDATE_OBLIGATION_MIN_RECEIVED_OFFSET: usize = 16;
+
+// refresh_reserve and refresh_obligation take no arguments. They bring a reserve's prices and
+// interest, or an obligation's values, up to date and must precede borrows, withdrawals and
+// liquidations in the same slot, so they are recorded for context but move no funds.
+pub const REFRESH_RESERVE_DATA_SIG: [u8; 8] = [0x02, 0xda, 0x8a, 0xeb, 0x4f, 0xc9, 0x19, 0x66];
+pub const REFRESH_RESERVE_RESERVE_ACCOUNT_KEY: usize = 0;
+pub const REFRESH_RESERVE_LENDING_MARKET_ACCOUNT_KEY: usize = 1;
+
+pub const REFRESH_OBLIGATION_DATA_SIG: [u8; 8] = [0x21, 0x84, 0x93, 0xe4, 0x97, 0xc0, 0x48, 0x59];
+pub const REFRESH_OBLIGATION_LENDING_MARKET_ACCOUNT_KEY: usize = 0;
+pub const REFRESH_OBLIGATION_OBLIGATION_ACCOUNT_KEY: usize = 1;
+
+pub const REFRESH_DATA_SIGS: [[u8; 8]; 2] = [REFRESH_RESERVE_DATA_SIG, REFRESH_OBLIGATION_DATA_SIG];
+
+// Lending market of every other decoded instruction, flash loans and liquidations included
+pub const LENDING_MARKET_ACCOUNT_KEY: usize = 2;
+
+// Amount arguments follow the discriminator, 8 bytes for every instruction
+pub const AMOUNT_OFFSET: usize = FLASH_LOAN_DATA_BORROW_SIG.len();
+// Shortest data any decoded instruction with an amount can have: its discriminator and the amount
+pub const MIN_INSTRUCTION_DATA_LEN: usize = AMOUNT_OFFSET + 8;
+
+/// Every discriminator the parser decodes, with the Kamino Lend instruction it belongs to
+pub const DISCRIMINATORS: &[(&str, [u8; 8])] = &[
+    ("flash_borrow_reserve_liquidity", FLASH_LOAN_DATA_BORROW_SIG),
+    ("flash_repay_reserve_liquidity", FLASH_LOAN_REPAY_SIG),
+    ("borrow_obligation_liquidity_v2", BORROW_OBLIGATION_DATA_SIG),
+    ("borrow_obligation_liquidity", BORROW_OBLIGATION_V1_DATA_SIG),
+    ("repay_obligation_liquidity_v2", REPAY_OBLIGATION_DATA_SIG),
+    ("repay_obligation_liquidity", REPAY_OBLIGATION_V1_DATA_SIG),
+    ("deposit_reserve_liquidity", DEPOSIT_RESERVE_LIQUIDITY_DATA_SIG),
+    ("deposit_obligation_collateral", DEPOSIT_OBLIGATION_COLLATERAL_DATA_SIG),
+    ("deposit_obligation_collateral_v2", DEPOSIT_OBLIGATION_COLLATERAL_V2_DATA_SIG),
+    ("deposit_reserve_liquidity_and_obligation_collateral", DEPOSIT_AND_COLLATERALIZE_DATA_SIG),
+    ("deposit_reserve_liquidity_and_obligation_collateral_v2", DEPOSIT_AND_COLLATERALIZE_V2_DATA_SIG),
+    ("withdraw_obligation_collateral_v2", WITHDRAW_OBLIGATION_COLLATERAL_DATA_SIG),
+    ("withdraw_obligation_collateral", WITHDRAW_OBLIGATION_COLLATERAL_V1_DATA_SIG),
+    ("redeem_reserve_collateral", REDEEM_RESERVE_COLLATERAL_DATA_SIG),
+    ("liquidate_obligation_and_redeem_reserve_collateral_v2", LIQUIDATE_OBLIGATION_DATA_SIG),
+    ("liquidate_obligation_and_redeem_reserve_collateral", LIQUIDATE_OBLIGATION_V1_DATA_SIG),
+    ("refresh_reserve", REFRESH_RESERVE_DATA_SIG),
+    ("refresh_obligation", REFRESH_OBLIGATION_DATA_SIG),
+];
+
+/// Name of the Kamino Lend instruction with this discriminator, if the parser decodes it
+pub fn instruction_name(discriminator: &[u8]) -> Option<&'static str> {
+    DISCRIMINATORS
+        .iter()
+        .find(|(_, known)| discriminator.starts_with(known))
+        .map(|(name, _)| *name)
+}
+
+/// Kind of a matched Kamino Lend instruction, carrying its decoded amounts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionType {
+    /// `initiator` is the transaction's fee payer, its first signer
+    FlashBorrow { amount: u64, initiator: Pubkey },
+    /// `fee` is the repay minus the paired flash borrow of the same mint in the transaction
+    FlashRepay { amount: u64, fee: Option<u64> },
+    /// `owner` is the obligation's owner, who signs the borrow. `referrer` is the referrer's token
+    /// state account that collects the host fee, `None` without a referrer.
+    Borrow { amount: u64, obligation: Pubkey, owner: Pubkey, referrer: Option<Pubkey> },
+    /// `split` is only filled in by [`crate::state::fill_repay_splits`]
+    Repay { amount: u64, obligation: Pubkey, reserve: Pubkey, split: Option<RepaySplit> },
+    Deposit { amount: u64 },
+    /// Collateral posted to `obligation` from cTokens already held. Amount is in collateral
+    /// (cToken) units and the record is keyed by `reserve`, as the instruction carries no mint.
+    DepositCollateral { amount: u64, obligation: Pubkey, reserve: Pubkey },
+    /// Liquidity deposited into `reserve` and posted to `obligation` as collateral in one
+    /// instruction. Amount is in liquidity units of the record's mint.
+    DepositAndCollateralize { amount: u64, obligation: Pubkey, reserve: Pubkey },
+    /// Collateral withdrawn from `obligation`. Amount is in collateral (cToken) units
+    Withdraw { amount: u64, obligation: Pubkey },
+    /// Amount is in collateral (cToken) units
+    Redeem { amount: u64 },
+    /// `liquidated_amount` is the debt repaid in the record's mint. `collateral_seized` is the
+    /// minimum collateral liquidity the liquidator accepted, in `collateral_mint` units: the
+    /// instruction does not carry the exact amount received, only this floor.
+    Liquidate {
+        liquidated_amount: u64,
+        collateral_seized: u64,
+        liquidator: Pubkey,
+        collateral_mint: Pubkey,
+    },
+    /// `reserve`'s prices and accrued interest were brought up to date; carries no amount
+    RefreshReserve { reserve: Pubkey },
+    /// `obligation`'s deposit and borrow values were brought up to date; carries no amount
+    RefreshObligation { obligation: Pubkey },
+    /// A Kamino Lend instruction the parser does not decode yet
+    Unknown { discriminator: [u8; 8] },
+}
+
+/// Every [`TransactionType::name`], for validating names given by users
+pub const TRANSACTION_TYPE_NAMES: [&str; 13] = [
+    "flash_borrow",
+    "flash_repay",
+    "borrow",
+    "repay",
+    "deposit",
+    "deposit_collateral",
+    "deposit_and_collateralize",
+    "withdraw",
+    "redeem",
+    "liquidate",
+    "refresh_reserve",
+    "refresh_obligation",
+    "unknown",
+];
+
+/// The [`TransactionType::name`] `name` refers to, in snake_case or kebab-case
+pub fn transaction_type_name(name: &str) -> Option<&'static str> {
+    let name = name.replace('-', "_");
+    TRANSACTION_TYPE_NAMES.into_iter().find(|known| *known == name)
+}
+
+impl TransactionType {
+    /// Stable snake_case name used in exported records
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransactionType::FlashBorrow { .. } => "flash_borrow",
+            TransactionType::FlashRepay { .. } => "flash_repay",
+            TransactionType::Borrow { .. } => "borrow",
+            TransactionType::Repay { .. } => "repay",
+            TransactionType::Deposit { .. } => "deposit",
+            TransactionType::DepositCollateral { .. } => "deposit_collateral",
+            TransactionType::DepositAndCollateralize { .. } => "deposit_and_collateralize",
+            TransactionType::Withdraw { .. } => "withdraw",
+            TransactionType::Redeem { .. } => "redeem",
+            TransactionType::Liquidate { .. } => "liquidate",
+            TransactionType::RefreshReserve { .. } => "refresh_reserve",
+            TransactionType::RefreshObligation { .. } => "refresh_obligation",
+            TransactionType::Unknown { .. } => "unknown",
+        }
+    }
+
+    /// The primary amount moved by the instruction, if it is a known one
+    pub fn amount(&self) -> Option<u64> {
+        match *self {
+            TransactionType::FlashBorrow { amount, .. }
+            | TransactionType::FlashRepay { amount, .. }
+            | TransactionType::Borrow { amount, .. }
+            | TransactionType::Repay { amount, .. }
+            | TransactionType::Deposit { amount }
+            | TransactionType::DepositCollateral { amount, .. }
+            | TransactionType::DepositAndCollateralize { amount, .. }
+            | TransactionType::Withdraw { amount, .. }
+            | TransactionType::Redeem { amount } => Some(amount),
+            TransactionType::Liquidate { liquidated_amount, .. } => Some(liquidated_amount),
+            TransactionType::RefreshReserve { .. } | TransactionType::RefreshObligation { .. } | TransactionType::Unknown { .. } => None,
+        }
+    }
+
+    /// Fee of a flash repay in basis points of the paired flash borrow; `None` for other
+    /// instructions, unpaired repays or a zero borrow
+    pub fn flash_loan_fee_bps(&self) -> Option<f64> {
+        let TransactionType::FlashRepay { amount, fee: Some(fee) } = *self else {
+            return None;
+        };
+        let borrowed = amount.saturating_sub(fee);
+        (borrowed > 0).then(|| fee as f64 * 10_000.0 / borrowed as f64)
+    }
+
+    /// Whether this is a refresh_reserve or refresh_obligation, recorded only as context for the
+    /// instructions around it
+    pub fn is_refresh(&self) -> bool {
+        matches!(self, TransactionType::RefreshReserve { .. } | TransactionType::RefreshObligation { .. })
+    }
+
+    /// The obligation the instruction acts on, for borrows, repays and collateral deposits and
+    /// withdrawals
+    pub fn obligation(&self) -> Option<Pubkey> {
+        match *self {
+            TransactionType::Borrow { obligation, .. }
+            | TransactionType::Repay { obligation, .. }
+            | TransactionType::DepositCollateral { obligation, .. }
+            | TransactionType::DepositAndCollateralize { obligation, .. }
+            | TransactionType::Withdraw { obligation, .. } => Some(obligation),
+            _ => None,
+        }
+    }
+}
+
+/// A single matched Kamino Lend instruction
+#[derive(Clone, Debug)]
+pub struct KaminoTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub transaction_type: TransactionType,
+    /// Reserve liquidity mint, or the reserve itself for collateral deposits and withdrawals which
+    /// carry no mint account; `None` for unknown instructions
+    pub reserve_token: Option<Pubkey>,
+    /// Position of the instruction among the transaction's top-level instructions, or of the
+    /// top-level instruction that invoked it
+    pub instruction_index: usize,
+    /// Position among the CPI instructions invoked by `instruction_index` when the instruction
+    /// came in through CPI (e.g. from an aggregator or vault), `None` for a top-level instruction
+    pub inner_index: Option<usize>,
+    /// The transaction failed; its instructions were attempted but moved no funds
+    pub failed: bool,
+    /// Lending market the instruction acts in; `None` for unknown instructions
+    pub lending_market: Option<Pubkey>,
+}
+
+/// Restricts records to instructions that touch given reserves or lending markets, to given
+/// reserve mints and to given instruction types. Empty lists match everything; every condition
+/// must hold.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AccountFilter {
+    /// Match instructions touching any of these reserves
+    pub reserves: Vec<Pubkey>,
+    /// Match instructions touching any of these lending markets
+    pub markets: Vec<Pubkey>,
+    /// Only count records of these reserve liquidity mints; takes precedence over `exclude_mints`
+    pub include_mints: Vec<Pubkey>,
+    /// Count records of every reserve liquidity mint but these
+    pub exclude_mints: Vec<Pubkey>,
+    /// Only count records of these [`TransactionType::name`]s
+    pub instruction_types: Vec<&'static str>,
+}
+
+impl KaminoTransaction {
+    /// Whether the instruction was invoked through CPI rather than at the top level
+    pub fn is_cpi(&self) -> bool {
+        self.inner_index.is_some()
+    }
+}
+
+impl AccountFilter {
+    pub fn is_empty(&self) -> bool {
+        self.reserves.is_empty()
+            && self.markets.is_empty()
+            && self.include_mints.is_empty()
+            && self.exclude_mints.is_empty()
+            && self.instruction_types.is_empty()
+    }
+
+    /// Whether records of the given [`TransactionType::name`] pass the instruction type list
+    pub fn matches_type(&self, name: &str) -> bool {
+        self.instruction_types.is_empty() || self.instruction_types.contains(&name)
+    }
+
+    /// Whether a record of `reserve_token`, once resolved, passes the mint lists. Records not keyed
+    /// by a liquidity mint, such as refreshes or collateral movements keyed by reserve, only pass
+    /// without an include list.
+    pub fn matches_mint(&self, reserve_token: Option<&Pubkey>) -> bool {
+        if !self.include_mints.is_empty() {
+            return reserve_token.is_some_and(|mint| self.include_mints.contains(mint));
+        }
+        reserve_token.is_none_or(|mint| !self.exclude_mints.contains(mint))
+    }
+
+    /// Whether `instruction`, with its indexes resolved against `all_accounts`, passes the filter
+    pub fn matches(&self, instruction: &CompiledInstruction, all_accounts: &[Pubkey]) -> bool {
+        let touches = |wanted: &[Pubkey]| {
+            wanted.is_empty()
+                || instruction
+                    .accounts
+                    .iter()
+                    .filter_map(|&index| all_accounts.get(index as usize))
+                    .any(|account| wanted.contains(account))
+        };
+        touches(&self.reserves) && touches(&self.markets)
+    }
+}
+
+/// Expands a v0 message's account keys the way the runtime indexes them: static keys, then the
+/// writable addresses of every lookup table in lookup order, then the readonly addresses of every
+/// table in lookup order. Writable and readonly lookups are not interleaved per table.
+///
+/// Returns `None` when a table is missing from `lookup_tables` or an index is out of range, since
+/// every later address would shift onto the wrong index; out-of-range indexes are logged with
+/// their table.
+pub fn resolve_account_keys(message: &v0::Message, lookup_tables: &HashMap<Pubkey, Vec<Pubkey>>) -> Option<Vec<Pubkey>> {
+    let mut writable = Vec::new();
+    let mut readonly = Vec::new();
+    for lookup in &message.address_table_lookups {
+        let addresses = lookup_tables.get(&lookup.account_key)?;
+        let address = |index: u8, kind: &str| {
+            let address = addresses.get(index as usize).copied();
+            if address.is_none() {
+                warn!(
+                    table = %lookup.account_key, index, table_len = addresses.len(),
+                    "{} lookup table index out of range", kind
+                );
+            }
+            address
+        };
+        for &index in &lookup.writable_indexes {
+            writable.push(address(index, "Writable")?);
+        }
+        for &index in &lookup.readonly_indexes {
+            readonly.push(address(index, "Readonly")?);
+        }
+    }
+
+    let mut all_accounts = message.account_keys.clone();
+    all_accounts.extend(writable);
+    all_accounts.extend(readonly);
+    Some(all_accounts)
+}
+
+/// Returns one record per Kamino Lend instruction found in `tx`, resolving account indexes
+/// against `all_accounts` (static keys followed by lookup table addresses). Instructions of other
+/// programs are ignored even when their data starts with a Kamino discriminator.
+pub fn parse_transaction(
+    tx: &VersionedTransaction,
+    slot: u64,
+    block_time: Option<i64>,
+    all_accounts: &[Pubkey],
+    program_id: &Pubkey,
+) -> Vec<KaminoTransaction> {
+    let instructions = tx.message.instructions().iter().enumerate().map(|(index, instruction)| (index, None, instruction));
+    parse_instructions(tx, slot, block_time, instructions, all_accounts, program_id)
+}
+
+/// Like [`parse_transaction`], but for the CPI instructions recorded in the transaction's meta:
+/// `inner_instructions` pairs each top-level instruction index with the instructions it invoked,
+/// in order. Only instructions invoking `program_id` directly are considered, and their records
+/// carry an `inner_index`.
+pub fn parse_inner_instructions(
+    tx: &VersionedTransaction,
+    slot: u64,
+    block_time: Option<i64>,
+    inner_instructions: &[(usize, Vec<CompiledInstruction>)],
+    all_accounts: &[Pubkey],
+    program_id: &Pubkey,
+) -> Vec<KaminoTransaction> {
+    let instructions = inner_instructions
+        .iter()
+        .flat_map(|(index, inner)| inner.iter().enumerate().map(move |(inner_index, instruction)| (*index, Some(inner_index), instruction)));
+    parse_instructions(tx, slot, block_time, instructions, all_accounts, program_id)
+}
+
+fn parse_instructions<'a>(
+    tx: &VersionedTransaction,
+    slot: u64,
+    block_time: Option<i64>,
+    instructions: impl Iterator<Item = (usize, Option<usize>, &'a CompiledInstruction)>,
+    all_accounts: &[Pubkey],
+    program_id: &Pubkey,
+) -> Vec<KaminoTransaction> {
+    let mut records = Vec::new();
+    // Flash borrows awaiting their repay, per mint, in instruction order
+    let mut pending_flash_borrows: HashMap<Pubkey, VecDeque<u64>> = HashMap::new();
+    let mut push = |(instruction_index, inner_index, lending_market), transaction_type, reserve_token| {
+        records.push(KaminoTransaction {
+            signature: tx.signatures[0].to_string(),
+            slot,
+            block_time,
+            transaction_type,
+            reserve_token,
+            instruction_index,
+            inner_index,
+            failed: false,
+            lending_market,
+        })
+    };
+
+    // Other programs' data can start with the same bytes, e.g. an aggregator instruction wrapping
+    // a flash loan. CPI instructions index every account, so the program they invoke can come
+    // from a lookup table
+    let instructions = instructions.filter(|(_, inner_index, instruction)| match inner_index {
+        None => invoked_program_id(&tx.message, instruction) == Some(program_id),
+        Some(_) => all_accounts.get(instruction.program_id_index as usize) == Some(program_id),
+    });
+    for (instruction_index, inner_index, instruction) in instructions {
+        let lending_market_position = if instruction.data.starts_with(&REDEEM_RESERVE_COLLATERAL_DATA_SIG) {
+            REDEEM_RESERVE_COLLATERAL_LENDING_MARKET_ACCOUNT_KEY
+        } else if instruction.data.starts_with(&REFRESH_RESERVE_DATA_SIG) {
+            REFRESH_RESERVE_LENDING_MARKET_ACCOUNT_KEY
+        } else if instruction.data.starts_with(&REFRESH_OBLIGATION_DATA_SIG) {
+            REFRESH_OBLIGATION_LENDING_MARKET_ACCOUNT_KEY
+        } else {
+            LENDING_MARKET_ACCOUNT_KEY
+        };
+        let lending_market = instruction_name(&instruction.data)
+            .and_then(|_| instruction_account(instruction, lending_market_position, all_accounts));
+        let origin = (instruction_index, inner_index, lending_market);
+        // A layout change in a program upgrade must not panic the parser, so out-of-bounds
+        // accounts skip the instruction instead
+        let account = |account_position: usize| {
+            let account = instruction_account(instruction, account_position, all_accounts);
+            if account.is_none() {
+                debug!(
+                    signature = %tx.signatures[0], instruction_index, ?inner_index,
+                    "Account {} out of bounds; skipping instruction", account_position
+                );
+            }
+            account
+        };
+        // Likewise truncated data: a known discriminator without its amount is skipped before
+        // any decoding, while shorter unknown data is still recorded for --dump-unknown
+        if instruction.data.len() < MIN_INSTRUCTION_DATA_LEN
+            && instruction_name(&instruction.data).is_some()
+            && !matches_any(&instruction.data, &REFRESH_DATA_SIGS)
+        {
+            debug!(
+                signature = %tx.signatures[0], instruction_index, ?inner_index,
+                "Instruction data is {} bytes, too short for its amount; skipping instruction", instruction.data.len()
+            );
+            continue;
+        }
+        if instruction.data.starts_with(&FLASH_LOAN_DATA_BORROW_SIG) {
+            let Some(reserve_token) = account(FLASH_LOAN_TOKEN_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            let Some(amount) = read_u64_le(&instruction.data, AMOUNT_OFFSET) else {
+                continue;
+            };
+            let Some(&initiator) = tx.message.static_account_keys().first() else {
+                continue;
+            };
+
+            pending_flash_borrows.entry(reserve_token).or_default().push_back(amount);
+            push(origin, TransactionType::FlashBorrow { amount, initiator }, Some(reserve_token));
+        } else if instruction.data.starts_with(&FLASH_LOAN_REPAY_SIG) {
+            let Some(reserve_token) = account(FLASH_LOAN_REPAY_TOKEN_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            let Some(amount) = read_u64_le(&instruction.data, AMOUNT_OFFSET) else {
+                continue;
+            };
+
+            let fee = pending_flash_borrows
+                .get_mut(&reserve_token)
+                .and_then(|borrows| borrows.pop_front())
+                .map(|borrowed| amount.saturating_sub(borrowed));
+            push(origin, TransactionType::FlashRepay { amount, fee }, Some(reserve_token));
+        } else if matches_any(&instruction.data, &BORROW_OBLIGATION_DATA_SIGS) {
+            let Some(owner) = account(BORROW_OBLIGATION_OWNER_ACCOUNT_KEY) else {
+                continue;
+            };
+            let Some(obligation) = account(BORROW_OBLIGATION_OBLIGATION_ACCOUNT_KEY) else {
+                continue;
+            };
+            let Some(reserve_token) = account(BORROW_OBLIGATION_TOKEN_ACCOUNT_KEY) else {
+                continue;
+            };
+            let referrer = instruction_account(instruction, BORROW_OBLIGATION_REFERRER_ACCOUNT_KEY, all_accounts)
+                .filter(|referrer| referrer != program_id && *referrer != Pubkey::default());
+
+            let Some(amount) = read_u64_le(&instruction.data, AMOUNT_OFFSET) else {
+                continue;
+            };
+
+            push(origin, TransactionType::Borrow { amount, obligation, owner, referrer }, Some(reserve_token));
+        } else if matches_any(&instruction.data, &REPAY_OBLIGATION_DATA_SIGS) {
+            let Some(obligation) = account(REPAY_OBLIGATION_OBLIGATION_ACCOUNT_KEY) else {
+                continue;
+            };
+            let Some(reserve) = account(REPAY_OBLIGATION_RESERVE_ACCOUNT_KEY) else {
+                continue;
+            };
+            let Some(reserve_token) = account(REPAY_OBLIGATION_TOKEN_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            let Some(amount) = read_u64_le(&instruction.data, AMOUNT_OFFSET) else {
+                continue;
+            };
+
+            push(origin, TransactionType::Repay { amount, obligation, reserve, split: None }, Some(reserve_token));
+        } else if instruction.data.starts_with(&DEPOSIT_RESERVE_LIQUIDITY_DATA_SIG) {
+            let Some(reserve_token) = account(DEPOSIT_RESERVE_LIQUIDITY_TOKEN_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            let Some(amount) = read_u64_le(&instruction.data, AMOUNT_OFFSET) else {
+                continue;
+            };
+
+            push(origin, TransactionType::Deposit { amount }, Some(reserve_token));
+        } else if matches_any(&instruction.data, &DEPOSIT_OBLIGATION_COLLATERAL_DATA_SIGS) {
+            let Some(obligation) = account(DEPOSIT_OBLIGATION_COLLATERAL_OBLIGATION_ACCOUNT_KEY) else {
+                continue;
+            };
+            let Some(reserve) = account(DEPOSIT_OBLIGATION_COLLATERAL_RESERVE_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            let Some(amount) = read_u64_le(&instruction.data, AMOUNT_OFFSET) else {
+                continue;
+            };
+
+            push(origin, TransactionType::DepositCollateral { amount, obligation, reserve }, Some(reserve));
+        } else if matches_any(&instruction.data, &DEPOSIT_AND_COLLATERALIZE_DATA_SIGS) {
+            let Some(obligation) = account(DEPOSIT_AND_COLLATERALIZE_OBLIGATION_ACCOUNT_KEY) else {
+                continue;
+            };
+            let Some(reserve) = account(DEPOSIT_AND_COLLATERALIZE_RESERVE_ACCOUNT_KEY) else {
+                continue;
+            };
+            let Some(reserve_token) = account(DEPOSIT_AND_COLLATERALIZE_TOKEN_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            let Some(amount) = read_u64_le(&instruction.data, AMOUNT_OFFSET) else {
+                continue;
+            };
+
+            push(origin, TransactionType::DepositAndCollateralize { amount, obligation, reserve }, Some(reserve_token));
+        } else if matches_any(&instruction.data, &WITHDRAW_OBLIGATION_COLLATERAL_DATA_SIGS) {
+            let Some(obligation) = account(WITHDRAW_OBLIGATION_COLLATERAL_OBLIGATION_ACCOUNT_KEY) else {
+                continue;
+            };
+            let Some(reserve) = account(WITHDRAW_OBLIGATION_COLLATERAL_RESERVE_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            let Some(amount) = read_u64_le(&instruction.data, AMOUNT_OFFSET) else {
+                continue;
+            };
+
+            push(origin, TransactionType::Withdraw { amount, obligation }, Some(reserve));
+        } else if instruction.data.starts_with(&REDEEM_RESERVE_COLLATERAL_DATA_SIG) {
+            let Some(reserve_token) = account(REDEEM_RESERVE_COLLATERAL_TOKEN_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            let Some(amount) = read_u64_le(&instruction.data, AMOUNT_OFFSET) else {
+                continue;
+            };
+
+            push(origin, TransactionType::Redeem { amount }, Some(reserve_token));
+        } else if matches_any(&instruction.data, &LIQUIDATE_OBLIGATION_DATA_SIGS) {
+            let Some(liquidator) = account(LIQUIDATE_OBLIGATION_LIQUIDATOR_ACCOUNT_KEY) else {
+                continue;
+            };
+            let Some(reserve_token) = account(LIQUIDATE_OBLIGATION_REPAY_TOKEN_ACCOUNT_KEY) else {
+                continue;
+            };
+            let Some(collateral_mint) = account(LIQUIDATE_OBLIGATION_WITHDRAW_TOKEN_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            let (Some(liquidated_amount), Some(collateral_seized)) =
+                (read_u64_le(&instruction.data, AMOUNT_OFFSET), read_u64_le(&instruction.data, LIQUIDATE_OBLIGATION_MIN_RECEIVED_OFFSET))
+            else {
+                continue;
+            };
+
+            push(
+                origin,
+                TransactionType::Liquidate { liquidated_amount, collateral_seized, liquidator, collateral_mint },
+                Some(reserve_token),
+            );
+        } else if instruction.data.starts_with(&REFRESH_RESERVE_DATA_SIG) {
+            let Some(reserve) = account(REFRESH_RESERVE_RESERVE_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            push(origin, TransactionType::RefreshReserve { reserve }, None);
+        } else if instruction.data.starts_with(&REFRESH_OBLIGATION_DATA_SIG) {
+            let Some(obligation) = account(REFRESH_OBLIGATION_OBLIGATION_ACCOUNT_KEY) else {
+                continue;
+            };
+
+            push(origin, TransactionType::RefreshObligation { obligation }, None);
+        } else {
+            let discriminator = discriminator(&instruction.data);
+            push(origin, TransactionType::Unknown { discriminator }, None);
+        }
+    }
+
+    records
+}
+
+/// Program a top-level instruction of `message` invokes. Per the v0 spec program ids are always
+/// static keys, never lookup table addresses, so this needs no lookup tables; `None` when the
+/// index points past the static keys.
+pub fn invoked_program_id<'a>(message: &'a VersionedMessage, instruction: &CompiledInstruction) -> Option<&'a Pubkey> {
+    message.static_account_keys().get(instruction.program_id_index as usize)
+}
+
+/// Index and discriminator of every top-level instruction invoking `program_id`, without
+/// resolving lookup tables, see [`invoked_program_id`]. Shorter data is zero-padded.
+pub fn program_instructions(tx: &VersionedTransaction, program_id: &Pubkey) -> Vec<(usize, [u8; 8])> {
+    tx.message
+        .instructions()
+        .iter()
+        .enumerate()
+        .filter(|(_, instruction)| invoked_program_id(&tx.message, instruction) == Some(program_id))
+        .map(|(instruction_index, instruction)| (instruction_index, discriminator(&instruction.data)))
+        .collect()
+}
+
+/// Address of the instruction's account at `position`, or `None` when the instruction has fewer
+/// accounts or points past the transaction's account list
+pub fn instruction_account(instruction: &CompiledInstruction, position: usize, all_accounts: &[Pubkey]) -> Option<Pubkey> {
+    let index = *instruction.accounts.get(position)?;
+    all_accounts.get(usize::from(index)).copied()
+}
+
+/// Whether instruction data starts with any of one instruction's variant discriminators
+fn matches_any(data: &[u8], discriminators: &[[u8; 8]]) -> bool {
+    discriminators.iter().any(|discriminator| data.starts_with(discriminator))
+}
+
+/// First 8 bytes of instruction data, zero-padded when shorter
+fn discriminator(data: &[u8]) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    let len = data.len().min(8);
+    discriminator[..len].copy_from_slice(&data[..len]);
+    discriminator
+}
+
+/// Reads a little-endian u64 at `offset`, or `None` when `data` is too short
+pub fn read_u64_le(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// Lowercase hex encoding of raw instruction bytes
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
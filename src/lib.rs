@@ -0,0 +1,900 @@
+//! Kamino Lend transaction parsing.
+//!
+//! [`fetch_and_parse`] pages through the program's recent signatures, decodes every successful
+//! transaction and aggregates the matched instructions into a [`ParseSummary`];
+//! [`fetch_and_parse_slots`] does the same for every block in a slot range. Their `_with` variants
+//! also hand each record to a callback as soon as it is parsed, and [`parse_stream`] yields the
+//! records as a stream instead, without keeping a summary; both are built on the transactions of
+//! [`scan_transactions`], which [`summarize_scan`] folds into the summary. The decoding in
+//! [`instruction`] works on already-fetched transactions and can be used on its own.
+
+pub mod cache;
+pub mod columnar;
+pub mod db;
+pub mod instruction;
+pub mod market;
+pub mod metrics;
+pub mod mint;
+pub mod obligation;
+pub mod price;
+pub mod reserve;
+pub mod rpc;
+pub mod state;
+pub mod summary;
+pub mod watch;
+
+pub use instruction::{parse_inner_instructions, parse_transaction, program_instructions, resolve_account_keys, AccountFilter, KaminoTransaction, TransactionType};
+pub use mint::MintRegistry;
+pub use rpc::{resolve_accounts, LookupCache};
+pub use summary::{AmountStats, BorrowMix, BorrowRate, FeeRate, MintFlow, MintOrder, ObligationActivity, ParseSummary, Sample, SummaryComparison, TimeBucket};
+
+use anyhow::{anyhow, bail, Context, Result};
+use futures::stream::{self, Stream, StreamExt};
+use futures::future::Either;
+use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
+use rpc::{fetch_signatures, fetch_transaction, history_commitment, is_skipped_slot, retry_rpc};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcBlockConfig, RpcTransactionConfig};
+use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::{legacy, MessageHeader, VersionedMessage};
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{TransactionVersion, VersionedTransaction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, EncodedTransactionWithStatusMeta, TransactionDetails,
+    UiInstruction, UiMessage, UiParsedInstruction, UiTransaction, UiTransactionEncoding, UiTransactionStatusMeta,
+};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+
+// Kamino Lend Program ID (same for mainnet and devnet)
+pub const KAMINO_LEND_PROGRAM_ID: &str = "KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD";
+
+/// Tracing target of the dumps of instructions the parser does not decode, enabled by
+/// `--dump-unknown`
+pub const UNKNOWN_INSTRUCTIONS_TARGET: &str = "kamino::unknown_instructions";
+
+/// Knobs for [`fetch_and_parse`]
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    /// Number of transactions fetched from the RPC concurrently
+    pub concurrency: NonZeroUsize,
+    /// Retries for transient RPC failures before a request is dropped
+    pub rpc_retries: u32,
+    /// Only fetch and parse this many of the most recent transactions, failed ones excluded
+    /// unless `include_failed` is set
+    pub max_transactions: Option<usize>,
+    /// Stop paging back through signature history at this slot (inclusive)
+    pub since_slot: Option<u64>,
+    /// Only keep instructions touching these reserves or markets
+    pub account_filter: AccountFilter,
+    /// Mint metadata known upfront, such as `--token-list` symbols; the summary starts from it
+    pub mints: MintRegistry,
+    /// Lookup tables known upfront, such as from the disk cache; the summary starts from them
+    pub lookup_tables: LookupCache,
+    /// Show a progress bar on stderr while transactions are fetched
+    pub progress_bar: bool,
+    /// Keep failed transactions; their instructions are counted as attempts but add no amounts
+    pub include_failed: bool,
+    /// Only keep signatures with a block time at or after this unix timestamp
+    pub since: Option<i64>,
+    /// Only keep signatures with a block time at or before this unix timestamp
+    pub until: Option<i64>,
+    /// Transaction encoding requested from the RPC: `Base64`, or `JsonParsed` to have the node
+    /// resolve lookup tables instead of fetching them
+    pub encoding: UiTransactionEncoding,
+    /// Only fetch a random sample of the signatures and extrapolate the summary's totals from it,
+    /// see [`ParseSummary::extrapolate`]. Only used by [`fetch_and_parse`].
+    pub sample: Option<Sample>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: NonZeroUsize::new(8).unwrap(),
+            rpc_retries: 4,
+            max_transactions: None,
+            since_slot: None,
+            account_filter: AccountFilter::default(),
+            mints: MintRegistry::default(),
+            lookup_tables: LookupCache::new(),
+            progress_bar: false,
+            include_failed: false,
+            since: None,
+            until: None,
+            encoding: UiTransactionEncoding::Base64,
+            sample: None,
+        }
+    }
+}
+
+/// Fetches the program's recent successful transactions (and failed ones with
+/// [`ParseOptions::include_failed`]) and parses them into a summary.
+pub async fn fetch_and_parse(client: &RpcClient, program_id: &Pubkey, opts: ParseOptions) -> Result<ParseSummary> {
+    fetch_and_parse_with(client, program_id, opts, |_, _| {}).await
+}
+
+/// [`fetch_and_parse`], calling `on_record` for each parsed instruction as soon as its
+/// transaction is decoded, with the summary so far (mint decimals included)
+pub async fn fetch_and_parse_with(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    opts: ParseOptions,
+    on_record: impl FnMut(&KaminoTransaction, &ParseSummary),
+) -> Result<ParseSummary> {
+    let scan = scan_transactions(client, program_id, &opts).await?;
+    Ok(summarize_scan(client, program_id, scan, on_record).await)
+}
+
+/// A finished signature scan, before its transactions are fetched, see [`scan_transactions`]
+pub struct Scan<S> {
+    /// The summary to fold the transactions into, with the scan's size and time window
+    pub summary: ParseSummary,
+    /// The sample fetched and the number of signatures it was drawn from, when sampling
+    pub sample: Option<(Sample, usize)>,
+    /// The scanned transactions, fetched as the stream is polled, in the scan's order
+    pub transactions: S,
+}
+
+/// Pages through the program's signatures like [`fetch_and_parse`] and returns the scan, whose
+/// transactions are only fetched as its stream is polled; [`summarize_scan`] folds them into the
+/// summary and [`parse_stream`] yields their records. Fails when the signatures cannot be
+/// fetched; Ctrl-C while paging returns an empty, interrupted scan.
+pub async fn scan_transactions<'a>(
+    client: &'a RpcClient,
+    program_id: &Pubkey,
+    opts: &ParseOptions,
+) -> Result<Scan<impl Stream<Item = FetchedTransaction> + Send + 'a>> {
+    info!(%program_id, "Fetching recent transactions for Kamino Lend program");
+    let config = transaction_config(client, opts);
+    let Some(signatures) = scan_signatures(client, program_id, opts).await? else {
+        return Ok(Scan {
+            summary: ParseSummary {
+                interrupted: true,
+                ..Default::default()
+            },
+            sample: None,
+            transactions: transaction_stream(client, Vec::new(), config, opts),
+        });
+    };
+
+    if signatures.is_empty() {
+        info!("No successful transactions found for program in the recent window");
+        return Ok(Scan { summary: ParseSummary::default(), sample: None, transactions: transaction_stream(client, signatures, config, opts) });
+    }
+
+    let mut summary = ParseSummary {
+        transactions_scanned: signatures.len(),
+        truncated_to: opts.max_transactions.filter(|max| signatures.len() >= *max),
+        ..ParseSummary::for_options(opts)
+    };
+
+    // Signatures come newest first; very recent slots may not have a block time yet, so the
+    // window runs between the newest and oldest signatures that have one. A requested window
+    // takes precedence over the signature times.
+    let newest_block_time = signatures.iter().find_map(|sig_info| sig_info.block_time);
+    let oldest_block_time = signatures.iter().rev().find_map(|sig_info| sig_info.block_time);
+    summary.block_time_window = match (opts.since.or(oldest_block_time), opts.until.or(newest_block_time)) {
+        (Some(oldest), Some(newest)) => Some((oldest, newest)),
+        _ => {
+            warn!("No scanned signature has a block time; skipping the time window");
+            None
+        }
+    };
+
+    let population = signatures.len();
+    let signatures = match opts.sample {
+        Some(sample) => {
+            let sampled = sample.select(signatures);
+            info!(seed = sample.seed, "Sampled {} of {} transactions", sampled.len(), population);
+            summary.transactions_scanned = sampled.len();
+            sampled
+        }
+        None => signatures,
+    };
+
+    info!("Fetching {} transactions with concurrency {}", signatures.len(), opts.concurrency);
+    Ok(Scan {
+        summary,
+        sample: opts.sample.map(|sample| (sample, population)),
+        transactions: transaction_stream(client, signatures, config, opts),
+    })
+}
+
+/// Fetches and parses the transactions of `scan` into its summary, calling `on_record` as in
+/// [`fetch_and_parse_with`]. Ctrl-C stops early with the transactions fetched so far.
+pub async fn summarize_scan(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    scan: Scan<impl Stream<Item = FetchedTransaction>>,
+    mut on_record: impl FnMut(&KaminoTransaction, &ParseSummary),
+) -> ParseSummary {
+    let Scan { mut summary, sample, transactions } = scan;
+    let signature_count = summary.transactions_scanned;
+    // Each transaction is parsed as soon as it and the ones before it have arrived
+    let mut fetched_transactions = Box::pin(transactions.take_until(tokio::signal::ctrl_c()).enumerate());
+
+    let (mut legacy_count, mut v0_count, mut fetched_count) = (0, 0, 0);
+    while let Some((i, (sig_info, fetched))) = fetched_transactions.next().await {
+        fetched_count += 1;
+        debug!(signature = %sig_info.signature, "Processing transaction {}/{}", i + 1, signature_count);
+
+        let transaction = match fetched {
+            Ok(tx) => tx,
+            Err(e) => {
+                warn!(signature = %sig_info.signature, "Failed to get transaction: {}", e);
+                summary.fetch_errors.push(sig_info.signature.clone());
+                continue;
+            }
+        };
+        match transaction.transaction.version {
+            Some(TransactionVersion::Number(_)) => v0_count += 1,
+            _ => legacy_count += 1,
+        }
+
+        let parsed = parse_fetched_transaction(client, &transaction, &mut summary.lookup_tables, program_id, &summary.account_filter).await;
+        add_and_report(client, &mut summary, parsed, &mut on_record).await;
+    }
+    // Drops the stream, and with it the progress bar
+    drop(fetched_transactions);
+    if fetched_count < signature_count {
+        warn!("Interrupted; summarizing the {} of {} transactions fetched so far", fetched_count, signature_count);
+        summary.interrupted = true;
+        summary.transactions_scanned = fetched_count;
+    }
+
+    debug!(legacy = legacy_count, v0 = v0_count, "Transaction versions parsed");
+    if let Some((sample, population)) = sample {
+        summary.extrapolate(sample, population);
+    }
+
+    summary
+}
+
+/// The records of the transactions [`fetch_and_parse`] would scan, as a stream: transactions
+/// are fetched as the stream is polled, [`ParseOptions::concurrency`] at a time, so a slow
+/// consumer holds back the fetching instead of records piling up. Records come in the scan's
+/// order, newest transaction first, with no summary kept; transactions that cannot be fetched or
+/// parsed yield an error and the stream carries on. When the signatures cannot be fetched, the
+/// stream yields that error and ends. It ends after the last transaction, or early when dropped.
+/// Mint decimals are not resolved, see [`MintRegistry::resolve`].
+pub fn parse_stream<'a>(client: &'a RpcClient, program_id: &'a Pubkey, opts: ParseOptions) -> impl Stream<Item = Result<KaminoTransaction>> + 'a {
+    stream::once(async move {
+        let scan = match scan_transactions(client, program_id, &opts).await {
+            Ok(scan) => scan,
+            Err(e) => return Either::Left(stream::iter([Err(e)])),
+        };
+        let fetched = scan.transactions.boxed();
+        Either::Right(
+            stream::unfold((fetched, opts.lookup_tables, opts.account_filter), move |(mut fetched, mut lookup_tables, account_filter)| async move {
+                let (sig_info, fetched_transaction) = fetched.next().await?;
+                let records: Vec<Result<KaminoTransaction>> = match fetched_transaction {
+                    Ok(transaction) => match parse_fetched_transaction(client, &transaction, &mut lookup_tables, program_id, &account_filter).await {
+                        Ok(records) => records.into_iter().map(Ok).collect(),
+                        Err(SkippedTransaction::Undecodable { .. }) => vec![Err(anyhow!("transaction {} cannot be decoded", sig_info.signature))],
+                        Err(SkippedTransaction::UnresolvedLookupTables(unresolved)) => {
+                            vec![Err(unresolved.error.context(format!("cannot resolve the lookup tables of transaction {}", sig_info.signature)))]
+                        }
+                    },
+                    Err(e) => vec![Err(e.context(format!("failed to get transaction {}", sig_info.signature)))],
+                };
+                Some((stream::iter(records), (fetched, lookup_tables, account_filter)))
+            })
+            .flatten(),
+        )
+    })
+    .flatten()
+}
+
+/// Parses every transaction touching the program in the blocks `from_slot..=to_slot`, so results
+/// do not depend on how much signature history the RPC node keeps. Skipped slots are passed over;
+/// Ctrl-C stops early with the blocks fetched so far.
+pub async fn fetch_and_parse_slots(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    from_slot: u64,
+    to_slot: u64,
+    opts: ParseOptions,
+) -> Result<ParseSummary> {
+    fetch_and_parse_slots_with(client, program_id, from_slot, to_slot, opts, |_, _| {}).await
+}
+
+/// [`fetch_and_parse_slots`], calling `on_record` for each parsed instruction as in
+/// [`fetch_and_parse_with`]
+pub async fn fetch_and_parse_slots_with(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    from_slot: u64,
+    to_slot: u64,
+    opts: ParseOptions,
+    mut on_record: impl FnMut(&KaminoTransaction, &ParseSummary),
+) -> Result<ParseSummary> {
+    if from_slot > to_slot {
+        bail!("--from-slot {} is after --to-slot {}", from_slot, to_slot);
+    }
+    info!(%program_id, "Fetching blocks {} to {} for Kamino Lend program", from_slot, to_slot);
+    let config = RpcBlockConfig {
+        encoding: opts.encoding.into(),
+        transaction_details: TransactionDetails::Full.into(),
+        rewards: Some(false),
+        commitment: history_commitment(client).into(),
+        max_supported_transaction_version: Some(0),
+    };
+
+    let slot_count = to_slot - from_slot + 1;
+    let progress = progress_bar(&opts, slot_count, "slots");
+    // Blocks are large, so each one is parsed as soon as it arrives rather than collected first
+    let mut blocks = stream::iter(from_slot..=to_slot)
+        .map(|slot| async move { (slot, retry_rpc(opts.rpc_retries, || client.get_block_with_config(slot, config)).await) })
+        .buffered(opts.concurrency.get())
+        .inspect(|_| progress.inc(1))
+        .take_until(tokio::signal::ctrl_c())
+        .boxed();
+
+    let mut summary = ParseSummary::for_options(&opts);
+    let (mut blocks_fetched, mut skipped_slots) = (0, 0);
+    while let Some((slot, fetched)) = blocks.next().await {
+        blocks_fetched += 1;
+        let block = match fetched {
+            Ok(block) => block,
+            Err(e) if is_skipped_slot(&e) => {
+                debug!(slot, "Skipped slot: {}", e);
+                skipped_slots += 1;
+                continue;
+            }
+            Err(e) => {
+                warn!(slot, "Failed to get block: {}", e);
+                continue;
+            }
+        };
+        if let Some(block_time) = block.block_time {
+            summary.observe_block_time(block_time);
+        }
+
+        for transaction in block.transactions.unwrap_or_default() {
+            let failed = transaction.meta.as_ref().is_some_and(|meta| meta.err.is_some());
+            if (failed && !opts.include_failed) || !touches_program(&transaction, program_id) {
+                continue;
+            }
+            summary.transactions_scanned += 1;
+            let transaction = EncodedConfirmedTransactionWithStatusMeta {
+                slot,
+                transaction,
+                block_time: block.block_time,
+            };
+            let parsed = parse_fetched_transaction(client, &transaction, &mut summary.lookup_tables, program_id, &opts.account_filter).await;
+            add_and_report(client, &mut summary, parsed, &mut on_record).await;
+        }
+    }
+    progress.finish_and_clear();
+
+    if blocks_fetched < slot_count {
+        warn!("Interrupted; summarizing the {} of {} slots fetched so far", blocks_fetched, slot_count);
+        summary.interrupted = true;
+    }
+    info!(slots = blocks_fetched, skipped = skipped_slots, transactions = summary.transactions_scanned, "Scanned slot range");
+
+    Ok(summary)
+}
+
+/// Fetches and parses a single transaction, e.g. one found on an explorer, without enumerating
+/// signatures. Fails when the transaction cannot be found, does not invoke the program, or uses
+/// lookup tables that cannot be resolved. Failed transactions are parsed like successful ones.
+pub async fn fetch_and_parse_signature(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    signature: &Signature,
+    opts: ParseOptions,
+) -> Result<ParseSummary> {
+    let config = RpcTransactionConfig {
+        commitment: history_commitment(client).into(),
+        encoding: opts.encoding.into(),
+        max_supported_transaction_version: Some(0),
+    };
+    let transaction = retry_rpc(opts.rpc_retries, || client.get_transaction_with_config(signature, config))
+        .await
+        .with_context(|| format!("transaction {} not found on this cluster", signature))?;
+    if !touches_program(&transaction.transaction, program_id) {
+        bail!("transaction {} does not invoke program {}", signature, program_id);
+    }
+
+    let mut summary = ParseSummary {
+        transactions_scanned: 1,
+        block_time_window: transaction.block_time.map(|block_time| (block_time, block_time)),
+        ..ParseSummary::for_options(&opts)
+    };
+    let records = parse_fetched_transaction(client, &transaction, &mut summary.lookup_tables, program_id, &opts.account_filter)
+        .await
+        .map_err(|skipped| match skipped {
+            SkippedTransaction::Undecodable { .. } => anyhow!("transaction {} cannot be decoded", signature),
+            SkippedTransaction::UnresolvedLookupTables(unresolved) => {
+                unresolved.error.context("cannot resolve the transaction's lookup tables; try --encoding jsonparsed")
+            }
+        })?;
+    for record in records {
+        summary.add_record(record);
+    }
+    fill_mint_decimals(client, &mut summary).await;
+    Ok(summary)
+}
+
+/// Reads newline-delimited signatures, e.g. from an explorer export. Blank lines are ignored;
+/// lines that are not a signature are logged and skipped.
+pub fn read_signature_list(reader: impl BufRead) -> Result<Vec<Signature>> {
+    let mut signatures = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.context("failed to read the signature list")?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match Signature::from_str(line) {
+            Ok(signature) => signatures.push(signature),
+            Err(e) => warn!("Line {}: {:?} is not a signature ({}); skipping", i + 1, line, e),
+        }
+    }
+    Ok(signatures)
+}
+
+/// Fetches and parses the given transactions instead of the program's signature history, like
+/// [`fetch_and_parse_with`]. Transactions that do not invoke the program are skipped with a
+/// warning; failed ones are skipped unless [`ParseOptions::include_failed`] is set. The time
+/// window spans the block times of the transactions fetched. Ctrl-C stops early with the
+/// transactions fetched so far.
+pub async fn fetch_and_parse_signatures_with(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    signatures: Vec<Signature>,
+    opts: ParseOptions,
+    mut on_record: impl FnMut(&KaminoTransaction, &ParseSummary),
+) -> Result<ParseSummary> {
+    let signature_count = signatures.len();
+    info!(%program_id, "Fetching {} listed transactions with concurrency {}", signature_count, opts.concurrency);
+    let config = transaction_config(client, &opts);
+    let progress = progress_bar(&opts, signature_count as u64, "transactions");
+    // Listed signatures carry no slot, so a missing transaction is not waited for
+    let mut fetched_transactions = stream::iter(signatures)
+        .map(|signature| async move { (signature, fetch_transaction(client, &signature, None, config, opts.rpc_retries).await) })
+        .buffered(opts.concurrency.get())
+        .inspect(|_| progress.inc(1))
+        .take_until(tokio::signal::ctrl_c())
+        .boxed();
+
+    let mut summary = ParseSummary::for_options(&opts);
+    let mut fetched_count = 0;
+    while let Some((signature, fetched)) = fetched_transactions.next().await {
+        fetched_count += 1;
+        let transaction = match fetched {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                warn!(%signature, "Failed to get transaction: {}", e);
+                summary.transactions_scanned += 1;
+                summary.fetch_errors.push(signature.to_string());
+                continue;
+            }
+        };
+        let failed = transaction.transaction.meta.as_ref().is_some_and(|meta| meta.err.is_some());
+        if failed && !opts.include_failed {
+            debug!(%signature, "Skipping failed transaction");
+            continue;
+        }
+        if !touches_program(&transaction.transaction, program_id) {
+            warn!(%signature, "Transaction does not invoke program {}; skipping", program_id);
+            continue;
+        }
+        summary.transactions_scanned += 1;
+        if let Some(block_time) = transaction.block_time {
+            summary.observe_block_time(block_time);
+        }
+        let parsed = parse_fetched_transaction(client, &transaction, &mut summary.lookup_tables, program_id, &opts.account_filter).await;
+        add_and_report(client, &mut summary, parsed, &mut on_record).await;
+    }
+    progress.finish_and_clear();
+    if fetched_count < signature_count {
+        warn!("Interrupted; summarizing the {} of {} transactions fetched so far", fetched_count, signature_count);
+        summary.interrupted = true;
+    }
+
+    Ok(summary)
+}
+
+/// Adds one parsed transaction to the summary, resolves the decimals of its mints and passes its
+/// records to `on_record`
+async fn add_and_report(
+    client: &RpcClient,
+    summary: &mut ParseSummary,
+    parsed: Result<Vec<KaminoTransaction>, SkippedTransaction>,
+    on_record: &mut impl FnMut(&KaminoTransaction, &ParseSummary),
+) {
+    let first_new = summary.records.len();
+    summary.add_parsed(parsed);
+    resolve_record_mints(client, &mut summary.mints, &summary.records[first_new..]).await;
+    for record in &summary.records[first_new..] {
+        on_record(record, summary);
+    }
+}
+
+/// Whether a block's transaction invokes the program, directly or through CPI, from its static
+/// keys or the addresses it loaded from lookup tables
+fn touches_program(transaction: &EncodedTransactionWithStatusMeta, program_id: &Pubkey) -> bool {
+    if let EncodedTransaction::Json(tx) = &transaction.transaction {
+        return decode_json_parsed(tx).is_some_and(|(_, all_accounts)| all_accounts.contains(program_id));
+    }
+    let Some(tx) = transaction.transaction.decode() else {
+        return false;
+    };
+    if tx.message.static_account_keys().contains(program_id) {
+        return true;
+    }
+    let program_id = program_id.to_string();
+    match transaction.meta.as_ref().map(|meta| &meta.loaded_addresses) {
+        Some(OptionSerializer::Some(loaded)) => loaded.writable.contains(&program_id) || loaded.readonly.contains(&program_id),
+        _ => false,
+    }
+}
+
+/// Top-level program instructions of one transaction, for exploring discriminators
+#[derive(Clone, Debug)]
+pub struct InstructionListing {
+    pub signature: String,
+    pub slot: u64,
+    /// Instruction index and 8-byte discriminator of every instruction invoking the program
+    pub instructions: Vec<(usize, [u8; 8])>,
+}
+
+/// Fetches the same transactions as [`fetch_and_parse`] but only lists the discriminators of
+/// their program instructions, decoding no amounts and fetching no lookup tables. Stops early on
+/// Ctrl-C with what was fetched so far.
+pub async fn fetch_and_list_instructions(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    opts: ParseOptions,
+) -> Result<Vec<InstructionListing>> {
+    let Some(signatures) = scan_signatures(client, program_id, &opts).await? else {
+        return Ok(Vec::new());
+    };
+
+    info!("Fetching {} transactions with concurrency {}", signatures.len(), opts.concurrency);
+    let mut fetched_transactions = transaction_stream(client, signatures, transaction_config(client, &opts), &opts)
+        .take_until(tokio::signal::ctrl_c())
+        .boxed();
+    let mut listings = Vec::new();
+    while let Some((sig_info, fetched)) = fetched_transactions.next().await {
+        let transaction = match fetched {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                warn!(signature = %sig_info.signature, "Failed to get transaction: {}", e);
+                continue;
+            }
+        };
+        let Some(tx) = transaction.transaction.transaction.decode() else {
+            warn!(signature = %sig_info.signature, "Failed to decode transaction");
+            continue;
+        };
+        listings.push(InstructionListing {
+            signature: sig_info.signature,
+            slot: transaction.slot,
+            instructions: program_instructions(&tx, program_id),
+        });
+    }
+    Ok(listings)
+}
+
+/// Pages through the program's signatures and drops failed ones unless
+/// [`ParseOptions::include_failed`] is set. `None` when interrupted by Ctrl-C.
+async fn scan_signatures(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    opts: &ParseOptions,
+) -> Result<Option<Vec<RpcConfirmedTransactionStatusWithSignature>>> {
+    let all_signatures = tokio::select! {
+        fetched = fetch_signatures(client, program_id, opts) => fetched.context("failed to fetch the program's signatures")?,
+        _ = tokio::signal::ctrl_c() => {
+            warn!("Interrupted while fetching signatures");
+            return Ok(None);
+        }
+    };
+    info!("Found {} total recent transactions", all_signatures.len());
+    // Filter out failed transactions - only keep successful ones unless asked for
+    let sucsigs: Vec<_> = all_signatures
+        .iter()
+        .filter(|sig_info| opts.include_failed || sig_info.err.is_none())
+        .cloned()
+        .collect::<Vec<_>>();
+    let failed_count = all_signatures.iter().filter(|sig_info| sig_info.err.is_some()).count();
+
+    if opts.include_failed {
+        info!(total = sucsigs.len(), failed = failed_count, "Keeping failed transactions");
+    } else {
+        info!(successful = sucsigs.len(), failed = failed_count, "Filtered out failed transactions");
+    }
+
+    for sig_info in &sucsigs {
+        debug!(signature = %sig_info.signature, slot = sig_info.slot, block_time = ?sig_info.block_time, failed = sig_info.err.is_some(), "Transaction");
+    }
+
+    Ok(Some(sucsigs))
+}
+
+/// A scanned signature and its transaction, or why it could not be fetched
+pub type FetchedTransaction = (RpcConfirmedTransactionStatusWithSignature, Result<EncodedConfirmedTransactionWithStatusMeta>);
+
+/// Fetches the transactions of `signatures` as the stream is polled, up to
+/// [`ParseOptions::concurrency`] at a time, yielding each with its signature in the given order.
+/// The progress bar, when enabled, is cleared once the stream is dropped.
+fn transaction_stream<'a>(
+    client: &'a RpcClient,
+    signatures: Vec<RpcConfirmedTransactionStatusWithSignature>,
+    config: RpcTransactionConfig,
+    opts: &ParseOptions,
+) -> impl Stream<Item = FetchedTransaction> + Send + 'a {
+    let rpc_retries = opts.rpc_retries;
+    let progress = progress_bar(opts, signatures.len() as u64, "transactions").with_finish(ProgressFinish::AndClear);
+    stream::iter(signatures)
+        .map(move |sig_info| async move {
+            let fetched = async {
+                let signature = Signature::from_str(&sig_info.signature)?;
+                let transaction = fetch_transaction(client, &signature, Some(sig_info.slot), config, rpc_retries).await?;
+                Ok::<_, anyhow::Error>(transaction)
+            }
+            .await;
+            (sig_info, fetched)
+        })
+        .buffered(opts.concurrency.get())
+        .inspect(move |_| progress.inc(1))
+}
+
+fn transaction_config(client: &RpcClient, opts: &ParseOptions) -> RpcTransactionConfig {
+    RpcTransactionConfig {
+        commitment: history_commitment(client).into(),
+        encoding: opts.encoding.into(),
+        max_supported_transaction_version: Some(0),
+    }
+}
+
+/// Progress bar on stderr counting `unit`s, hidden unless [`ParseOptions::progress_bar`] is set
+fn progress_bar(opts: &ParseOptions, len: u64, unit: &str) -> ProgressBar {
+    if !opts.progress_bar {
+        return ProgressBar::hidden();
+    }
+    ProgressBar::new(len).with_style(
+        ProgressStyle::with_template(&format!("{{bar:40}} {{pos}}/{{len}} {}, {{per_sec}}, ETA {{eta}}", unit))
+            .expect("progress bar template is valid"),
+    )
+}
+
+/// Why a fetched transaction was skipped instead of parsed
+#[derive(Debug)]
+pub enum SkippedTransaction {
+    /// Its encoding could not be decoded into a transaction, e.g. corrupt or unsupported data
+    Undecodable { slot: u64 },
+    UnresolvedLookupTables(UnresolvedLookupTables),
+}
+
+/// A v0 transaction skipped because its lookup tables could not all be resolved
+#[derive(Debug)]
+pub struct UnresolvedLookupTables {
+    pub signature: String,
+    /// Tables the transaction uses that did not make it into the cache
+    pub tables: Vec<Pubkey>,
+    pub error: anyhow::Error,
+}
+
+/// Resolves a fetched transaction's lookup tables, fetching and caching any not seen yet, and
+/// parses its Kamino Lend instructions. Legacy transactions are parsed against their static keys;
+/// v0 transactions whose lookup tables cannot be resolved, and transactions that cannot be
+/// decoded, are skipped with an error.
+pub async fn parse_fetched_transaction(
+    client: &RpcClient,
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    lookup_table_cache: &mut LookupCache,
+    program_id: &Pubkey,
+    account_filter: &AccountFilter,
+) -> Result<Vec<KaminoTransaction>, SkippedTransaction> {
+    // jsonParsed transactions list every account, lookup table addresses included
+    if let EncodedTransaction::Json(tx) = &transaction.transaction.transaction {
+        return Ok(match decode_json_parsed(tx) {
+            Some((versioned_tx, all_accounts)) => {
+                parse_resolved_transaction(transaction, &versioned_tx, &all_accounts, program_id, account_filter)
+            }
+            None => {
+                warn!(signature = ?tx.signatures.first(), "Skipping transaction without parsed account keys");
+                Vec::new()
+            }
+        });
+    }
+    let Some(versioned_tx) = transaction.transaction.transaction.decode() else {
+        warn!(slot = transaction.slot, "Skipping transaction that cannot be decoded");
+        return Err(SkippedTransaction::Undecodable { slot: transaction.slot });
+    };
+
+    // Legacy messages have no lookup tables, so their static keys are all the accounts
+    let msg = match &versioned_tx.message {
+        VersionedMessage::V0(msg) => msg,
+        VersionedMessage::Legacy(msg) => {
+            let all_accounts = msg.account_keys.clone();
+            return Ok(parse_resolved_transaction(transaction, &versioned_tx, &all_accounts, program_id, account_filter));
+        }
+    };
+    let all_accounts = match resolve_accounts(client, msg, lookup_table_cache).await {
+        Ok(all_accounts) => all_accounts,
+        Err(error) => {
+            warn!(signature = %versioned_tx.signatures[0], "Skipping transaction: {:#}", error);
+            return Err(SkippedTransaction::UnresolvedLookupTables(UnresolvedLookupTables {
+                signature: versioned_tx.signatures[0].to_string(),
+                tables: msg
+                    .address_table_lookups
+                    .iter()
+                    .map(|lookup| lookup.account_key)
+                    .filter(|table| !lookup_table_cache.contains_key(table))
+                    .collect(),
+                error,
+            }));
+        }
+    };
+
+    Ok(parse_resolved_transaction(transaction, &versioned_tx, &all_accounts, program_id, account_filter))
+}
+
+/// Parses a decoded transaction whose account keys are already resolved, applying the account
+/// filter and the transaction's failure status
+fn parse_resolved_transaction(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    versioned_tx: &VersionedTransaction,
+    all_accounts: &[Pubkey],
+    program_id: &Pubkey,
+    account_filter: &AccountFilter,
+) -> Vec<KaminoTransaction> {
+    let (slot, block_time) = (transaction.slot, transaction.block_time);
+    let meta = transaction.transaction.meta.as_ref();
+    let failed = meta.is_some_and(|meta| meta.err.is_some());
+    let inner_instructions = meta.map(|meta| inner_instructions(meta, all_accounts)).unwrap_or_default();
+
+    let mut records = parse_transaction(versioned_tx, slot, block_time, all_accounts, program_id);
+    records.extend(parse_inner_instructions(versioned_tx, slot, block_time, &inner_instructions, all_accounts, program_id));
+    records.sort_by_key(|record| (record.instruction_index, record.inner_index));
+
+    let instructions = versioned_tx.message.instructions();
+    let instruction = |record: &KaminoTransaction| match record.inner_index {
+        Some(inner_index) => inner_instructions
+            .iter()
+            .find(|(index, _)| *index == record.instruction_index)
+            .and_then(|(_, inner)| inner.get(inner_index)),
+        None => instructions.get(record.instruction_index),
+    };
+    records.retain(|record| {
+        instruction(record).is_some_and(|instruction| account_filter.matches(instruction, all_accounts))
+            && account_filter.matches_mint(record.reserve_token.as_ref())
+            && account_filter.matches_type(record.transaction_type.name())
+    });
+    for record in &mut records {
+        record.failed = failed;
+        if let (TransactionType::Unknown { discriminator }, Some(instruction)) = (record.transaction_type, instruction(record)) {
+            dump_unknown_instruction(record, &discriminator, instruction, all_accounts);
+        }
+    }
+    records
+}
+
+/// Logs an instruction the parser does not decode with its full data and resolved accounts, under
+/// the [`UNKNOWN_INSTRUCTIONS_TARGET`] target at debug level
+fn dump_unknown_instruction(record: &KaminoTransaction, discriminator: &[u8; 8], instruction: &CompiledInstruction, all_accounts: &[Pubkey]) {
+    debug!(
+        target: UNKNOWN_INSTRUCTIONS_TARGET,
+        signature = %record.signature,
+        instruction_index = record.instruction_index,
+        inner_index = ?record.inner_index,
+        data = %crate::instruction::hex(&instruction.data),
+        accounts = ?instruction
+            .accounts
+            .iter()
+            .map(|&index| all_accounts.get(index as usize).map_or_else(|| format!("<index {} out of range>", index), Pubkey::to_string))
+            .collect::<Vec<_>>(),
+        "Unknown Kamino Lend instruction {}",
+        crate::instruction::hex(discriminator)
+    );
+}
+
+/// CPI instructions from the transaction meta, keyed by the top-level instruction that invoked
+/// them. Inner instruction data is base58 in the RPC response whatever the transaction encoding.
+fn inner_instructions(meta: &UiTransactionStatusMeta, all_accounts: &[Pubkey]) -> Vec<(usize, Vec<CompiledInstruction>)> {
+    let OptionSerializer::Some(inner_instructions) = &meta.inner_instructions else {
+        return Vec::new();
+    };
+    let account_indexes = account_indexes(all_accounts.iter().map(Pubkey::to_string));
+    inner_instructions
+        .iter()
+        .map(|inner| {
+            let instructions = inner
+                .instructions
+                .iter()
+                .filter_map(|instruction| compile_ui_instruction(instruction, &account_indexes))
+                .collect();
+            (inner.index as usize, instructions)
+        })
+        .collect()
+}
+
+/// Rebuilds a `jsonParsed` transaction as a legacy message over its full account list, which the
+/// node has already resolved, so it parses like any other transaction without fetching lookup
+/// tables. Also returns that account list.
+fn decode_json_parsed(tx: &UiTransaction) -> Option<(VersionedTransaction, Vec<Pubkey>)> {
+    let UiMessage::Parsed(message) = &tx.message else {
+        return None;
+    };
+    let all_accounts: Vec<Pubkey> =
+        message.account_keys.iter().map(|account| Pubkey::from_str(&account.pubkey).ok()).collect::<Option<_>>()?;
+    let account_indexes = account_indexes(message.account_keys.iter().map(|account| account.pubkey.clone()));
+    let instructions = message
+        .instructions
+        .iter()
+        .map(|instruction| compile_ui_instruction(instruction, &account_indexes))
+        .collect::<Option<_>>()?;
+    let signatures = tx.signatures.iter().map(|signature| Signature::from_str(signature).ok()).collect::<Option<_>>()?;
+    let versioned_tx = VersionedTransaction {
+        signatures,
+        message: VersionedMessage::Legacy(legacy::Message {
+            header: MessageHeader::default(),
+            account_keys: all_accounts.clone(),
+            recent_blockhash: Hash::from_str(&message.recent_blockhash).unwrap_or_default(),
+            instructions,
+        }),
+    };
+    Some((versioned_tx, all_accounts))
+}
+
+/// Position of every account address in a transaction's account list
+fn account_indexes(addresses: impl Iterator<Item = String>) -> HashMap<String, u8> {
+    addresses.enumerate().map(|(index, address)| (address, index as u8)).collect()
+}
+
+/// An RPC instruction as a compiled one over the transaction's account list. Instructions the
+/// node decoded itself, such as SPL Token transfers, are never Kamino Lend ones and keep only
+/// their program so the positions of the others stay right.
+fn compile_ui_instruction(instruction: &UiInstruction, account_indexes: &HashMap<String, u8>) -> Option<CompiledInstruction> {
+    match instruction {
+        UiInstruction::Compiled(compiled) => Some(CompiledInstruction {
+            program_id_index: compiled.program_id_index,
+            accounts: compiled.accounts.clone(),
+            data: bs58::decode(&compiled.data).into_vec().ok()?,
+        }),
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(decoded)) => Some(CompiledInstruction {
+            program_id_index: *account_indexes.get(&decoded.program_id)?,
+            accounts: decoded.accounts.iter().map(|account| account_indexes.get(account).copied()).collect::<Option<_>>()?,
+            data: bs58::decode(&decoded.data).into_vec().ok()?,
+        }),
+        UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => Some(CompiledInstruction {
+            program_id_index: *account_indexes.get(&parsed.program_id)?,
+            accounts: Vec::new(),
+            data: Vec::new(),
+        }),
+    }
+}
+
+/// Looks up decimals once for every mint that showed up in a record and is not known yet.
+/// Collateral deposits and withdrawals are keyed by reserve rather than mint, so they stay in raw
+/// collateral units.
+pub async fn fill_mint_decimals(client: &RpcClient, summary: &mut ParseSummary) {
+    resolve_record_mints(client, &mut summary.mints, &summary.records).await;
+}
+
+async fn resolve_record_mints(client: &RpcClient, mints: &mut MintRegistry, records: &[KaminoTransaction]) {
+    let record_mints: Vec<Pubkey> = records
+        .iter()
+        .flat_map(|record| match record.transaction_type {
+            TransactionType::DepositCollateral { .. } | TransactionType::Withdraw { .. } => [None, None],
+            TransactionType::Liquidate { collateral_mint, .. } => [record.reserve_token, Some(collateral_mint)],
+            _ => [record.reserve_token, None],
+        })
+        .flatten()
+        .collect();
+    for mint in record_mints {
+        mints.resolve(client, &mint).await;
+    }
+}
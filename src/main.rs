@@ -1,227 +1,1721 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use clap::parser::ValueSource;
+use clap::{ArgGroup, ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_kamino_rs::instruction::{hex, instruction_name, transaction_type_name, TRANSACTION_TYPE_NAMES};
+use solana_kamino_rs::market::{known_market_name, market_label};
+use solana_kamino_rs::cache::DiskCache;
+use solana_kamino_rs::columnar::write_parquet;
+use solana_kamino_rs::db::Database;
+use solana_kamino_rs::metrics::{self, Metrics};
+use solana_kamino_rs::price::{PriceTable, UsdVolume};
+use solana_kamino_rs::rpc::{fetch_signatures, rate_limited_client};
+use solana_kamino_rs::reserve::{fetch_market_reserves, Reserve};
+use solana_kamino_rs::state::fill_repay_splits;
+use solana_kamino_rs::summary::rfc3339;
+use solana_kamino_rs::watch::{follow, watch, watch_reserves, websocket_url, WatchOptions};
+use solana_kamino_rs::{fetch_and_list_instructions, fetch_and_parse, fetch_and_parse_signature, fetch_and_parse_signatures_with, fetch_and_parse_slots_with, read_signature_list, scan_transactions, summarize_scan, InstructionListing, AccountFilter, AmountStats, BorrowMix, KaminoTransaction, MintOrder, MintRegistry, ParseOptions, ParseSummary, Sample, TransactionType, KAMINO_LEND_PROGRAM_ID, UNKNOWN_INSTRUCTIONS_TARGET};
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_client::rpc_config::RpcTransactionConfig;
-use solana_sdk::message::VersionedMessage;
-use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
 use solana_transaction_status::UiTransactionEncoding;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::fs::File;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+use url::Url;
 
-// Kamino Lend Program ID (same for mainnet and devnet)
-const KAMINO_LEND_PROGRAM_ID: &str = "KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD";
+/// Parse recent Kamino Lend transactions and summarize borrow, repay and liquidity flows
+#[derive(Parser, Debug)]
+#[command(version, about, after_help = EXIT_CODES)]
+#[command(group(ArgGroup::new("live").args(["watch", "follow"])))]
+struct Cli {
+    /// TOML file with default option values; command-line flags override it, and it overrides
+    /// environment variables
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
 
-const SOL_MINT: &str = "So11111111111111111111111111111111111111112";
-const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    /// Number of transactions fetched from the RPC concurrently
+    #[arg(long, env = "CONCURRENCY", default_value = "8")]
+    concurrency: NonZeroUsize,
 
-const FLASH_LOAN_DATA_BORROW_SIG: &[u8] = &[0x87, 0xe7, 0x34, 0xa7];
-const FLASH_LOAN_TOKEN_ACCOUNT_KEY: usize = 4;
+    /// Maximum RPC requests per second, shared by all concurrent fetches; public endpoints allow about 10
+    #[arg(long, env = "RPS", default_value = "10")]
+    rps: NonZeroU32,
 
-const BORROW_OBLIGATION_DATA_SIG: &[u8] = &[0xa1, 0x80, 0x8f, 0xf5];
-const BORROW_OBLIGATION_TOKEN_ACCOUNT_KEY: usize = 5;
+    /// Retries for transient RPC failures (timeouts, rate limits) before a request is dropped
+    #[arg(long, env = "RPC_RETRIES", default_value_t = 4)]
+    rpc_retries: u32,
 
+    /// Output format of the summary on stdout; logs always go to stderr
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// With --format ndjson, end the record stream with the summary as one more JSON line
+    #[arg(long)]
+    summary: bool,
+
+    /// Indent JSON output for reading; the default when writing to a terminal
+    #[arg(long, conflicts_with = "compact")]
+    pretty: bool,
+
+    /// Write JSON output on a single line for other programs; the default when piped or written
+    /// to --output-file. ndjson lines are always compact
+    #[arg(long)]
+    compact: bool,
+
+    /// Write the output to this file instead of stdout. The file is replaced atomically once the
+    /// scan finishes; streamed records (ndjson, and CSV in --watch or --follow mode) are written to it as they
+    /// arrive instead
+    #[arg(long, alias = "output", value_name = "PATH")]
+    output_file: Option<PathBuf>,
+
+    /// Only fetch and parse the N most recent successful transactions, to bound RPC usage
+    #[arg(long, value_name = "N")]
+    max_transactions: Option<usize>,
+
+    /// Only fetch and parse this random share of the signatures, e.g. 0.1 for 10%, and
+    /// extrapolate totals and counts from it; the summary is labelled as an estimate
+    #[arg(
+        long,
+        value_name = "RATE",
+        value_parser = parse_sample_rate,
+        conflicts_with_all = ["from_slot", "signature", "db", "live", "list_instructions", "check", "watch_reserves"]
+    )]
+    sample: Option<f64>,
+
+    /// Seed of the --sample selection, to repeat a run [default: random, printed in the summary]
+    #[arg(long, requires = "sample")]
+    seed: Option<u64>,
+
+    /// Stop paging back through signature history at this slot (inclusive)
+    #[arg(long)]
+    since_slot: Option<u64>,
+
+    /// Scan every block from this slot instead of the recent signature history, for reproducible
+    /// results; requires --to-slot
+    #[arg(
+        long,
+        requires = "to_slot",
+        conflicts_with_all = ["max_transactions", "since_slot", "resume", "since", "until", "live", "list_instructions"]
+    )]
+    from_slot: Option<u64>,
+
+    /// Last slot (inclusive) of the --from-slot range
+    #[arg(long, requires = "from_slot")]
+    to_slot: Option<u64>,
+
+    /// Only fetch and parse this transaction and print a breakdown of its instructions, e.g. to
+    /// check the parser against a transaction found on an explorer
+    #[arg(
+        long,
+        value_name = "SIG",
+        conflicts_with_all = ["max_transactions", "since_slot", "resume", "since", "until", "live", "list_instructions", "from_slot"]
+    )]
+    signature: Option<Signature>,
+
+    /// Fetch and parse the transactions listed in this file, one signature per line, instead of
+    /// the program's signature history; `-` reads them from stdin. Invalid lines are skipped
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["max_transactions", "since_slot", "resume", "since", "until", "live", "list_instructions", "from_slot", "signature", "compare", "sample"]
+    )]
+    signatures_file: Option<PathBuf>,
+
+    /// Store parsed records in this SQLite database; already stored instructions are skipped
+    #[arg(long)]
+    db: Option<PathBuf>,
+
+    /// Start from the latest slot stored in --db instead of the newest signatures
+    #[arg(long, requires = "db")]
+    resume: bool,
+
+    /// Only count instructions touching this reserve; repeat to match any of several
+    #[arg(long = "reserve", value_name = "PUBKEY")]
+    reserves: Vec<Pubkey>,
+
+    /// Only count instructions touching this lending market; repeat to match any of several
+    #[arg(long = "market", value_name = "PUBKEY")]
+    markets: Vec<Pubkey>,
+
+    /// Only count instructions of this reserve liquidity mint; repeat to allow several. Takes
+    /// precedence over --exclude-mint
+    #[arg(long = "include-mint", value_name = "MINT")]
+    include_mints: Vec<Pubkey>,
+
+    /// Do not count instructions of this reserve liquidity mint; repeat to leave out several
+    #[arg(long = "exclude-mint", value_name = "MINT")]
+    exclude_mints: Vec<Pubkey>,
+
+    /// Only count these instruction types, comma-separated, e.g. flash-borrow,flash-repay
+    #[arg(long, value_name = "TYPES", value_delimiter = ',', value_parser = parse_instruction_type)]
+    only: Vec<&'static str>,
+
+    /// JSON object of mint addresses to symbols, extending the built-in ones shown in text output
+    #[arg(long, value_name = "FILE")]
+    token_list: Option<PathBuf>,
+
+    /// Decimal places of token amounts in text output, rounded; defaults to each mint's own
+    /// decimals with trailing zeros dropped
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(..=18))]
+    precision: Option<u8>,
+
+    /// Give every amount in JSON and table output as both the exact raw integer and the
+    /// decimals-scaled UI value; CSV and ndjson records always carry both
+    #[arg(long)]
+    raw_amounts: bool,
+
+    /// Directory of the on-disk cache of lookup tables and mint decimals
+    /// [default: $XDG_CACHE_HOME/solana-kamino-rs or ~/.cache/solana-kamino-rs]
+    #[arg(long, env = "CACHE_DIR", value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+
+    /// Neither read nor write the on-disk cache
+    #[arg(long, conflicts_with = "cache_dir")]
+    no_cache: bool,
+
+    /// Also break borrows down per obligation owner
+    #[arg(long)]
+    by_user: bool,
+
+    /// Also list borrows and repays per obligation, which one owner can have several of
+    #[arg(long)]
+    by_obligation: bool,
+
+    /// Also break borrows down per referrer, i.e. the token state account collecting the host fee
+    #[arg(long)]
+    by_referrer: bool,
+
+    /// Also break flash loan and obligation borrows down per lending market, named for Kamino's
+    /// main markets (Main, JLP, Altcoins) and by address otherwise
+    #[arg(long)]
+    by_market: bool,
+
+    /// Also report mean, median, min, max and p90 of flash loan and obligation borrow sizes per mint
+    #[arg(long)]
+    stats: bool,
+
+    /// Also report the share of borrow volume that came from flash loans rather than obligation
+    /// borrows, per mint and overall (by USD volume with --prices, by count otherwise)
+    #[arg(long)]
+    borrow_mix: bool,
+
+    /// Also break activity down per hour or day of block time (UTC): transactions, borrows and
+    /// borrow volume per bucket, for spotting peaks the totals hide
+    #[arg(long, value_enum, value_name = "WIDTH")]
+    buckets: Option<BucketWidth>,
+
+    /// In text output, only list the N mints with the highest flash loan and obligation borrow
+    /// volume, each ranked on its own (by USD value with --prices) and listed in --sort order
+    #[arg(long, value_name = "N")]
+    top_n: Option<usize>,
+
+    /// Order of the per-token lines in text and table output: by mint address, so repeated runs
+    /// over the same data print identically, or by volume, largest first
+    #[arg(long, value_enum, value_name = "ORDER", default_value_t = SortOrder::Mint)]
+    sort: SortOrder,
+
+    /// JSON file mapping mint addresses to USD prices, to value borrow and flash loan volume
+    #[arg(long, value_name = "FILE")]
+    prices: Option<PathBuf>,
+
+    /// Only list the discriminator of every program instruction per transaction, with a count per
+    /// discriminator, to find instructions the parser does not decode yet
+    #[arg(long, conflicts_with = "live")]
+    list_instructions: bool,
+
+    /// Check the configuration and exit: RPC health and version, that the program id is a
+    /// deployed program, and that a recent transaction can be fetched and parsed
+    #[arg(long, conflicts_with_all = ["live", "list_instructions", "signature", "from_slot", "compare", "watch_reserves"])]
+    check: bool,
+
+    /// List the reserves of this lending market, with their liquidity mint, and exit; the
+    /// addresses are the ones --reserve and --watch-reserve take
+    #[arg(
+        long,
+        value_name = "MARKET",
+        conflicts_with_all = ["live", "list_instructions", "check", "signature", "from_slot", "compare", "watch_reserves"]
+    )]
+    list_reserves: Option<Pubkey>,
+
+    /// Also parse failed transactions, counting their instructions as attempts without amounts
+    #[arg(long)]
+    include_failed: bool,
+
+    /// Only scan transactions at or after this time: RFC3339 (2024-05-01T12:00:00Z) or a duration ago (1h, 30m)
+    #[arg(long, value_parser = parse_time_bound)]
+    since: Option<i64>,
+
+    /// Only scan transactions at or before this time: RFC3339 or a duration ago
+    #[arg(long, value_parser = parse_time_bound)]
+    until: Option<i64>,
+
+    /// Also scan the window of the same length right before --since/--until and print how
+    /// per-mint volumes and instruction counts changed between the two
+    #[arg(long, requires = "since", conflicts_with_all = ["max_transactions", "since_slot", "resume", "db", "live", "list_instructions"])]
+    compare: bool,
+
+    /// Kamino Lend program to scan, for forks or local redeployments [default: mainnet program]
+    #[arg(long, env = "PROGRAM_ID")]
+    program_id: Option<Pubkey>,
+
+    /// Only log warnings and errors; overrides RUST_LOG
+    #[arg(long, short, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Log per-transaction detail (debug level) unless RUST_LOG says otherwise
+    #[arg(long, short)]
+    verbose: bool,
+
+    /// Log every Kamino Lend instruction the parser does not decode with its discriminator, full
+    /// data as hex and resolved accounts, even with --quiet
+    #[arg(long, conflicts_with = "list_instructions")]
+    dump_unknown: bool,
+
+    /// Exit with an error when more than this fraction of the scanned transactions had to be
+    /// skipped for fetch or lookup table errors; the summary is still written first
+    #[arg(long, value_name = "FRACTION", default_value_t = 0.05, value_parser = parse_fraction)]
+    max_skipped_fraction: f64,
+
+    /// Fetch the obligation and reserve of every repay to estimate its principal and interest;
+    /// repays whose accounts cannot be decoded keep only their raw amount
+    #[arg(long, conflicts_with_all = ["live", "list_instructions"])]
+    decode_state: bool,
+
+    /// Keep running and parse new transactions as they land, via a logs WebSocket subscription
+    #[arg(long)]
+    watch: bool,
+
+    /// Like --watch, but polls the signature history every --interval over plain HTTP, for RPC
+    /// endpoints without WebSocket support
+    #[arg(long)]
+    follow: bool,
+
+    /// Time between --follow polls, e.g. 10s or 1m
+    #[arg(long, default_value = "10s", value_parser = humantime::parse_duration, requires = "follow")]
+    interval: Duration,
+
+    /// Follow this reserve account over a WebSocket subscription and print its available
+    /// liquidity and total borrows on every change; repeat to follow several
+    #[arg(
+        long = "watch-reserve",
+        value_name = "PUBKEY",
+        conflicts_with_all = ["live", "list_instructions", "signature", "from_slot", "compare", "decode_state", "db"]
+    )]
+    watch_reserves: Vec<Pubkey>,
+
+    /// WebSocket endpoint for --watch and --watch-reserve [default: derived from the RPC URL]
+    #[arg(long, env = "WS_URL")]
+    ws_url: Option<String>,
+
+    /// Seconds between running-total reports in --watch and --follow mode
+    #[arg(long, default_value = "60")]
+    report_interval: NonZeroU64,
+
+    /// Serve the --watch or --follow running totals as Prometheus metrics on this port
+    #[arg(long, value_name = "PORT", requires = "live")]
+    metrics_port: Option<u16>,
+
+    /// Transaction encoding to fetch. jsonparsed has the RPC node resolve lookup tables, so
+    /// transactions whose lookup tables were closed can still be parsed
+    #[arg(long, value_enum, default_value_t = Encoding::Base64, conflicts_with_all = ["live", "list_instructions"])]
+    encoding: Encoding,
+
+    /// Commitment level for RPC requests: lower levels see transactions sooner but may include
+    /// ones from dropped forks. Transaction history is never fetched below confirmed, and --watch
+    /// always follows confirmed transactions.
+    #[arg(long, value_enum, env = "COMMITMENT", default_value_t = Commitment::Finalized)]
+    commitment: Commitment,
+
+    /// Cluster whose public RPC endpoint is used when RPC_URL is not set
+    #[arg(long, value_enum)]
+    network: Option<Network>,
+}
+
+// Shown with every config error so it is clear which value ends up being used
+const EXIT_CODES: &str = "Exit codes:
+  0  the scan completed
+  1  the scan failed, e.g. the RPC endpoint could not be reached
+  2  invalid flags, config file, environment or input files, e.g. RPC_URL is not set
+  3  the scan completed, but skipped more than --max-skipped-fraction of the transactions;
+     the summary is still written";
+const EXIT_FAILURE: u8 = 1;
+const EXIT_CONFIG: u8 = 2;
+const EXIT_PARTIAL: u8 = 3;
+
+/// Failures with their own exit code; any other error exits with [`EXIT_FAILURE`]
+#[derive(Debug)]
+enum ExitError {
+    /// The run cannot start as configured; clap reports its usage errors with the same code
+    Config(String),
+    /// Too many transactions were skipped for the summary to be trusted
+    Partial(String),
+}
+
+impl ExitError {
+    /// Keeps the full context chain of `error` in the message
+    fn config(error: anyhow::Error) -> Self {
+        ExitError::Config(format!("{:#}", error))
+    }
+
+    fn code(&self) -> u8 {
+        match self {
+            ExitError::Config(_) => EXIT_CONFIG,
+            ExitError::Partial(_) => EXIT_PARTIAL,
+        }
+    }
+}
+
+impl fmt::Display for ExitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExitError::Config(message) | ExitError::Partial(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ExitError {}
+
+const CONFIG_PRECEDENCE: &str = "command-line flags override --config values, which override environment variables and built-in defaults";
+
+/// Contents of a `--config` TOML file; every key is optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct FileConfig {
+    network: Option<Network>,
+    program_id: Option<String>,
+    concurrency: Option<NonZeroUsize>,
+    rps: Option<NonZeroU32>,
+    reserves: Option<Vec<String>>,
+    markets: Option<Vec<String>>,
+    include_mints: Option<Vec<String>>,
+    exclude_mints: Option<Vec<String>>,
+    format: Option<OutputFormat>,
+    commitment: Option<Commitment>,
+}
+
+impl FileConfig {
+    fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {} ({})", path.display(), CONFIG_PRECEDENCE))?;
+        toml::from_str(&contents).with_context(|| format!("invalid config file {} ({})", path.display(), CONFIG_PRECEDENCE))
+    }
+
+    /// Fills in every option that was not given on the command line, replacing environment
+    /// variables and defaults
+    fn apply(self, cli: &mut Cli, matches: &ArgMatches) -> Result<()> {
+        let from_command_line = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        let pubkeys = |key: &str, values: Vec<String>| -> Result<Vec<Pubkey>> {
+            values
+                .iter()
+                .map(|value| {
+                    Pubkey::from_str(value)
+                        .with_context(|| format!("config {} has an invalid pubkey {:?} ({})", key, value, CONFIG_PRECEDENCE))
+                })
+                .collect()
+        };
+
+        if let (Some(network), false) = (self.network, from_command_line("network")) {
+            cli.network = Some(network);
+        }
+        if let (Some(program_id), false) = (self.program_id, from_command_line("program_id")) {
+            cli.program_id = Some(pubkeys("program-id", vec![program_id])?[0]);
+        }
+        if let (Some(concurrency), false) = (self.concurrency, from_command_line("concurrency")) {
+            cli.concurrency = concurrency;
+        }
+        if let (Some(commitment), false) = (self.commitment, from_command_line("commitment")) {
+            cli.commitment = commitment;
+        }
+        if let (Some(rps), false) = (self.rps, from_command_line("rps")) {
+            cli.rps = rps;
+        }
+        if let (Some(reserves), false) = (self.reserves, from_command_line("reserves")) {
+            cli.reserves = pubkeys("reserves", reserves)?;
+        }
+        if let (Some(markets), false) = (self.markets, from_command_line("markets")) {
+            cli.markets = pubkeys("markets", markets)?;
+        }
+        if let (Some(mints), false) = (self.include_mints, from_command_line("include_mints")) {
+            cli.include_mints = pubkeys("include-mints", mints)?;
+        }
+        if let (Some(mints), false) = (self.exclude_mints, from_command_line("exclude_mints")) {
+            cli.exclude_mints = pubkeys("exclude-mints", mints)?;
+        }
+        if let (Some(format), false) = (self.format, from_command_line("format")) {
+            cli.format = format;
+        }
+        Ok(())
+    }
+}
+
+/// What the text and JSON summaries include beyond the totals
+struct ReportOptions {
+    /// List the signatures of skipped transactions and the unresolved lookup tables
+    skipped: bool,
+    by_user: bool,
+    by_referrer: bool,
+    by_market: bool,
+    by_obligation: bool,
+    stats: bool,
+    borrow_mix: bool,
+    buckets: Option<BucketWidth>,
+    /// Cut the borrow volume listings to the largest mints
+    top_n: Option<usize>,
+    sort: MintOrder,
+    prices: Option<PriceTable>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum BucketWidth {
+    Hour,
+    Day,
+}
+
+impl BucketWidth {
+    fn duration(self) -> Duration {
+        match self {
+            BucketWidth::Hour => Duration::from_secs(3_600),
+            BucketWidth::Day => Duration::from_secs(86_400),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            BucketWidth::Hour => "hour",
+            BucketWidth::Day => "day",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum SortOrder {
+    Mint,
+    Volume,
+}
+
+impl SortOrder {
+    fn mint_order(self) -> MintOrder {
+        match self {
+            SortOrder::Mint => MintOrder::Mint,
+            SortOrder::Volume => MintOrder::Volume,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Encoding {
+    Base64,
+    #[value(name = "jsonparsed")]
+    JsonParsed,
+}
+
+impl Encoding {
+    fn ui_encoding(self) -> UiTransactionEncoding {
+        match self {
+            Encoding::Base64 => UiTransactionEncoding::Base64,
+            Encoding::JsonParsed => UiTransactionEncoding::JsonParsed,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+impl Commitment {
+    fn config(self) -> CommitmentConfig {
+        match self {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    Text,
+    Json,
+    /// One row per parsed instruction
+    Csv,
+    /// One JSON object per parsed instruction, written as soon as its transaction is parsed
+    Ndjson,
+    /// The CSV rows as a typed, columnar Parquet file; requires --output-file
+    Parquet,
+    /// The per-token totals as an aligned table; falls back to json when stdout is not a terminal
+    Table,
+}
+
+/// Row layout of `--format csv`, and the object on each `--format ndjson` line
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    signature: &'a str,
+    slot: u64,
+    block_time: Option<i64>,
+    instruction_type: &'static str,
+    /// Position among the CPI instructions of the invoking top-level instruction; empty for
+    /// top-level instructions
+    inner_index: Option<usize>,
+    reserve_mint: Option<String>,
+    raw_amount: Option<u64>,
+    ui_amount: Option<f64>,
+    /// Repay split with --decode-state, in raw units; empty when it could not be decoded
+    principal_amount: Option<u64>,
+    interest_amount: Option<u64>,
+    failed: bool,
+}
+
+/// Header of `--format csv`, written on its own when there are no rows to take it from
+const CSV_COLUMNS: [&str; 11] = [
+    "signature",
+    "slot",
+    "block_time",
+    "instruction_type",
+    "inner_index",
+    "reserve_mint",
+    "raw_amount",
+    "ui_amount",
+    "principal_amount",
+    "interest_amount",
+    "failed",
+];
+
+#[derive(Clone, Copy, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum Network {
+    Mainnet,
+    Devnet,
+}
+
+impl Network {
+    fn name(self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Devnet => "devnet",
+        }
+    }
+
+    fn default_rpc_url(self) -> &'static str {
+        match self {
+            Network::Mainnet => "https://api.mainnet-beta.solana.com",
+            Network::Devnet => "https://api.devnet.solana.com",
+        }
+    }
+}
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(e.downcast_ref::<ExitError>().map_or(EXIT_FAILURE, ExitError::code))
+        }
+    }
+}
+
+async fn run() -> Result<()> {
     // Load environment variables from .env file
     dotenv::dotenv().ok();
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(path) = &cli.config {
+        FileConfig::load(path)
+            .and_then(|config| config.apply(&mut cli, &matches))
+            .map_err(ExitError::config)?;
+    }
+    if cli.compare && matches!(cli.format, OutputFormat::Csv | OutputFormat::Ndjson | OutputFormat::Parquet) {
+        bail!(ExitError::Config("--compare only supports --format text or json".to_string()));
+    }
+    if cli.format == OutputFormat::Parquet {
+        if cli.output_file.is_none() {
+            bail!(ExitError::Config("--format parquet writes a binary file; pass --output-file <PATH>".to_string()));
+        }
+        if cli.watch || cli.follow || !cli.watch_reserves.is_empty() {
+            bail!(ExitError::Config("--format parquet cannot be used with --watch, --follow or --watch-reserve, a Parquet file is only readable once complete".to_string()));
+        }
+    }
+    if cli.decode_state && cli.format == OutputFormat::Ndjson {
+        bail!(ExitError::Config("--decode-state cannot be used with --format ndjson, whose records are written before repays are decoded".to_string()));
+    }
+
+    // Logs go to stderr so stdout only carries the summary; RUST_LOG controls verbosity
+    let mut filter = if cli.quiet {
+        EnvFilter::new("warn")
+    } else {
+        let default_level = if cli.verbose { "debug" } else { "info" };
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level))
+    };
+    if cli.dump_unknown {
+        filter = filter.add_directive(format!("{}=debug", UNKNOWN_INSTRUCTIONS_TARGET).parse()?);
+    }
+    tracing_subscriber::fmt().with_env_filter(filter).with_writer(std::io::stderr).init();
+    if cli.format == OutputFormat::Table && (cli.output_file.is_some() || !io::stdout().is_terminal()) {
+        info!("--format table is for terminals; writing JSON instead");
+        cli.format = OutputFormat::Json;
+    }
+    cli.pretty = !cli.compact && (cli.pretty || (cli.output_file.is_none() && io::stdout().is_terminal()));
     
-    println!("🚀 Starting Kamino Lend Transaction Parser");
-    println!("📋 Program ID: {}", KAMINO_LEND_PROGRAM_ID);
-    
-    // Initialize RPC client (using devnet for testing, change to mainnet as needed)
-    let client = RpcClient::new_with_commitment(
-        String::from(env::var("RPC_URL").unwrap()),
-        CommitmentConfig::finalized(),
-    );
-    
-    let program_id = Pubkey::from_str(KAMINO_LEND_PROGRAM_ID)?;    
-    println!("🔍 Fetching recent transactions for Kamino Lend program...\n");
-    // Get recent signatures for the program
-    let successful_signatures = match client.get_signatures_for_address(&program_id).await {
-        Ok(all_signatures) => {
-            println!("📊 Found {} total recent transactions", all_signatures.len());
-            // Filter out failed transactions - only keep successful ones
-            let sucsigs: Vec<_> = all_signatures
-                .iter()
-                .filter(|sig_info| sig_info.err.is_none())
-                .cloned()
-                .collect::<Vec<_>>();
-            let failed_count = all_signatures.len() - sucsigs.len();
-            
-            println!("✅ {} successful transactions", sucsigs.len());
-            println!("❌ {} failed transactions (filtered out)", failed_count);
-            println!("{}", "=".repeat(60));
-            
-            for (i, sig_info) in sucsigs.iter().enumerate() {
-                println!("{}. Transaction Hash: {}", i + 1, sig_info.signature);
-                println!("   Slot: {}", sig_info.slot);
-                if let Some(block_time) = sig_info.block_time {
-                    println!("   Time: {}", block_time);
-                }
-                println!("   Status: Success ✅");
-                println!();
+    // RPC_URL always wins; --network only picks a default endpoint when it is missing
+    let (rpc_url, cluster) = match (env::var("RPC_URL"), cli.network) {
+        (Ok(rpc_url), Some(network)) => (rpc_url, format!("{} (RPC_URL)", network.name())),
+        (Ok(rpc_url), None) => (rpc_url, "custom (RPC_URL)".to_string()),
+        (Err(_), Some(network)) => (network.default_rpc_url().to_string(), format!("{} (public default)", network.name())),
+        (Err(_), None) => bail!(ExitError::Config("RPC_URL environment variable is not set; add it to your .env, export it, or pass --network mainnet|devnet (or set network in --config)".to_string())),
+    };
+    Url::parse(&rpc_url)
+        .with_context(|| format!("RPC_URL {:?} is not a valid URL", rpc_url))
+        .map_err(ExitError::config)?;
+
+    let program_id = match cli.program_id {
+        Some(program_id) => program_id,
+        None => Pubkey::from_str(KAMINO_LEND_PROGRAM_ID)?,
+    };
+
+    info!(%program_id, %cluster, rpc_endpoint = %rpc_url, "Starting Kamino Lend Transaction Parser");
+
+    let client = rate_limited_client(&rpc_url, cli.rps, cli.commitment.config());
+
+    if cli.check {
+        let opts = ParseOptions { encoding: cli.encoding.ui_encoding(), rpc_retries: cli.rpc_retries, ..ParseOptions::default() };
+        return run_checks(&client, &program_id, opts).await;
+    }
+
+    let mut db = cli.db.as_deref().map(Database::open).transpose()?;
+    // Resuming rescans the latest stored slot; its already stored instructions are skipped
+    let resume_slot = match (&db, cli.resume) {
+        (Some(db), true) => db.latest_slot()?,
+        _ => None,
+    };
+    if let Some(slot) = resume_slot {
+        info!("Resuming from stored slot {}", slot);
+    }
+
+    // Instruction listings resolve neither lookup tables nor mints, so they skip the cache
+    let mut cache = match (cli.no_cache || cli.list_instructions, cli.cache_dir.clone().or_else(DiskCache::default_dir)) {
+        (false, Some(dir)) => match DiskCache::open(&client, &dir).await {
+            Ok(cache) => Some(cache),
+            Err(e) => {
+                warn!("Not using the cache: {:#}", e);
+                None
             }
+        },
+        _ => None,
+    };
 
-            sucsigs
+    let mut mints = MintRegistry::new();
+    if let Some(cache) = &cache {
+        cache.seed_mints(&mut mints);
+    }
+    mints.set_precision(cli.precision);
+    mints.set_raw_amounts(cli.raw_amounts);
+    if let Some(path) = &cli.token_list {
+        mints.load_token_list(path).map_err(ExitError::config)?;
+    }
+    let account_filter = AccountFilter {
+        reserves: cli.reserves.clone(),
+        markets: cli.markets.clone(),
+        include_mints: cli.include_mints.clone(),
+        exclude_mints: cli.exclude_mints.clone(),
+        instruction_types: cli.only.clone(),
+    };
+    let opts = ParseOptions {
+        account_filter: account_filter.clone(),
+        mints: mints.clone(),
+        lookup_tables: cache.as_ref().map(|cache| cache.lookup_tables.clone()).unwrap_or_default(),
+        encoding: cli.encoding.ui_encoding(),
+        concurrency: cli.concurrency,
+        rpc_retries: cli.rpc_retries,
+        max_transactions: cli.max_transactions,
+        since_slot: cli.since_slot.max(resume_slot),
+        // Only draw the bar for interactive runs so piped output stays clean
+        progress_bar: !cli.quiet && io::stdout().is_terminal() && io::stderr().is_terminal(),
+        include_failed: cli.include_failed,
+        since: cli.since,
+        until: cli.until,
+        sample: cli.sample.map(|rate| Sample { rate, seed: cli.seed.unwrap_or_else(rand::random) }),
+    };
+
+    let report = ReportOptions {
+        skipped: cli.verbose,
+        by_user: cli.by_user,
+        by_referrer: cli.by_referrer,
+        by_market: cli.by_market,
+        by_obligation: cli.by_obligation,
+        stats: cli.stats,
+        borrow_mix: cli.borrow_mix,
+        buckets: cli.buckets,
+        top_n: cli.top_n,
+        sort: cli.sort.mint_order(),
+        prices: cli.prices.as_deref().map(PriceTable::load).transpose().map_err(ExitError::config)?,
+    };
+    let listed_signatures = cli.signatures_file.as_deref().map(load_signature_list).transpose().map_err(ExitError::config)?;
+
+    if !cli.watch_reserves.is_empty() {
+        let ws_url = match &cli.ws_url {
+            Some(ws_url) => ws_url.clone(),
+            None => websocket_url(&rpc_url)?,
+        };
+        return follow_reserves(&ws_url, &cli, mints).await;
+    }
+
+    if cli.watch || cli.follow {
+        let watch_opts = WatchOptions {
+            rpc_retries: cli.rpc_retries,
+            report_interval: Duration::from_secs(cli.report_interval.get()),
+            account_filter,
+            mints,
+            lookup_tables: opts.lookup_tables.clone(),
+        };
+        let mut writer = match cli.format {
+            OutputFormat::Csv => Some(csv::Writer::from_writer(stream_writer(cli.output_file.as_deref())?)),
+            _ => None,
+        };
+        let mut ndjson = match cli.format {
+            OutputFormat::Ndjson => Some(stream_writer(cli.output_file.as_deref())?),
+            _ => None,
+        };
+        let metrics = match cli.metrics_port {
+            Some(port) => {
+                let metrics = Arc::new(Metrics::new()?);
+                metrics::serve(metrics.clone(), port)?;
+                Some(metrics)
+            }
+            None => None,
+        };
+        let (format, pretty) = (cli.format, cli.pretty);
+        let on_record = |record: &KaminoTransaction, summary: &ParseSummary| {
+            if let Some(metrics) = &metrics {
+                metrics.observe(record, summary);
+            }
+            if let Some(db) = db.as_mut() {
+                if let Err(e) = db.insert_records([record]) {
+                    warn!(signature = %record.signature, "Failed to store record: {}", e);
+                }
+            }
+            let row = csv_row(record, summary);
+            match (format, writer.as_mut()) {
+                (OutputFormat::Csv, Some(writer)) => {
+                    if let Err(e) = writer.serialize(row).and_then(|()| writer.flush().map_err(csv::Error::from)) {
+                        warn!("Failed to write CSV record: {}", e);
+                    }
+                }
+                (OutputFormat::Json, _) => match to_json(&row, pretty) {
+                    Ok(json) => println!("{}", json),
+                    Err(e) => warn!("Failed to write JSON record: {}", e),
+                },
+                (OutputFormat::Ndjson, _) => {
+                    if let Some(out) = ndjson.as_mut() {
+                        if let Err(e) = write_ndjson_record(out, record, summary) {
+                            warn!("Failed to write record: {}", e);
+                        }
+                    }
+                }
+                _ => match (record.reserve_token, row.raw_amount) {
+                    (Some(mint), Some(amount)) => println!(
+                        "{} {} {}: {}",
+                        record.signature,
+                        row.instruction_type,
+                        summary.mints.symbol(&mint),
+                        summary.format_ui_amount(&mint, amount as i128)
+                    ),
+                    _ => println!("{} {}", record.signature, row.instruction_type),
+                },
+            }
+        };
+        let on_report = |summary: &ParseSummary| {
+            if let Some(metrics) = &metrics {
+                metrics.report(summary);
+            }
+            match format {
+                OutputFormat::Text => {
+                    if let Err(e) = write_text_summary(&mut io::stdout().lock(), summary, &report) {
+                        warn!("Failed to print running totals: {}", e);
+                    }
+                }
+                OutputFormat::Table => {
+                    if let Err(e) = write_table_summary(&mut io::stdout().lock(), summary, &report) {
+                        warn!("Failed to print running totals: {}", e);
+                    }
+                }
+                _ => info!(transactions = summary.transactions_scanned, records = summary.records.len(), "Running totals"),
+            }
+        };
+        let summary = if cli.follow {
+            follow(&client, &program_id, cli.interval, watch_opts, on_record, on_report).await?
+        } else {
+            let ws_url = match &cli.ws_url {
+                Some(ws_url) => ws_url.clone(),
+                None => websocket_url(&rpc_url)?,
+            };
+            watch(&client, &ws_url, &program_id, watch_opts, on_record, on_report).await?
+        };
+        save_cache(cache.as_mut(), &summary);
+        // Final totals on Ctrl-C; streamed formats already wrote their records to the output
+        let mut output = Vec::new();
+        match format {
+            OutputFormat::Text => write_text_summary(&mut output, &summary, &report)?,
+            OutputFormat::Table => write_table_summary(&mut output, &summary, &report)?,
+            OutputFormat::Json => writeln!(output, "{}", to_json(&json_summary(&summary, &report), cli.pretty)?)?,
+            OutputFormat::Csv | OutputFormat::Parquet => {}
+            OutputFormat::Ndjson => {
+                if let (true, Some(out)) = (cli.summary, ndjson.as_mut()) {
+                    write_ndjson_summary(out, &summary, &report)?;
+                }
+            }
         }
-        Err(e) => {
-            eprintln!("❌ Error fetching signatures: {}", e);
-            vec![]
+        if !output.is_empty() {
+            emit_output(cli.output_file.as_deref(), &output)?;
+        }
+        return check_skipped(&summary, cli.max_skipped_fraction);
+    }
+
+    if let Some(market) = cli.list_reserves {
+        let reserves = fetch_market_reserves(&client, &program_id, &market).await?;
+        info!(reserves = reserves.len(), "Listed the reserves of {}", market_label(&market));
+        let mut output = Vec::new();
+        write_reserve_listing(&mut output, &reserves, &mints, cli.format, cli.pretty)?;
+        return emit_output(cli.output_file.as_deref(), &output);
+    }
+
+    if cli.list_instructions {
+        let listings = fetch_and_list_instructions(&client, &program_id, opts).await?;
+        let mut output = Vec::new();
+        write_instruction_listings(&mut output, &listings)?;
+        return emit_output(cli.output_file.as_deref(), &output);
+    }
+
+    // ndjson records are written as they are parsed, straight to the output
+    let mut ndjson = match cli.format {
+        OutputFormat::Ndjson => Some(stream_writer(cli.output_file.as_deref())?),
+        _ => None,
+    };
+    let mut on_record = |record: &KaminoTransaction, summary: &ParseSummary| {
+        if let Some(out) = ndjson.as_mut() {
+            if let Err(e) = write_ndjson_record(out, record, summary) {
+                warn!("Failed to write record: {}", e);
+            }
         }
     };
 
-    let block_time_first = successful_signatures[0].block_time.unwrap();
-    let block_time_last = successful_signatures[successful_signatures.len() - 1].block_time.unwrap();
-    let block_time_diff = block_time_first - block_time_last;
+    let compare_opts = cli.compare.then(|| opts.clone());
 
-    let config = RpcTransactionConfig {
-        commitment: CommitmentConfig::finalized().into(),
-        encoding: UiTransactionEncoding::Base64.into(),
-        max_supported_transaction_version: Some(0),
+    // Ctrl-C stops the scan early; the partial summary is still printed below
+    let mut summary = match (cli.signature, listed_signatures, cli.from_slot, cli.to_slot) {
+        (Some(signature), _, _, _) => {
+            let summary = fetch_and_parse_signature(&client, &program_id, &signature, opts).await?;
+            for record in &summary.records {
+                on_record(record, &summary);
+            }
+            summary
+        }
+        (None, Some(signatures), _, _) => fetch_and_parse_signatures_with(&client, &program_id, signatures, opts, on_record).await?,
+        (None, None, Some(from_slot), Some(to_slot)) => fetch_and_parse_slots_with(&client, &program_id, from_slot, to_slot, opts, on_record).await?,
+        _ => {
+            let scan = scan_transactions(&client, &program_id, &opts).await?;
+            summarize_scan(&client, &program_id, scan, on_record).await
+        }
     };
+    save_cache(cache.as_mut(), &summary);
+    // A quiet window still compares against a busy one
+    if let Some(opts) = compare_opts {
+        return compare_with_previous_window(&client, &program_id, opts, summary, &cli, cache.as_mut()).await;
+    }
+    if cli.decode_state {
+        fill_repay_splits(&client, &mut summary).await;
+    }
+    if let Some(db) = db.as_mut() {
+        let inserted = db.insert_records(&summary.records)?;
+        info!("Stored {} new of {} records", inserted, summary.records.len());
+    }
 
+    if let Some(out) = ndjson.as_mut() {
+        if cli.summary {
+            write_ndjson_summary(out, &summary, &report)?;
+        }
+        return check_skipped(&summary, cli.max_skipped_fraction);
+    }
 
-    let sol_mint_key = Pubkey::from_str(SOL_MINT).unwrap();
-    let usdc_mint_key = Pubkey::from_str(USDC_MINT).unwrap();
-    let mut flash_loan_borrow_sol_amount: u64 = 0;
-    let mut flash_loan_borrow_usdc_amount: u64 = 0;
-    let mut loan_borrow_usdc_amount: u64 = 0;
-    let mut loan_borrow_sol_amount: u64 = 0;
+    let mut output = Vec::new();
+    if cli.format == OutputFormat::Csv {
+        let mut writer = csv::Writer::from_writer(&mut output);
+        if summary.records.is_empty() {
+            writer.write_record(CSV_COLUMNS)?;
+        }
+        for record in &summary.records {
+            writer.serialize(csv_row(record, &summary))?;
+        }
+        writer.flush()?;
+    } else if cli.format == OutputFormat::Parquet {
+        write_parquet(&mut output, &summary)?;
+    } else if cli.format == OutputFormat::Json && cli.signature.is_some() {
+        let rows: Vec<CsvRow> = summary.records.iter().map(|record| csv_row(record, &summary)).collect();
+        writeln!(output, "{}", to_json(&rows, cli.pretty)?)?;
+    } else if cli.format == OutputFormat::Json {
+        writeln!(output, "{}", to_json(&json_summary(&summary, &report), cli.pretty)?)?;
+    } else if cli.signature.is_some() {
+        write_instruction_breakdown(&mut output, &summary)?;
+    } else if cli.format == OutputFormat::Table {
+        write_table_summary(&mut output, &summary, &report)?;
+    } else {
+        write_text_summary(&mut output, &summary, &report)?;
+    }
 
-    let mut flash_loan_txs_count: u64 = 0;
-    let mut loan_txs_count: u64 = 0;
+    emit_output(cli.output_file.as_deref(), &output)?;
+    check_skipped(&summary, cli.max_skipped_fraction)
+}
 
-    // Print just the successful hashes for easy copying
-    println!("\n🔗 Successful transaction hashes only:");
-    for (i, sig_info) in successful_signatures.iter().enumerate() {
-        println!("Processing transaction {}/{}: {}", i + 1, successful_signatures.len(), sig_info.signature);
-        
-        let transaction = match client.get_transaction_with_config(&sig_info.signature.parse()?, config).await {
-            Ok(tx) => tx,
-            Err(e) => {
-                println!("ERROR: Failed to get transaction {}: {}", sig_info.signature, e);
-                continue;
+/// `--compare`: scans the window of the same length ending right before `summary`'s and prints
+/// the change from it to `summary`
+async fn compare_with_previous_window(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    opts: ParseOptions,
+    mut summary: ParseSummary,
+    cli: &Cli,
+    cache: Option<&mut DiskCache>,
+) -> Result<()> {
+    let since = cli.since.context("--compare requires --since")?;
+    let until = cli.until.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs() as i64));
+    let (baseline_since, baseline_until) = (since - (until - since) - 1, since - 1);
+    info!("Scanning the previous window, {} to {}, to compare against", baseline_since, baseline_until);
+    let baseline_opts = ParseOptions {
+        since: Some(baseline_since),
+        until: Some(baseline_until),
+        mints: summary.mints.clone(),
+        lookup_tables: summary.lookup_tables.clone(),
+        ..opts
+    };
+    let mut baseline = fetch_and_parse(client, program_id, baseline_opts).await?;
+    // Windows without a signature have no block times to report, so report the requested ones
+    summary.block_time_window.get_or_insert((since, until));
+    baseline.block_time_window.get_or_insert((baseline_since, baseline_until));
+    save_cache(cache, &baseline);
+
+    let comparison = summary.compare(&baseline);
+    let mut output = Vec::new();
+    match cli.format {
+        OutputFormat::Json => writeln!(output, "{}", to_json(&comparison, cli.pretty)?)?,
+        _ => write!(output, "{}", comparison)?,
+    }
+    emit_output(cli.output_file.as_deref(), &output)?;
+    check_skipped(&summary, cli.max_skipped_fraction)?;
+    check_skipped(&baseline, cli.max_skipped_fraction)
+}
+
+/// `--check`: verifies the endpoint and program id with a few cheap requests, printing a pass or
+/// fail line for each, and fails if any of them did
+async fn run_checks(client: &RpcClient, program_id: &Pubkey, opts: ParseOptions) -> Result<()> {
+    let mut failed = 0;
+    let mut report = |check: &str, result: Result<String>| match result {
+        Ok(detail) => println!("PASS  {}: {}", check, detail),
+        Err(e) => {
+            failed += 1;
+            // RPC client errors repeat their causes in their own messages, so only the root one is kept
+            match e.chain().len() {
+                1 => println!("FAIL  {}: {}", check, e),
+                _ => println!("FAIL  {}: {}: {}", check, e, e.root_cause()),
             }
-        };
+        }
+    };
 
-        let versioned_tx = transaction.transaction.transaction.clone().decode().unwrap();
-
-        if let VersionedMessage::V0(msg) = versioned_tx.message {
-            let mut all_accounts: Vec<solana_sdk::pubkey::Pubkey> = Vec::new();
-            
-            // Add static accounts
-            all_accounts.extend_from_slice(&msg.account_keys);
-            
-            // Collect all writable lookup accounts first
-            let mut writable_lookup_accounts: Vec<solana_sdk::pubkey::Pubkey> = Vec::new();
-            let mut readonly_lookup_accounts: Vec<solana_sdk::pubkey::Pubkey> = Vec::new();
-            
-            for (_, lookup) in msg.address_table_lookups.iter().enumerate() {
-                // println!("  Fetching lookup table {}/{}: {}", lookup_idx + 1, msg.address_table_lookups.len(), lookup.account_key);
-                match client.get_account(&lookup.account_key).await {
-                    Ok(account_info) => {
-                        // Parse lookup table data (skip 56-byte header)
-                        if account_info.data.len() >= 56 {
-                            let addresses_data = &account_info.data[56..];
-                            let num_addresses = addresses_data.len() / 32;
-                            
-                            // Collect writable accounts from this lookup table
-                            for &index in &lookup.writable_indexes {
-                                if (index as usize) < num_addresses {
-                                    let start = (index as usize) * 32;
-                                    let end = start + 32;
-                                    if end <= addresses_data.len() {
-                                        let pubkey_bytes: [u8; 32] = addresses_data[start..end].try_into().unwrap();
-                                        writable_lookup_accounts.push(solana_sdk::pubkey::Pubkey::new_from_array(pubkey_bytes));
-                                    }
-                                }
-                            }
-                            
-                            // Collect readonly accounts from this lookup table
-                            for &index in &lookup.readonly_indexes {
-                                if (index as usize) < num_addresses {
-                                    let start = (index as usize) * 32;
-                                    let end = start + 32;
-                                    if end <= addresses_data.len() {
-                                        let pubkey_bytes: [u8; 32] = addresses_data[start..end].try_into().unwrap();
-                                        readonly_lookup_accounts.push(solana_sdk::pubkey::Pubkey::new_from_array(pubkey_bytes));
-                                    }
-                                }
-                            }
-                        }
+    let health = client.get_health().await.context("getHealth failed");
+    report("RPC health", health.map(|()| "node is healthy".to_string()));
+    let version = client.get_version().await.context("getVersion failed");
+    report("RPC version", version.map(|version| format!("solana-core {}", version.solana_core)));
+
+    let program = client.get_account(program_id).await.context("getAccountInfo failed");
+    report(
+        "Program",
+        program.and_then(|account| {
+            ensure!(account.executable, "{} is not an executable program (owner {})", program_id, account.owner);
+            Ok(format!("{} is deployed (owner {})", program_id, account.owner))
+        }),
+    );
+
+    // The pipeline end to end: list the newest successful transaction, then fetch and parse it
+    let recent = async {
+        let signature_opts = ParseOptions { max_transactions: Some(1), rpc_retries: opts.rpc_retries, ..ParseOptions::default() };
+        let signatures = fetch_signatures(client, program_id, &signature_opts).await.context("getSignaturesForAddress failed")?;
+        let newest = signatures.first().ok_or_else(|| anyhow!("no successful transactions of {} found", program_id))?;
+        let signature = Signature::from_str(&newest.signature)?;
+        let summary = fetch_and_parse_signature(client, program_id, &signature, opts).await?;
+        Ok(format!("{} at slot {} parsed, {} Kamino Lend instructions", signature, newest.slot, summary.records.len()))
+    };
+    report("Recent transaction", recent.await);
+
+    ensure!(failed == 0, "{} of 4 checks failed", failed);
+    Ok(())
+}
+
+/// `--watch-reserve`: prints every change of the followed reserves' liquidity with the change
+/// since the previous update, as text or as one JSON object per line
+async fn follow_reserves(ws_url: &str, cli: &Cli, mut mints: MintRegistry) -> Result<()> {
+    if cli.format == OutputFormat::Csv {
+        bail!(ExitError::Config("--watch-reserve only supports --format text, json or ndjson".to_string()));
+    }
+    let mut out = stream_writer(cli.output_file.as_deref())?;
+    let mut previous: HashMap<Pubkey, (u64, i128)> = HashMap::new();
+    // ndjson stays one update per line
+    let (format, pretty) = (cli.format, cli.pretty && cli.format == OutputFormat::Json);
+    let on_update = |reserve: &Pubkey, slot: u64, state: &Reserve| {
+        let liquidity = &state.liquidity;
+        let mint = liquidity.mint;
+        mints.insert(mint, liquidity.mint_decimals);
+        let borrowed = liquidity.borrowed_amount().round() as i128;
+        let line = match format {
+            OutputFormat::Text | OutputFormat::Table => {
+                let change = |current: i128, previous: Option<i128>| match previous {
+                    Some(previous) if previous != current => {
+                        let sign = if current > previous { "+" } else { "" };
+                        format!(" ({}{})", sign, mints.format_ui_amount(current - previous, &mint))
                     }
+                    _ => String::new(),
+                };
+                let before = previous.get(reserve);
+                format!(
+                    "Reserve {} {} at slot {}: available {}{}, borrowed {}{}, utilization {:.2}%\n",
+                    reserve,
+                    mints.symbol(&mint),
+                    slot,
+                    mints.format_ui_amount(liquidity.available_amount as i128, &mint),
+                    change(liquidity.available_amount as i128, before.map(|(available, _)| *available as i128)),
+                    mints.format_ui_amount(borrowed, &mint),
+                    change(borrowed, before.map(|(_, borrowed)| *borrowed)),
+                    liquidity.utilization() * 100.0
+                )
+            }
+            _ => {
+                let update = json!({
+                    "reserve": reserve.to_string(),
+                    "mint": mint.to_string(),
+                    "slot": slot,
+                    "available": mints.amount_json(liquidity.available_amount as i128, &mint),
+                    "borrowed": mints.amount_json(borrowed, &mint),
+                    "utilization": liquidity.utilization(),
+                });
+                match to_json(&update, pretty) {
+                    Ok(json) => format!("{}\n", json),
                     Err(e) => {
-                        println!("  ERROR: Failed to fetch lookup table {}: {}", lookup.account_key, e);
-                        println!("  Continuing without this lookup table...");
+                        warn!("Failed to write reserve update: {}", e);
+                        return;
                     }
                 }
             }
-            
-            // Add all writable lookup accounts
-            all_accounts.extend(writable_lookup_accounts);
-            
-            // Add all readonly lookup accounts  
-            all_accounts.extend(readonly_lookup_accounts);
-            
-            for instruction in msg.instructions {
-                if instruction.data.starts_with(FLASH_LOAN_DATA_BORROW_SIG) {
-                    let reserve_token_index: usize = instruction.accounts[FLASH_LOAN_TOKEN_ACCOUNT_KEY].into();
-                    
-                    let reserve_token = all_accounts[reserve_token_index];
-                    let le_amount_bytes: [u8; 8] = instruction.data[8..].try_into().unwrap();
-                    let amount = u64::from_le_bytes([le_amount_bytes[0], le_amount_bytes[1], le_amount_bytes[2], le_amount_bytes[3], le_amount_bytes[4], le_amount_bytes[5], le_amount_bytes[6], le_amount_bytes[7]]);
-
-                    if reserve_token == usdc_mint_key {
-                        flash_loan_borrow_usdc_amount += amount;
-                        println!("Flash loan borrow USDC: {:?}", amount);
-                    }
+        };
+        previous.insert(*reserve, (liquidity.available_amount, borrowed));
+        if let Err(e) = out.write_all(line.as_bytes()).and_then(|()| out.flush()) {
+            warn!("Failed to write reserve update: {}", e);
+        }
+    };
+    watch_reserves(ws_url, &cli.watch_reserves, cli.commitment.config(), on_update).await
+}
 
-                    if reserve_token == sol_mint_key {
-                        flash_loan_borrow_sol_amount += amount;
-                        println!("Flash loan borrow SOL: {:?}", amount);
-                    }
+/// Saves the lookup tables and mint decimals the scan resolved; a cache that cannot be written
+/// only costs the next run some fetches, so it does not fail this one
+fn save_cache(cache: Option<&mut DiskCache>, summary: &ParseSummary) {
+    if let Some(cache) = cache {
+        cache.update(summary);
+        if let Err(e) = cache.save() {
+            warn!("{:#}", e);
+        }
+    }
+}
 
-                    flash_loan_txs_count += 1;
-                }
+/// Fails the run when too many transactions were skipped, so automation can tell the summary is
+/// incomplete
+fn check_skipped(summary: &ParseSummary, max_fraction: f64) -> Result<()> {
+    let fraction = summary.skipped_fraction();
+    if fraction > max_fraction {
+        bail!(ExitError::Partial(format!(
+            "{} of {} transactions ({:.1}%) were skipped, more than --max-skipped-fraction {}",
+            summary.skipped_count(),
+            summary.transactions_scanned,
+            fraction * 100.0,
+            max_fraction
+        )));
+    }
+    Ok(())
+}
+
+/// Destination of records streamed as they are parsed: they cannot be written atomically, so the
+/// output file is created upfront and written to directly
+fn stream_writer(path: Option<&Path>) -> Result<Box<dyn Write>> {
+    match path {
+        Some(path) => {
+            let file = File::create(path).with_context(|| format!("failed to create output file {}", path.display()))?;
+            Ok(Box::new(file))
+        }
+        None => Ok(Box::new(io::stdout())),
+    }
+}
 
-                if instruction.data.starts_with(BORROW_OBLIGATION_DATA_SIG) {
-                    let reserve_token_index: usize = instruction.accounts[BORROW_OBLIGATION_TOKEN_ACCOUNT_KEY].into();
-                    let reserve_token = all_accounts[reserve_token_index];
+/// One `--format ndjson` line, flushed right away so consumers see every record as it is parsed
+fn write_ndjson_record(out: &mut dyn Write, record: &KaminoTransaction, summary: &ParseSummary) -> io::Result<()> {
+    let line = format!("{}\n", json!(csv_row(record, summary)));
+    out.write_all(line.as_bytes())?;
+    out.flush()
+}
 
-                    let le_amount_bytes: [u8; 8] = instruction.data[8..].try_into().unwrap();
-                    let amount = u64::from_le_bytes([le_amount_bytes[0], le_amount_bytes[1], le_amount_bytes[2], le_amount_bytes[3], le_amount_bytes[4], le_amount_bytes[5], le_amount_bytes[6], le_amount_bytes[7]]);
+/// The `--format json` summary as the last `--format ndjson` line, with `--summary`
+fn write_ndjson_summary(out: &mut dyn Write, summary: &ParseSummary, report: &ReportOptions) -> io::Result<()> {
+    let line = format!("{}\n", json_summary(summary, report));
+    out.write_all(line.as_bytes())?;
+    out.flush()
+}
 
-                    if reserve_token == usdc_mint_key {
-                        loan_borrow_usdc_amount += amount;
-                        println!("Borrow obligation USDC: {:?}", amount);
-                    }
+/// JSON output, indented with --pretty or on one line with --compact
+fn to_json(value: &impl Serialize, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
 
-                    if reserve_token == sol_mint_key {
-                        loan_borrow_sol_amount += amount;
-                        println!("Borrow obligation SOL: {:?}", amount);
-                    }
+/// Writes the rendered output to stdout, or replaces `path` with it atomically: the bytes go to a
+/// temporary file next to it that is then renamed over it, so readers never see a partial file
+fn emit_output(path: Option<&Path>, output: &[u8]) -> Result<()> {
+    let Some(path) = path else {
+        io::stdout().lock().write_all(output)?;
+        return Ok(());
+    };
+    let file_name = path.file_name().with_context(|| format!("output path {} has no file name", path.display()))?;
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name.to_string_lossy(), std::process::id()));
+    let write = || -> Result<()> {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(output)?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        std::fs::remove_file(&temp_path).ok();
+        return Err(e.context(format!("failed to write output file {}", path.display())));
+    }
+    info!("Wrote output to {}", path.display());
+    Ok(())
+}
+
+/// One `--format csv` row per parsed instruction
+fn csv_row<'a>(record: &'a KaminoTransaction, summary: &ParseSummary) -> CsvRow<'a> {
+    let amount = record.transaction_type.amount();
+    let decimals = record.reserve_token.and_then(|mint| summary.mints.get(&mint));
+    let split = match record.transaction_type {
+        TransactionType::Repay { split, .. } => split,
+        _ => None,
+    };
+    CsvRow {
+        signature: &record.signature,
+        slot: record.slot,
+        block_time: record.block_time,
+        instruction_type: record.transaction_type.name(),
+        inner_index: record.inner_index,
+        reserve_mint: record.reserve_token.map(|mint| mint.to_string()),
+        raw_amount: amount,
+        ui_amount: amount
+            .zip(decimals)
+            .map(|(amount, decimals)| amount as f64 / 10f64.powi(decimals as i32)),
+        principal_amount: split.map(|split| split.principal),
+        interest_amount: split.map(|split| split.interest),
+        failed: record.failed,
+    }
+}
+
+/// `--format json` summary object: the summary itself plus the optional report sections
+fn json_summary(summary: &ParseSummary, report: &ReportOptions) -> serde_json::Value {
+    let ui_amounts = |amounts: &HashMap<Pubkey, u64>| -> serde_json::Map<String, serde_json::Value> {
+        amounts
+            .iter()
+            .map(|(mint, amount)| (mint.to_string(), summary.mints.amount_json(*amount as i128, mint)))
+            .collect()
+    };
+    let mut value = serde_json::to_value(summary).expect("summary serializes to JSON");
+    if report.by_user {
+        value["borrows_by_owner"] = summary
+            .borrows_by_owner
+            .iter()
+            .map(|(owner, amounts)| (owner.to_string(), json!(ui_amounts(amounts))))
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    if report.by_referrer {
+        value["borrows_by_referrer"] = summary
+            .borrows_by_referrer
+            .iter()
+            .map(|(referrer, amounts)| (referrer_label(referrer), json!(ui_amounts(amounts))))
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    if report.by_market {
+        value["borrows_by_market"] = summary
+            .borrows_by_market
+            .iter()
+            .map(|(market, mixes)| {
+                let by_mint = |volume: fn(&BorrowMix) -> u64| -> HashMap<Pubkey, u64> {
+                    mixes.iter().map(|(mint, mix)| (*mint, volume(mix))).filter(|(_, amount)| *amount > 0).collect()
+                };
+                let market_json = json!({
+                    "name": known_market_name(market),
+                    "flash_loan_borrows": ui_amounts(&by_mint(|mix| mix.flash_loan)),
+                    "obligation_borrows": ui_amounts(&by_mint(|mix| mix.obligation)),
+                });
+                (market.to_string(), market_json)
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    if report.by_obligation {
+        value["obligations"] = summary
+            .by_obligation
+            .iter()
+            .map(|(obligation, activity)| {
+                let activity = json!({
+                    "borrowed": ui_amounts(&activity.borrowed),
+                    "repaid": ui_amounts(&activity.repaid),
+                    "collateral_deposits": activity.collateral_deposits,
+                    "collateral_withdrawals": activity.collateral_withdrawals,
+                });
+                (obligation.to_string(), activity)
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+    }
+    if report.stats {
+        let stats_json = |name: &str| -> serde_json::Map<String, serde_json::Value> {
+            summary
+                .amount_stats(name)
+                .iter()
+                .map(|(mint, stats)| {
+                    let ui = |amount: u64| summary.mints.amount_json(amount as i128, mint);
+                    let value = json!({
+                        "count": stats.count,
+                        "mean": stats.mean / 10f64.powi(summary.mints.get(mint).unwrap_or(0) as i32),
+                        "median": ui(stats.median),
+                        "min": ui(stats.min),
+                        "max": ui(stats.max),
+                        "p90": ui(stats.p90),
+                    });
+                    (mint.to_string(), value)
+                })
+                .collect()
+        };
+        value["stats"] = json!({
+            "flash_loan_borrows": stats_json("flash_borrow"),
+            "obligation_borrows": stats_json("borrow"),
+        });
+    }
+    if report.borrow_mix {
+        let by_mint: serde_json::Map<String, serde_json::Value> = summary
+            .borrow_mix()
+            .iter()
+            .filter_map(|(mint, mix)| Some((mint.to_string(), json!({ "flash_loan_share": mix.flash_loan_share()? }))))
+            .collect();
+        let overall = overall_borrow_mix(summary, report).map(|(share, basis)| json!({ "flash_loan_share": share, "basis": basis }));
+        value["borrow_mix"] = json!({ "by_mint": by_mint, "overall": overall });
+    }
+    if let Some(width) = report.buckets {
+        let buckets: Vec<_> = summary
+            .time_buckets(width.duration())
+            .iter()
+            .map(|(start, bucket)| {
+                json!({
+                    "start": rfc3339(*start),
+                    "transactions": bucket.transactions,
+                    "borrows": bucket.borrows,
+                    "borrowed": ui_amounts(&bucket.borrowed),
+                    "borrowed_usd": report.prices.as_ref().map(|prices| prices.usd_volume(&bucket.borrowed, &summary.mints).total),
+                })
+            })
+            .collect();
+        value["buckets"] = json!({ "width": width.name(), "buckets": buckets });
+    }
+    if let Some(prices) = &report.prices {
+        let usd_json = |volume: UsdVolume| {
+            json!({
+                "by_mint": volume.by_mint.iter().map(|(mint, usd)| (mint.to_string(), json!(usd))).collect::<serde_json::Map<_, _>>(),
+                "total": volume.total,
+                "unpriced": volume.unpriced.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+            })
+        };
+        value["usd"] = json!({
+            "flash_loan_borrows": usd_json(usd_volume(summary, prices, &summary.flash_loan_borrow_amounts)),
+            "obligation_borrows": usd_json(usd_volume(summary, prices, &summary.loan_borrow_amounts)),
+        });
+    }
+    value
+}
+
+/// Human-readable breakdown printed in text mode, followed by the optional report sections
+fn write_text_summary(out: &mut impl Write, summary: &ParseSummary, report: &ReportOptions) -> io::Result<()> {
+    writeln!(out)?;
+    write!(out, "{}", summary.display_sorted(report.sort, report.top_n))?;
+    write_report_sections(out, summary, report)
+}
+
+/// `--format table`: the per-token table followed by the same optional report sections as the
+/// text summary
+fn write_table_summary(out: &mut impl Write, summary: &ParseSummary, report: &ReportOptions) -> io::Result<()> {
+    writeln!(out, "{}", summary.display_table(report.sort))?;
+    write_report_sections(out, summary, report)
+}
+
+/// The optional `--by-user`, `--stats`, `--prices` etc. sections shared by text and table output
+fn write_report_sections(out: &mut impl Write, summary: &ParseSummary, report: &ReportOptions) -> io::Result<()> {
+    if report.skipped {
+        for signature in &summary.fetch_errors {
+            writeln!(out, "Skipped (fetch error): {}", signature)?;
+        }
+        for signature in &summary.lookup_errors {
+            writeln!(out, "Skipped (unresolved lookup table): {}", signature)?;
+        }
+        for table in &summary.unresolved_lookup_tables {
+            writeln!(out, "Unresolved lookup table: {}", table)?;
+        }
+    }
+
+    if report.by_user {
+        let owners: BTreeMap<_, _> = summary.borrows_by_owner.iter().collect();
+        for (owner, amounts) in owners {
+            for (mint, amount) in summary.sorted_mints(amounts, report.sort) {
+                let amount = summary.format_ui_amount(mint, amount as i128);
+                writeln!(out, "Borrowed by {} {}: {}", owner, summary.mints.symbol(mint), amount)?;
+            }
+        }
+    }
 
-                    loan_txs_count += 1;
+    if report.by_referrer {
+        // "no referrer" first, then referrers by address
+        let referrers: BTreeMap<_, _> = summary.borrows_by_referrer.iter().collect();
+        for (referrer, amounts) in referrers {
+            for (mint, amount) in summary.sorted_mints(amounts, report.sort) {
+                let amount = summary.format_ui_amount(mint, amount as i128);
+                writeln!(out, "Borrowed via {} {}: {}", referrer_label(referrer), summary.mints.symbol(mint), amount)?;
+            }
+        }
+    }
+
+    if report.by_market {
+        let mut markets: Vec<_> = summary.borrows_by_market.iter().map(|(market, mixes)| (market_label(market), mixes)).collect();
+        markets.sort_by(|a, b| a.0.cmp(&b.0));
+        for (market, mixes) in markets {
+            let mut mixes: Vec<_> = mixes.iter().collect();
+            mixes.sort_by_key(|(mint, _)| (summary.mints.symbol(mint), **mint));
+            for (mint, mix) in mixes {
+                writeln!(
+                    out,
+                    "Borrowed in {} {}: {} by flash loans, {} by obligation borrows",
+                    market,
+                    summary.mints.symbol(mint),
+                    summary.format_ui_amount(mint, mix.flash_loan as i128),
+                    summary.format_ui_amount(mint, mix.obligation as i128)
+                )?;
+            }
+        }
+    }
+
+    if report.by_obligation {
+        let mut obligations: Vec<_> = summary.by_obligation.iter().collect();
+        obligations.sort_by_key(|(obligation, _)| **obligation);
+        for (obligation, activity) in obligations {
+            let amounts = |amounts: &HashMap<Pubkey, u64>| -> String {
+                let mut amounts: Vec<String> = amounts
+                    .iter()
+                    .map(|(mint, amount)| format!("{} {}", summary.format_ui_amount(mint, *amount as i128), summary.mints.symbol(mint)))
+                    .collect();
+                amounts.sort();
+                if amounts.is_empty() {
+                    return "nothing".to_string();
                 }
+                amounts.join(", ")
+            };
+            writeln!(
+                out,
+                "Obligation {}: borrowed {}, repaid {}, {} collateral deposits, {} collateral withdrawals",
+                obligation,
+                amounts(&activity.borrowed),
+                amounts(&activity.repaid),
+                activity.collateral_deposits,
+                activity.collateral_withdrawals
+            )?;
+        }
+    }
+
+    if report.stats {
+        for (label, name) in [("Flash loan borrow", "flash_borrow"), ("Borrow obligation", "borrow")] {
+            let stats: BTreeMap<_, _> = summary.amount_stats(name).into_iter().collect();
+            for (mint, stats) in &stats {
+                write_amount_stats(out, summary, label, mint, stats)?;
             }
         }
     }
 
-    println!();
-    println!("Breakdown for USDC and SOL loans on Kamino in {:?} latest blocks", block_time_diff);
+    if report.borrow_mix {
+        let mut mix: Vec<_> = summary.borrow_mix().into_iter().collect();
+        mix.sort_by_key(|(mint, _)| (summary.mints.symbol(mint), *mint));
+        for (mint, mix) in mix {
+            if let Some(share) = mix.flash_loan_share() {
+                writeln!(out, "Borrow mix {}: {}", summary.mints.symbol(&mint), format_borrow_mix(share))?;
+            }
+        }
+        if let Some((share, basis)) = overall_borrow_mix(summary, report) {
+            writeln!(out, "Borrow mix overall (by {}): {}", basis, format_borrow_mix(share))?;
+        }
+    }
 
-    println!("Flash Loan Borrow USDC: {:?}", flash_loan_borrow_usdc_amount as f64 / 1e6);
-    println!("Borrow obligation USDC: {:?}", loan_borrow_usdc_amount as f64 / 1e6);
-    println!("Flash Loan Borrow SOL: {:?}", flash_loan_borrow_sol_amount as f64 / 1e9);
-    println!("Borrow obligation SOL: {:?}", loan_borrow_sol_amount as f64 / 1e9);
+    if let Some(width) = report.buckets {
+        writeln!(out, "Activity by {} (UTC):", width.name())?;
+        for (start, bucket) in summary.time_buckets(width.duration()) {
+            let mut borrowed: Vec<String> = bucket
+                .borrowed
+                .iter()
+                .map(|(mint, amount)| format!("{} {}", summary.format_ui_amount(mint, *amount as i128), summary.mints.symbol(mint)))
+                .collect();
+            borrowed.sort();
+            let borrowed = if borrowed.is_empty() { "nothing".to_string() } else { borrowed.join(", ") };
+            let usd = match &report.prices {
+                Some(prices) => format!(" (${:.2})", prices.usd_volume(&bucket.borrowed, &summary.mints).total),
+                None => String::new(),
+            };
+            writeln!(
+                out,
+                "  {}: {} transactions, {} borrows, borrowed {}{}",
+                rfc3339(start),
+                bucket.transactions,
+                bucket.borrows,
+                borrowed,
+                usd
+            )?;
+        }
+    }
+
+    if let Some(prices) = &report.prices {
+        for (label, amounts) in [
+            ("Flash loan borrow", &summary.flash_loan_borrow_amounts),
+            ("Borrow obligation", &summary.loan_borrow_amounts),
+        ] {
+            let volume = usd_volume(summary, prices, amounts);
+            let mut by_mint: Vec<_> = volume.by_mint.iter().collect();
+            by_mint.sort_by(|(a_mint, a), (b_mint, b)| b.total_cmp(a).then(a_mint.cmp(b_mint)));
+            let others = report.top_n.map_or(0, |top_n| by_mint.len().saturating_sub(top_n));
+            by_mint.truncate(by_mint.len() - others);
+            for (mint, usd) in by_mint {
+                writeln!(out, "{} USD {}: ${:.2}", label, summary.mints.symbol(mint), usd)?;
+            }
+            if others > 0 {
+                writeln!(out, "{} USD ... and {} others", label, others)?;
+            }
+            writeln!(out, "{} USD total: ${:.2}", label, volume.total)?;
+        }
+    }
 
-    println!("Flash loan txs count: {:?}", flash_loan_txs_count);
-    println!("Loan txs count: {:?}", loan_txs_count);
-     
-     // Display summary of instruction types found
-     println!("\n{}", "=".repeat(60));
-     println!("📊 KAMINO LEND INSTRUCTION SUMMARY");
-     println!("{}", "=".repeat(60));
-     
-     Ok(())
+    // Display summary of instruction types found
+    writeln!(out, "\n{}", "=".repeat(60))?;
+    writeln!(out, "📊 KAMINO LEND INSTRUCTION SUMMARY")?;
+    writeln!(out, "{}", "=".repeat(60))?;
+    Ok(())
+}
+
+fn format_borrow_mix(flash_loan_share: f64) -> String {
+    format!("{:.1}% flash loans, {:.1}% obligation borrows", flash_loan_share * 100.0, (1.0 - flash_loan_share) * 100.0)
+}
+
+/// Flash loan share of all borrows and what it was measured by: mints cannot be added up in raw
+/// or whole tokens, so volume only counts with USD prices; otherwise each borrow counts once
+fn overall_borrow_mix(summary: &ParseSummary, report: &ReportOptions) -> Option<(f64, &'static str)> {
+    let (flash_loan, obligation, basis) = match &report.prices {
+        Some(prices) => {
+            let flash_loan = usd_volume(summary, prices, &summary.flash_loan_borrow_amounts).total;
+            let obligation = usd_volume(summary, prices, &summary.loan_borrow_amounts).total;
+            (flash_loan, obligation, "USD volume")
+        }
+        None => (summary.flash_loan_txs_count as f64, summary.loan_txs_count as f64, "count"),
+    };
+    (flash_loan + obligation > 0.0).then(|| (flash_loan / (flash_loan + obligation), basis))
+}
+
+/// `--list-reserves` output: one line per reserve with its token name and liquidity mint, or a
+/// JSON array of them
+fn write_reserve_listing(out: &mut impl Write, reserves: &[(Pubkey, Reserve)], mints: &MintRegistry, format: OutputFormat, pretty: bool) -> io::Result<()> {
+    if format == OutputFormat::Json {
+        let reserves: Vec<_> = reserves
+            .iter()
+            .map(|(address, reserve)| {
+                json!({
+                    "reserve": address.to_string(),
+                    "liquidity_mint": reserve.liquidity.mint.to_string(),
+                    "token_name": reserve.config.token_name,
+                    "status": reserve.config.status,
+                })
+            })
+            .collect();
+        return writeln!(out, "{}", to_json(&reserves, pretty)?);
+    }
+    for (address, reserve) in reserves {
+        let name = match reserve.config.token_name.as_str() {
+            "" => mints.symbol(&reserve.liquidity.mint),
+            name => name.to_string(),
+        };
+        writeln!(out, "{} {}: liquidity mint {}", address, name, reserve.liquidity.mint)?;
+    }
+    Ok(())
+}
+
+/// `--list-instructions` output: every program instruction per transaction, then a count per
+/// distinct discriminator, most frequent first
+fn write_instruction_listings(out: &mut impl Write, listings: &[InstructionListing]) -> io::Result<()> {
+    let mut counts: HashMap<[u8; 8], u64> = HashMap::new();
+    for listing in listings {
+        writeln!(out, "{} (slot {})", listing.signature, listing.slot)?;
+        for (instruction_index, discriminator) in &listing.instructions {
+            let name = instruction_name(discriminator).unwrap_or("unknown");
+            writeln!(out, "  #{} {} {}", instruction_index, hex(discriminator), name)?;
+            *counts.entry(*discriminator).or_insert(0) += 1;
+        }
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    writeln!(out)?;
+    writeln!(out, "Discriminators across {} transactions:", listings.len())?;
+    for (discriminator, count) in counts {
+        writeln!(out, "{} {}: {}", hex(&discriminator), instruction_name(&discriminator).unwrap_or("unknown"), count)?;
+    }
+    Ok(())
+}
+
+/// `--signature` text output: every parsed instruction of the transaction with its decoded fields
+fn write_instruction_breakdown(out: &mut impl Write, summary: &ParseSummary) -> io::Result<()> {
+    let Some(first) = summary.records.first() else {
+        writeln!(out, "No Kamino Lend instructions matched")?;
+        return Ok(());
+    };
+    let failed = if first.failed { " (failed)" } else { "" };
+    writeln!(out, "Transaction {} at slot {}{}", first.signature, first.slot, failed)?;
+    for record in &summary.records {
+        let position = match record.inner_index {
+            Some(inner_index) => format!("#{}.{} (CPI)", record.instruction_index, inner_index),
+            None => format!("#{}", record.instruction_index),
+        };
+        match (record.reserve_token, record.transaction_type.amount()) {
+            (Some(mint), Some(amount)) => writeln!(
+                out,
+                "{} {} {}: {}",
+                position,
+                record.transaction_type.name(),
+                summary.mints.symbol(&mint),
+                summary.format_ui_amount(&mint, amount as i128)
+            )?,
+            _ => writeln!(out, "{} {}", position, record.transaction_type.name())?,
+        }
+        writeln!(out, "    {:?}", record.transaction_type)?;
+    }
+    Ok(())
+}
+
+/// `--by-referrer` key of a referrer token state account
+fn referrer_label(referrer: &Option<Pubkey>) -> String {
+    match referrer {
+        Some(referrer) => referrer.to_string(),
+        None => "no referrer".to_string(),
+    }
+}
+
+/// Values per-mint totals with the `--prices` table, warning about mints left out of the total
+fn usd_volume(summary: &ParseSummary, prices: &PriceTable, amounts: &HashMap<Pubkey, u64>) -> UsdVolume {
+    let volume = prices.usd_volume(amounts, &summary.mints);
+    for mint in &volume.unpriced {
+        warn!(%mint, "No USD price; excluding it from the USD total");
+    }
+    volume
+}
+
+/// One `--stats` line for a mint
+fn write_amount_stats(
+    out: &mut impl Write,
+    summary: &ParseSummary,
+    label: &str,
+    mint: &Pubkey,
+    stats: &AmountStats,
+) -> io::Result<()> {
+    let mean = summary.mints.format_ui_value(stats.mean / 10f64.powi(summary.mints.get(mint).unwrap_or(0) as i32), mint);
+    writeln!(
+        out,
+        "{} size {}: n={} mean={} median={} min={} max={} p90={}",
+        label,
+        summary.mints.symbol(mint),
+        stats.count,
+        mean,
+        summary.format_ui_amount(mint, stats.median as i128),
+        summary.format_ui_amount(mint, stats.min as i128),
+        summary.format_ui_amount(mint, stats.max as i128),
+        summary.format_ui_amount(mint, stats.p90 as i128),
+    )?;
+    Ok(())
+}
+
+/// Reads `--signatures-file`, or stdin for `-`
+fn load_signature_list(path: &Path) -> Result<Vec<Signature>> {
+    let (signatures, source) = if path == Path::new("-") {
+        (read_signature_list(io::stdin().lock())?, "stdin".to_string())
+    } else {
+        let file = File::open(path).with_context(|| format!("failed to open signature list {}", path.display()))?;
+        (read_signature_list(io::BufReader::new(file))?, path.display().to_string())
+    };
+    ensure!(!signatures.is_empty(), "no valid signatures in {}", source);
+    Ok(signatures)
+}
+
+/// Parses one `--only` instruction type, in kebab-case or snake_case
+fn parse_instruction_type(value: &str) -> Result<&'static str, String> {
+    transaction_type_name(value).ok_or_else(|| {
+        let known: Vec<String> = TRANSACTION_TYPE_NAMES.iter().map(|name| name.replace('_', "-")).collect();
+        format!("{:?} is not an instruction type; expected one of {}", value, known.join(", "))
+    })
+}
+
+/// Parses `--sample`, a share above 0 and at most 1
+fn parse_sample_rate(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate <= 1.0 => Ok(rate),
+        _ => Err(format!("{:?} is not a sampling rate above 0 and at most 1", value)),
+    }
+}
+
+/// Parses `--max-skipped-fraction`, a fraction between 0 and 1
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        _ => Err(format!("{:?} is not a fraction between 0 and 1", value)),
+    }
+}
+
+/// Parses `--since`/`--until` into a unix timestamp, from RFC3339 or a duration before now
+fn parse_time_bound(value: &str) -> Result<i64, String> {
+    let time = match humantime::parse_rfc3339_weak(value) {
+        Ok(time) => time,
+        Err(_) => {
+            let ago = humantime::parse_duration(value)
+                .map_err(|_| format!("expected an RFC3339 timestamp or a duration like 1h or 30m, got {:?}", value))?;
+            SystemTime::now() - ago
+        }
+    };
+    let since_epoch = time.duration_since(UNIX_EPOCH).map_err(|_| format!("{:?} is before 1970", value))?;
+    Ok(since_epoch.as_secs() as i64)
 }
@@ -0,0 +1,21 @@
+//! Names of Kamino Lend's lending markets.
+
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+
+/// Names of Kamino Lend's main lending markets, shown instead of their address
+pub const KNOWN_MARKETS: &[(Pubkey, &str)] = &[
+    (pubkey!("7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF"), "Main"),
+    (pubkey!("DxXdAyU3kCjnyggvHmY5nAwg5cRbbmdyX3npfDMjjMek"), "JLP"),
+    (pubkey!("ByYiZxp8QrdN9qbdtaAiePN8AAr3qvTPppNJDpf5DVJ5"), "Altcoins"),
+];
+
+/// Name of a known lending market, or `None` for any other market
+pub fn known_market_name(market: &Pubkey) -> Option<&'static str> {
+    KNOWN_MARKETS.iter().find(|(known, _)| known == market).map(|(_, name)| *name)
+}
+
+/// Name of the lending market, falling back to its address for markets without a known name
+pub fn market_label(market: &Pubkey) -> String {
+    known_market_name(market).map_or_else(|| market.to_string(), str::to_string)
+}
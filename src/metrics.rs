@@ -0,0 +1,116 @@
+//! Prometheus metrics for `--watch` mode, served over HTTP on `--metrics-port`.
+
+use crate::{KaminoTransaction, ParseSummary, TransactionType};
+use anyhow::{Context, Result};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Response, Server};
+use prometheus::{CounterVec, Encoder, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Running totals of a watch, updated per parsed instruction
+pub struct Metrics {
+    registry: Registry,
+    transactions_scanned: IntGauge,
+    instructions: IntCounterVec,
+    borrow_volume: CounterVec,
+    flash_loan_volume: CounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new_custom(Some("kamino".to_string()), None)?;
+        let transactions_scanned = IntGauge::new("transactions_scanned", "Transactions fetched from the logs subscription")?;
+        let instructions = IntCounterVec::new(
+            Opts::new("instructions_total", "Parsed Kamino Lend instructions by type"),
+            &["type", "status"],
+        )?;
+        let borrow_volume = CounterVec::new(
+            Opts::new("borrow_volume_total", "Borrowed amount in UI units of the reserve mint"),
+            &["mint", "symbol"],
+        )?;
+        let flash_loan_volume = CounterVec::new(
+            Opts::new("flash_loan_volume_total", "Flash borrowed amount in UI units of the reserve mint"),
+            &["mint", "symbol"],
+        )?;
+        registry.register(Box::new(transactions_scanned.clone()))?;
+        registry.register(Box::new(instructions.clone()))?;
+        registry.register(Box::new(borrow_volume.clone()))?;
+        registry.register(Box::new(flash_loan_volume.clone()))?;
+        Ok(Self {
+            registry,
+            transactions_scanned,
+            instructions,
+            borrow_volume,
+            flash_loan_volume,
+        })
+    }
+
+    /// Counts a parsed instruction; `summary` supplies the mint decimals and symbols and the
+    /// number of transactions scanned so far
+    pub fn observe(&self, record: &KaminoTransaction, summary: &ParseSummary) {
+        self.report(summary);
+        let status = if record.failed { "failed" } else { "success" };
+        self.instructions.with_label_values(&[record.transaction_type.name(), status]).inc();
+        if record.failed {
+            return;
+        }
+        let Some(mint) = record.reserve_token else {
+            return;
+        };
+        let (volume, amount) = match record.transaction_type {
+            TransactionType::Borrow { amount, .. } => (&self.borrow_volume, amount),
+            TransactionType::FlashBorrow { amount, .. } => (&self.flash_loan_volume, amount),
+            _ => return,
+        };
+        volume
+            .with_label_values(&[&mint.to_string(), &summary.mints.symbol(&mint)])
+            .inc_by(summary.ui_amount(&mint, amount as i128));
+    }
+
+    /// Updates the totals taken from the summary rather than counted per instruction
+    pub fn report(&self, summary: &ParseSummary) {
+        self.transactions_scanned.set(summary.transactions_scanned as i64);
+    }
+
+    /// Current values in the Prometheus text exposition format
+    pub fn render(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// Binds `port` on all interfaces and serves `metrics` on every path from a background task
+pub fn serve(metrics: Arc<Metrics>, port: u16) -> Result<()> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let make_service = make_service_fn(move |_| {
+        let metrics = metrics.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_request| {
+                let metrics = metrics.clone();
+                async move {
+                    let response = match metrics.render() {
+                        Ok(body) => Response::builder()
+                            .header(header::CONTENT_TYPE, TextEncoder::new().format_type())
+                            .body(Body::from(body)),
+                        Err(e) => Response::builder().status(500).body(Body::from(e.to_string())),
+                    };
+                    Ok::<_, Infallible>(response.expect("static response parts are valid"))
+                }
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr)
+        .with_context(|| format!("failed to bind metrics server to {}", addr))?
+        .serve(make_service);
+    info!("Serving Prometheus metrics on http://{}/metrics", addr);
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            warn!("Metrics server stopped: {}", e);
+        }
+    });
+    Ok(())
+}
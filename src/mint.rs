@@ -0,0 +1,199 @@
+//! Decimals-aware amount formatting backed by a cache of SPL mint metadata.
+
+use crate::rpc::fetch_mint_decimals;
+use anyhow::{Context, Result};
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use tracing::warn;
+
+/// Wrapped SOL, the liquidity mint of Kamino's SOL reserves; its decimals match native lamports,
+/// so no account fetch is needed. Liquid staking tokens such as mSOL or JitoSOL have their own
+/// mints and reserves and are never treated as SOL.
+pub const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+pub const NATIVE_MINT_DECIMALS: u8 = 9;
+
+/// Ticker symbols of common Kamino Lend liquidity mints, shown instead of their address
+pub const KNOWN_SYMBOLS: &[(Pubkey, &str)] = &[
+    (NATIVE_MINT, "SOL"),
+    (pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"), "USDC"),
+    (pubkey!("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"), "USDT"),
+    (pubkey!("2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo"), "PYUSD"),
+    (pubkey!("mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So"), "mSOL"),
+    (pubkey!("J1toso1uCk3RLmjorhTtrVwY9HJ7X8V9yYac6Y7kGCPn"), "JitoSOL"),
+    (pubkey!("bSo13r4TkiE4KumL71LsHTPpL2euBYLFx6h9HP3piy1"), "bSOL"),
+    (pubkey!("jupSoLaHXQiZZTSfEWMTRRgpnyFm8f6sZdosWBjx93v"), "JupSOL"),
+    (pubkey!("27G8MtK7VtTcCHkpASjSDdkWWYfoqT6ggEuKidVJidD4"), "JLP"),
+    (pubkey!("JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN"), "JUP"),
+    (pubkey!("jtojtomepa8beP8AuQc6eXt5FriJwfFMwQx2v2f9mCL"), "JTO"),
+    (pubkey!("DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263"), "BONK"),
+];
+
+/// Lazily fetched decimals per mint, so raw token amounts can be scaled for display, and the
+/// symbols mints are displayed with
+#[derive(Clone, Debug, Default)]
+pub struct MintRegistry {
+    decimals: HashMap<Pubkey, u8>,
+    /// Mints whose account could not be read, kept at 0 decimals for this run only
+    unreadable: HashSet<Pubkey>,
+    symbols: HashMap<Pubkey, String>,
+    /// Decimal places amounts are formatted with; `None` for each mint's own decimals
+    precision: Option<u8>,
+    /// JSON amounts carry the raw integer next to the UI value
+    raw_amounts: bool,
+}
+
+impl MintRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decimals of `mint` if they have been resolved already. Only the exact [`NATIVE_MINT`] is
+    /// known without a fetch.
+    pub fn get(&self, mint: &Pubkey) -> Option<u8> {
+        if *mint == NATIVE_MINT {
+            return Some(NATIVE_MINT_DECIMALS);
+        }
+        self.decimals.get(mint).copied()
+    }
+
+    /// Records decimals for `mint` without fetching, e.g. when they are known from elsewhere
+    pub fn insert(&mut self, mint: Pubkey, decimals: u8) {
+        self.decimals.insert(mint, decimals);
+    }
+
+    /// Display name of `mint`: a `--token-list` symbol, then a built-in one from
+    /// [`KNOWN_SYMBOLS`], else the address shortened to its first and last four characters
+    pub fn symbol(&self, mint: &Pubkey) -> String {
+        if let Some(symbol) = self.symbols.get(mint) {
+            return symbol.clone();
+        }
+        if let Some((_, symbol)) = KNOWN_SYMBOLS.iter().find(|(known, _)| known == mint) {
+            return symbol.to_string();
+        }
+        let address = mint.to_string();
+        format!("{}…{}", &address[..4], &address[address.len() - 4..])
+    }
+
+    /// Records a symbol for `mint`, taking precedence over the built-in ones
+    pub fn insert_symbol(&mut self, mint: Pubkey, symbol: String) {
+        self.symbols.insert(mint, symbol);
+    }
+
+    /// Adds the symbols of a JSON object mapping mint addresses to symbols, e.g.
+    /// `{"EPjF...Dt1v": "USDC"}`
+    pub fn load_token_list(&mut self, path: &Path) -> Result<()> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("failed to read token list {}", path.display()))?;
+        let symbols: HashMap<String, String> =
+            serde_json::from_str(&json).with_context(|| format!("invalid token list {}", path.display()))?;
+        for (mint, symbol) in symbols {
+            let mint = Pubkey::from_str(&mint).with_context(|| format!("invalid mint {:?} in {}", mint, path.display()))?;
+            self.insert_symbol(mint, symbol);
+        }
+        Ok(())
+    }
+
+    /// Returns the decimals of `mint`, fetching the mint account on first use. A mint that
+    /// cannot be read is cached as 0 decimals, so its amounts are reported raw.
+    pub async fn resolve(&mut self, client: &RpcClient, mint: &Pubkey) -> u8 {
+        if let Some(decimals) = self.get(mint) {
+            return decimals;
+        }
+        let decimals = match fetch_mint_decimals(client, mint).await {
+            Ok(decimals) => decimals,
+            Err(e) => {
+                warn!(%mint, "Failed to fetch decimals, reporting raw amounts for this mint: {}", e);
+                self.unreadable.insert(*mint);
+                0
+            }
+        };
+        self.decimals.insert(*mint, decimals);
+        decimals
+    }
+
+    /// Decimals read from mint accounts or inserted, leaving out mints that could not be read, e.g.
+    /// to persist them between runs
+    pub fn known_decimals(&self) -> impl Iterator<Item = (Pubkey, u8)> + '_ {
+        self.decimals
+            .iter()
+            .filter(|(mint, _)| !self.unreadable.contains(mint))
+            .map(|(mint, decimals)| (*mint, *decimals))
+    }
+
+    /// Scales a raw amount by the mint's decimals; mints without known decimals stay raw
+    pub fn ui_amount(&self, raw: i128, mint: &Pubkey) -> f64 {
+        let decimals = self.get(mint).unwrap_or(0);
+        raw as f64 / 10f64.powi(decimals as i32)
+    }
+
+    /// Formats amounts with exactly `precision` decimal places, rounding half away from zero,
+    /// instead of each mint's own decimals with trailing zeros dropped
+    pub fn set_precision(&mut self, precision: Option<u8>) {
+        self.precision = precision;
+    }
+
+    /// Decimal rendering of a raw amount, computed on integers so large totals stay exact, e.g.
+    /// 1500000 with 6 decimals is "1.5", or "1.50" with a precision of 2
+    pub fn format_ui_amount(&self, raw: i128, mint: &Pubkey) -> String {
+        let decimals = self.get(mint).unwrap_or(0) as u32;
+        let sign = if raw < 0 { "-" } else { "" };
+        let (mut units, places) = (raw.unsigned_abs(), self.precision.map_or(decimals, u32::from));
+        if places < decimals {
+            let dropped = 10u128.pow(decimals - places);
+            units = units / dropped + u128::from(units % dropped >= dropped.div_ceil(2));
+        } else {
+            units *= 10u128.pow(places - decimals);
+        }
+        let scale = 10u128.pow(places);
+        let (whole, fraction) = (units / scale, units % scale);
+        let sign = if units == 0 { "" } else { sign };
+        if places == 0 {
+            return format!("{}{}", sign, whole);
+        }
+        let fraction = format!("{:0width$}", fraction, width = places as usize);
+        let fraction = match self.precision {
+            Some(_) => fraction.as_str(),
+            None => fraction.trim_end_matches('0'),
+        };
+        if fraction.is_empty() {
+            return format!("{}{}", sign, whole);
+        }
+        format!("{}{}.{}", sign, whole, fraction)
+    }
+
+    /// Has [`MintRegistry::amount_json`] give the exact raw amount next to the UI value, for
+    /// reconciling against on-chain integers
+    pub fn set_raw_amounts(&mut self, raw_amounts: bool) {
+        self.raw_amounts = raw_amounts;
+    }
+
+    /// Whether amounts are output in raw units as well, see [`MintRegistry::set_raw_amounts`]
+    pub fn raw_amounts(&self) -> bool {
+        self.raw_amounts
+    }
+
+    /// A raw amount as JSON: its UI value as a number, or with raw amounts on, an object with
+    /// the exact `raw_amount` and the `ui_amount`
+    pub fn amount_json(&self, raw: i128, mint: &Pubkey) -> serde_json::Value {
+        let ui_amount = self.ui_amount(raw, mint);
+        match self.raw_amounts {
+            true => json!({ "raw_amount": raw, "ui_amount": ui_amount }),
+            false => json!(ui_amount),
+        }
+    }
+
+    /// Renders a value already in UI units that is not a whole number of raw units, such as a
+    /// mean, with the same decimal places as [`MintRegistry::format_ui_amount`]
+    pub fn format_ui_value(&self, value: f64, mint: &Pubkey) -> String {
+        let places = self.precision.unwrap_or_else(|| self.get(mint).unwrap_or(0)) as usize;
+        let formatted = format!("{:.*}", places, value);
+        match (self.precision, formatted.contains('.')) {
+            (None, true) => formatted.trim_end_matches('0').trim_end_matches('.').to_string(),
+            _ => formatted,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Decoding of Kamino Lend `Obligation` accounts: one owner's deposits and borrows in a lending
+//! market.
+//!
+//! Offsets follow the program's IDL and include the 8-byte Anchor account discriminator. Values
+//! are as of the obligation's last refresh, which the program performs before every instruction
+//! that reads them; they go stale in between.
+
+use crate::state::{big_fraction, scaled_fraction};
+use anyhow::{ensure, Result};
+use solana_sdk::pubkey::Pubkey;
+
+/// Anchor discriminator of `Obligation` accounts, `sha256("account:Obligation")[..8]`
+pub const OBLIGATION_DISCRIMINATOR: [u8; 8] = [0xa8, 0xce, 0x8d, 0x6a, 0x58, 0x4c, 0xac, 0xa7];
+pub const OBLIGATION_SIZE: usize = 3344;
+
+const TAG_OFFSET: usize = 8;
+const LAST_UPDATE_SLOT_OFFSET: usize = 16;
+const LENDING_MARKET_OFFSET: usize = 32;
+const OWNER_OFFSET: usize = 64;
+const DEPOSITED_VALUE_OFFSET: usize = 1192;
+const BORROW_FACTOR_ADJUSTED_DEBT_VALUE_OFFSET: usize = 2208;
+const ALLOWED_BORROW_VALUE_OFFSET: usize = 2240;
+const UNHEALTHY_BORROW_VALUE_OFFSET: usize = 2256;
+const REFERRER_OFFSET: usize = 2288;
+
+const DEPOSITS_OFFSET: usize = 96;
+const DEPOSITS_LEN: usize = 8;
+const COLLATERAL_SIZE: usize = 136;
+// Within an ObligationCollateral entry
+const COLLATERAL_AMOUNT_OFFSET: usize = 32;
+const COLLATERAL_MARKET_VALUE_OFFSET: usize = 40;
+
+const BORROWS_OFFSET: usize = 1208;
+const BORROWS_LEN: usize = 5;
+const LIQUIDITY_SIZE: usize = 200;
+// Within an ObligationLiquidity entry
+const LIQUIDITY_RATE_OFFSET: usize = 32;
+const LIQUIDITY_BORROWED_OFFSET: usize = 88;
+
+/// A Kamino Lend obligation. Values are in the market's quote currency, usually USD.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Obligation {
+    /// Kind of position the obligation was opened as, e.g. 0 for vanilla, 1 for multiply
+    pub tag: u64,
+    pub last_update_slot: u64,
+    pub lending_market: Pubkey,
+    pub owner: Pubkey,
+    /// Collateral deposits, empty slots left out
+    pub deposits: Vec<ObligationDeposit>,
+    /// Debts, empty slots left out
+    pub borrows: Vec<ObligationBorrow>,
+    pub deposited_value: f64,
+    /// Debt value with each borrow scaled by its reserve's borrow factor
+    pub borrow_factor_adjusted_debt_value: f64,
+    /// Most the adjusted debt may reach through new borrows or withdrawals
+    pub allowed_borrow_value: f64,
+    /// Adjusted debt at which the obligation can be liquidated
+    pub unhealthy_borrow_value: f64,
+    /// Referrer receiving part of the borrow fees, if any
+    pub referrer: Option<Pubkey>,
+}
+
+/// An obligation's collateral in one reserve
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObligationDeposit {
+    pub reserve: Pubkey,
+    /// Collateral tokens (cTokens) of the reserve, in raw units
+    pub deposited_amount: u64,
+    pub market_value: f64,
+}
+
+/// An obligation's debt in one reserve
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObligationBorrow {
+    pub reserve: Pubkey,
+    /// Cumulative borrow rate of the reserve when the debt was last refreshed
+    pub cumulative_borrow_rate: f64,
+    /// Debt including the interest accrued up to that refresh, in raw units
+    pub borrowed_amount: f64,
+}
+
+impl Obligation {
+    /// Decodes an obligation account, checking its discriminator and size
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        ensure!(data.len() >= 8 && data[..8] == OBLIGATION_DISCRIMINATOR, "account is not a Kamino Lend obligation");
+        Self::from_layout(data)
+    }
+
+    /// Decodes the obligation layout without checking the discriminator
+    pub(crate) fn from_layout(data: &[u8]) -> Result<Self> {
+        ensure!(data.len() >= OBLIGATION_SIZE, "account is too short to be an obligation");
+        let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().expect("slice is 8 bytes"));
+        let pubkey_at = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).expect("slice is 32 bytes");
+        let deposits = (0..DEPOSITS_LEN)
+            .map(|i| DEPOSITS_OFFSET + i * COLLATERAL_SIZE)
+            .map(|entry| ObligationDeposit {
+                reserve: pubkey_at(entry),
+                deposited_amount: u64_at(entry + COLLATERAL_AMOUNT_OFFSET),
+                market_value: scaled_fraction(&data[entry + COLLATERAL_MARKET_VALUE_OFFSET..]),
+            })
+            .filter(|deposit| deposit.reserve != Pubkey::default())
+            .collect();
+        let borrows = (0..BORROWS_LEN)
+            .map(|i| BORROWS_OFFSET + i * LIQUIDITY_SIZE)
+            .map(|entry| ObligationBorrow {
+                reserve: pubkey_at(entry),
+                cumulative_borrow_rate: big_fraction(&data[entry + LIQUIDITY_RATE_OFFSET..]),
+                borrowed_amount: scaled_fraction(&data[entry + LIQUIDITY_BORROWED_OFFSET..]),
+            })
+            .filter(|borrow| borrow.reserve != Pubkey::default())
+            .collect();
+        let referrer = pubkey_at(REFERRER_OFFSET);
+        Ok(Self {
+            tag: u64_at(TAG_OFFSET),
+            last_update_slot: u64_at(LAST_UPDATE_SLOT_OFFSET),
+            lending_market: pubkey_at(LENDING_MARKET_OFFSET),
+            owner: pubkey_at(OWNER_OFFSET),
+            deposits,
+            borrows,
+            deposited_value: scaled_fraction(&data[DEPOSITED_VALUE_OFFSET..]),
+            borrow_factor_adjusted_debt_value: scaled_fraction(&data[BORROW_FACTOR_ADJUSTED_DEBT_VALUE_OFFSET..]),
+            allowed_borrow_value: scaled_fraction(&data[ALLOWED_BORROW_VALUE_OFFSET..]),
+            unhealthy_borrow_value: scaled_fraction(&data[UNHEALTHY_BORROW_VALUE_OFFSET..]),
+            referrer: (referrer != Pubkey::default()).then_some(referrer),
+        })
+    }
+
+    /// How far the obligation is from liquidation: the adjusted debt it may reach before becoming
+    /// liquidatable over the adjusted debt it has, below 1 once it can be liquidated. `None`
+    /// without debt.
+    pub fn health_factor(&self) -> Option<f64> {
+        (self.borrow_factor_adjusted_debt_value > 0.0).then(|| self.unhealthy_borrow_value / self.borrow_factor_adjusted_debt_value)
+    }
+}
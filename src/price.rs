@@ -0,0 +1,62 @@
+//! USD valuation of per-mint totals from a user-supplied price table.
+
+use crate::mint::MintRegistry;
+use anyhow::{Context, Result};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// USD price per whole token, keyed by mint
+#[derive(Clone, Debug, Default)]
+pub struct PriceTable {
+    prices: HashMap<Pubkey, f64>,
+}
+
+/// USD value of a set of per-mint totals
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsdVolume {
+    pub by_mint: HashMap<Pubkey, f64>,
+    /// Sum over the priced mints only
+    pub total: f64,
+    /// Mints with a non-zero total but no price, left out of `total`
+    pub unpriced: Vec<Pubkey>,
+}
+
+impl PriceTable {
+    /// Reads a JSON object mapping mint addresses to USD prices, e.g. `{"EPjF...Dt1v": 1.0}`
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("failed to read prices {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("invalid prices file {}", path.display()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        let raw: HashMap<String, f64> = serde_json::from_str(json)?;
+        let prices = raw
+            .into_iter()
+            .map(|(mint, price)| Ok((Pubkey::from_str(&mint).with_context(|| format!("invalid mint {:?}", mint))?, price)))
+            .collect::<Result<_>>()?;
+        Ok(Self { prices })
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<f64> {
+        self.prices.get(mint).copied()
+    }
+
+    /// Values raw per-mint `amounts`, scaled by the decimals in `mints`
+    pub fn usd_volume(&self, amounts: &HashMap<Pubkey, u64>, mints: &MintRegistry) -> UsdVolume {
+        let mut volume = UsdVolume::default();
+        for (mint, amount) in amounts {
+            match self.get(mint) {
+                Some(price) => {
+                    let usd = mints.ui_amount(*amount as i128, mint) * price;
+                    volume.by_mint.insert(*mint, usd);
+                    volume.total += usd;
+                }
+                None if *amount > 0 => volume.unpriced.push(*mint),
+                None => {}
+            }
+        }
+        volume
+    }
+}
@@ -0,0 +1,210 @@
+//! Decoding of Kamino Lend `Reserve` accounts, and enumeration of a lending market's reserves.
+//!
+//! Offsets follow the program's IDL and include the 8-byte Anchor account discriminator. Only the
+//! fields the analyses use are decoded; padding and the rest of the config are skipped.
+
+use crate::state::{big_fraction, scaled_fraction};
+use anyhow::{ensure, Context, Result};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::pubkey::Pubkey;
+use tracing::warn;
+
+/// Anchor discriminator of `Reserve` accounts, `sha256("account:Reserve")[..8]`
+pub const RESERVE_DISCRIMINATOR: [u8; 8] = [0x2b, 0xf2, 0xcc, 0xca, 0x1a, 0xf7, 0x3b, 0x7f];
+pub const RESERVE_SIZE: usize = 8624;
+
+const VERSION_OFFSET: usize = 8;
+const LAST_UPDATE_SLOT_OFFSET: usize = 16;
+const LENDING_MARKET_OFFSET: usize = 32;
+
+// ReserveLiquidity starts at 128
+const LIQUIDITY_MINT_OFFSET: usize = 128;
+const LIQUIDITY_SUPPLY_VAULT_OFFSET: usize = 160;
+const LIQUIDITY_FEE_VAULT_OFFSET: usize = 192;
+const LIQUIDITY_AVAILABLE_AMOUNT_OFFSET: usize = 224;
+const LIQUIDITY_BORROWED_AMOUNT_OFFSET: usize = 232;
+const LIQUIDITY_MARKET_PRICE_OFFSET: usize = 248;
+const LIQUIDITY_MINT_DECIMALS_OFFSET: usize = 272;
+const LIQUIDITY_CUMULATIVE_BORROW_RATE_OFFSET: usize = 296;
+
+// ReserveCollateral starts at 2560
+const COLLATERAL_MINT_OFFSET: usize = 2560;
+const COLLATERAL_MINT_TOTAL_SUPPLY_OFFSET: usize = 2592;
+const COLLATERAL_SUPPLY_VAULT_OFFSET: usize = 2600;
+
+// ReserveConfig starts at 4856
+const CONFIG_STATUS_OFFSET: usize = 4856;
+const CONFIG_LOAN_TO_VALUE_OFFSET: usize = 4872;
+const CONFIG_LIQUIDATION_THRESHOLD_OFFSET: usize = 4873;
+const CONFIG_BORROW_FACTOR_OFFSET: usize = 5008;
+const CONFIG_DEPOSIT_LIMIT_OFFSET: usize = 5016;
+const CONFIG_BORROW_LIMIT_OFFSET: usize = 5024;
+// token_info.name, a zero-padded UTF-8 string
+const CONFIG_TOKEN_NAME_OFFSET: usize = 5032;
+const CONFIG_TOKEN_NAME_LEN: usize = 32;
+
+/// A Kamino Lend reserve: one mint's liquidity in a lending market, and the collateral token
+/// minted against it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reserve {
+    pub version: u64,
+    /// Slot the reserve was last refreshed at; borrowed amounts and rates are as of this slot
+    pub last_update_slot: u64,
+    pub lending_market: Pubkey,
+    pub liquidity: ReserveLiquidity,
+    pub collateral: ReserveCollateral,
+    pub config: ReserveConfig,
+}
+
+/// Liquidity side of a reserve, amounts in raw units of its mint
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReserveLiquidity {
+    pub mint: Pubkey,
+    pub supply_vault: Pubkey,
+    pub fee_vault: Pubkey,
+    /// Liquidity in the supply vault that can be borrowed or withdrawn
+    pub available_amount: u64,
+    /// Outstanding borrows including accrued interest, a scaled fraction with 60 fractional bits
+    pub borrowed_amount_sf: u128,
+    /// Oracle price in the market's quote currency, a scaled fraction
+    pub market_price_sf: u128,
+    pub mint_decimals: u8,
+    /// Growth of one unit of debt since the reserve was created, a big fraction as four
+    /// little-endian u64 limbs
+    pub cumulative_borrow_rate_bsf: [u64; 4],
+}
+
+/// Collateral side of a reserve: the cToken minted to depositors
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReserveCollateral {
+    pub mint: Pubkey,
+    pub mint_total_supply: u64,
+    pub supply_vault: Pubkey,
+}
+
+/// The parts of a reserve's configuration used by the analyses
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReserveConfig {
+    /// 0 active, 1 obsolete, 2 hidden
+    pub status: u8,
+    pub loan_to_value_pct: u8,
+    pub liquidation_threshold_pct: u8,
+    pub borrow_factor_pct: u64,
+    /// Caps on total deposits and borrows, in raw units
+    pub deposit_limit: u64,
+    pub borrow_limit: u64,
+    /// Token name set by the market owner, e.g. `USDC`
+    pub token_name: String,
+}
+
+impl Reserve {
+    /// Decodes a reserve account, checking its discriminator and size
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        ensure!(data.len() >= 8 && data[..8] == RESERVE_DISCRIMINATOR, "account is not a Kamino Lend reserve");
+        Self::from_layout(data)
+    }
+
+    /// Decodes the reserve layout without checking the discriminator
+    pub(crate) fn from_layout(data: &[u8]) -> Result<Self> {
+        ensure!(data.len() >= RESERVE_SIZE, "account is too short to be a reserve");
+        let u64_at = |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().expect("slice is 8 bytes"));
+        let u128_at = |offset: usize| u128::from_le_bytes(data[offset..offset + 16].try_into().expect("slice is 16 bytes"));
+        let pubkey_at = |offset: usize| Pubkey::try_from(&data[offset..offset + 32]).expect("slice is 32 bytes");
+        let token_name = &data[CONFIG_TOKEN_NAME_OFFSET..][..CONFIG_TOKEN_NAME_LEN];
+        let token_name = String::from_utf8_lossy(token_name).trim_end_matches('\0').to_string();
+        Ok(Self {
+            version: u64_at(VERSION_OFFSET),
+            last_update_slot: u64_at(LAST_UPDATE_SLOT_OFFSET),
+            lending_market: pubkey_at(LENDING_MARKET_OFFSET),
+            liquidity: ReserveLiquidity {
+                mint: pubkey_at(LIQUIDITY_MINT_OFFSET),
+                supply_vault: pubkey_at(LIQUIDITY_SUPPLY_VAULT_OFFSET),
+                fee_vault: pubkey_at(LIQUIDITY_FEE_VAULT_OFFSET),
+                available_amount: u64_at(LIQUIDITY_AVAILABLE_AMOUNT_OFFSET),
+                borrowed_amount_sf: u128_at(LIQUIDITY_BORROWED_AMOUNT_OFFSET),
+                market_price_sf: u128_at(LIQUIDITY_MARKET_PRICE_OFFSET),
+                mint_decimals: u64_at(LIQUIDITY_MINT_DECIMALS_OFFSET) as u8,
+                cumulative_borrow_rate_bsf: std::array::from_fn(|limb| u64_at(LIQUIDITY_CUMULATIVE_BORROW_RATE_OFFSET + limb * 8)),
+            },
+            collateral: ReserveCollateral {
+                mint: pubkey_at(COLLATERAL_MINT_OFFSET),
+                mint_total_supply: u64_at(COLLATERAL_MINT_TOTAL_SUPPLY_OFFSET),
+                supply_vault: pubkey_at(COLLATERAL_SUPPLY_VAULT_OFFSET),
+            },
+            config: ReserveConfig {
+                status: data[CONFIG_STATUS_OFFSET],
+                loan_to_value_pct: data[CONFIG_LOAN_TO_VALUE_OFFSET],
+                liquidation_threshold_pct: data[CONFIG_LIQUIDATION_THRESHOLD_OFFSET],
+                borrow_factor_pct: u64_at(CONFIG_BORROW_FACTOR_OFFSET),
+                deposit_limit: u64_at(CONFIG_DEPOSIT_LIMIT_OFFSET),
+                borrow_limit: u64_at(CONFIG_BORROW_LIMIT_OFFSET),
+                token_name,
+            },
+        })
+    }
+}
+
+/// `getProgramAccounts` filters matching the reserves of `lending_market`: the reserve
+/// discriminator and the market field
+pub fn market_reserve_filters(lending_market: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, RESERVE_DISCRIMINATOR.to_vec())),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(LENDING_MARKET_OFFSET, lending_market.to_bytes().to_vec())),
+    ]
+}
+
+/// Every reserve of `lending_market` owned by `program_id`, by address. Accounts that match the
+/// filters but cannot be decoded are skipped with a warning.
+pub async fn fetch_market_reserves(client: &RpcClient, program_id: &Pubkey, lending_market: &Pubkey) -> Result<Vec<(Pubkey, Reserve)>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(market_reserve_filters(lending_market)),
+        account_config: RpcAccountInfoConfig { encoding: Some(UiAccountEncoding::Base64), ..RpcAccountInfoConfig::default() },
+        ..RpcProgramAccountsConfig::default()
+    };
+    let accounts = client
+        .get_program_accounts_with_config(program_id, config)
+        .await
+        .with_context(|| format!("failed to list the reserves of lending market {}", lending_market))?;
+    let mut reserves: Vec<(Pubkey, Reserve)> = accounts
+        .into_iter()
+        .filter_map(|(address, account)| match Reserve::from_account_data(&account.data) {
+            Ok(reserve) => Some((address, reserve)),
+            Err(e) => {
+                warn!(%address, "Skipping reserve that cannot be decoded: {}", e);
+                None
+            }
+        })
+        .collect();
+    reserves.sort_by_key(|(address, _)| *address);
+    Ok(reserves)
+}
+
+impl ReserveLiquidity {
+    /// Outstanding borrows including accrued interest, in raw units
+    pub fn borrowed_amount(&self) -> f64 {
+        scaled_fraction(&self.borrowed_amount_sf.to_le_bytes())
+    }
+
+    /// Oracle price of one whole token in the market's quote currency
+    pub fn market_price(&self) -> f64 {
+        scaled_fraction(&self.market_price_sf.to_le_bytes())
+    }
+
+    pub fn cumulative_borrow_rate(&self) -> f64 {
+        let bytes: Vec<u8> = self.cumulative_borrow_rate_bsf.iter().flat_map(|limb| limb.to_le_bytes()).collect();
+        big_fraction(&bytes)
+    }
+
+    /// Share of the reserve's total liquidity that is borrowed, between 0 and 1
+    pub fn utilization(&self) -> f64 {
+        let borrowed = self.borrowed_amount();
+        let total = self.available_amount as f64 + borrowed;
+        if total == 0.0 {
+            return 0.0;
+        }
+        borrowed / total
+    }
+}
@@ -0,0 +1,345 @@
+//! RPC helpers: signature paging, rate limiting, retries for transient failures and mint metadata.
+
+use crate::{resolve_account_keys, ParseOptions};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use rand::Rng;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_custom_error::{
+    JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED, JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE, JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    JSON_RPC_SERVER_ERROR_SLOT_SKIPPED,
+};
+use solana_client::rpc_client::RpcClientConfig;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_client::rpc_request::{RpcError, RpcRequest};
+use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use solana_rpc_client::http_sender::HttpSender;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use solana_sdk::message::v0;
+use solana_sdk::signature::Signature;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::future::Future;
+use std::num::NonZeroU32;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+// First retry delay for transient RPC errors, doubled on every further attempt
+const RPC_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+// A transaction this close to the finalized slot, or past it, that the node reports as missing
+// is assumed to be lagging behind the node that listed its signature
+const RECENT_SLOT_MARGIN: u64 = 150;
+
+// Retries, and the delay between them, for a recent transaction reported as missing
+const NOT_FOUND_RETRIES: u32 = 5;
+const NOT_FOUND_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+// getSignaturesForAddress returns at most this many signatures per call
+const SIGNATURES_PAGE_LIMIT: usize = 1000;
+
+// Address lookup table accounts: a 56-byte metadata header, then the 32-byte addresses
+const LOOKUP_TABLE_META_SIZE: usize = 56;
+
+// SPL token mint layout: mint_authority (36) + supply (8), then decimals
+const SPL_MINT_DECIMALS_OFFSET: usize = 44;
+
+/// Pages backwards through the program's signature history, newest first, keeping the
+/// signatures inside the slot and block time bounds of `opts`.
+///
+/// With `max_transactions`, paging stops once that many successful signatures (or signatures of
+/// any status with `include_failed`) are collected. Without `max_transactions`, `since_slot` or a
+/// time window only the latest page is fetched, matching the plain `getSignaturesForAddress`
+/// behaviour. Signatures without a block time are dropped whenever a time window is set, since
+/// they cannot be placed in it. Each page is fetched with [`retry_rpc`], so a transient failure
+/// partway through a long history does not abort the scan.
+pub async fn fetch_signatures(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    opts: &ParseOptions,
+) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+    let mut signatures = Vec::new();
+    if opts.max_transactions == Some(0) {
+        return Ok(signatures);
+    }
+    let mut before = None;
+    let in_time_window = |block_time: Option<i64>| match block_time {
+        Some(block_time) => {
+            opts.since.is_none_or(|since| block_time >= since) && opts.until.is_none_or(|until| block_time <= until)
+        }
+        None => opts.since.is_none() && opts.until.is_none(),
+    };
+
+    loop {
+        let config = || GetConfirmedSignaturesForAddress2Config {
+            before,
+            until: None,
+            limit: Some(SIGNATURES_PAGE_LIMIT),
+            commitment: Some(history_commitment(client)),
+        };
+        let page = retry_rpc(opts.rpc_retries, || client.get_signatures_for_address_with_config(program_id, config())).await?;
+        let page_len = page.len();
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature)?);
+        let reached_since_slot = opts.since_slot.is_some_and(|since_slot| last.slot < since_slot);
+        let reached_since = opts.since.is_some_and(|since| last.block_time.is_some_and(|t| t < since));
+        // Keep paging past signatures newer than --until until the window is reached
+        let before_until = opts.until.is_some_and(|until| last.block_time.is_none_or(|t| t > until));
+
+        signatures.extend(page.into_iter().filter(|sig_info| {
+            opts.since_slot.is_none_or(|since_slot| sig_info.slot >= since_slot) && in_time_window(sig_info.block_time)
+        }));
+        debug!("Fetched signature page of {} (total {})", page_len, signatures.len());
+
+        // Only the signatures that will be fetched count towards the limit
+        if let Some(max_transactions) = opts.max_transactions {
+            let mut fetchable = 0;
+            let limit_reached = signatures.iter().position(|sig_info| {
+                fetchable += usize::from(opts.include_failed || sig_info.err.is_none());
+                fetchable >= max_transactions
+            });
+            if let Some(last) = limit_reached {
+                signatures.truncate(last + 1);
+                break;
+            }
+        }
+        let paging = opts.max_transactions.is_some() || opts.since_slot.is_some() || opts.since.is_some() || before_until;
+        if !paging || reached_since_slot || reached_since || page_len < SIGNATURES_PAGE_LIMIT {
+            break;
+        }
+    }
+
+    Ok(signatures)
+}
+
+/// Signatures of the program newer than `until`, newest first, paging back as far as needed to
+/// reach it. Without `until` only the newest signature is returned, as a starting point.
+pub async fn fetch_signatures_until(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    until: Option<Signature>,
+    retries: u32,
+) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let config = || GetConfirmedSignaturesForAddress2Config {
+            before,
+            until,
+            limit: Some(if until.is_some() { SIGNATURES_PAGE_LIMIT } else { 1 }),
+            commitment: Some(history_commitment(client)),
+        };
+        let page = retry_rpc(retries, || client.get_signatures_for_address_with_config(program_id, config())).await?;
+        let page_len = page.len();
+        before = match page.last() {
+            Some(last) => Some(Signature::from_str(&last.signature)?),
+            None => None,
+        };
+        signatures.extend(page);
+        if until.is_none() || page_len < SIGNATURES_PAGE_LIMIT {
+            return Ok(signatures);
+        }
+    }
+}
+
+/// Transport that holds every RPC request until the token bucket allows it, so bursts from the
+/// concurrent fetches stay under an endpoint's requests-per-second cap
+pub struct RateLimitedSender {
+    inner: HttpSender,
+    limiter: DefaultDirectRateLimiter,
+}
+
+impl RateLimitedSender {
+    pub fn new(url: impl ToString, requests_per_second: NonZeroU32) -> Self {
+        Self {
+            inner: HttpSender::new(url),
+            limiter: RateLimiter::direct(Quota::per_second(requests_per_second)),
+        }
+    }
+}
+
+#[async_trait]
+impl RpcSender for RateLimitedSender {
+    async fn send(&self, request: RpcRequest, params: serde_json::Value) -> Result<serde_json::Value, ClientError> {
+        self.limiter.until_ready().await;
+        self.inner.send(request, params).await
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+/// RPC client for `url` whose requests are throttled to `requests_per_second`
+pub fn rate_limited_client(url: impl ToString, requests_per_second: NonZeroU32, commitment: CommitmentConfig) -> RpcClient {
+    RpcClient::new_sender(
+        RateLimitedSender::new(url, requests_per_second),
+        RpcClientConfig::with_commitment(commitment),
+    )
+}
+
+/// Commitment for transaction history requests (getSignaturesForAddress, getTransaction,
+/// getBlock): the client's, except that these do not support `processed`, so it is raised to
+/// `confirmed`
+pub fn history_commitment(client: &RpcClient) -> CommitmentConfig {
+    let commitment = client.commitment();
+    if commitment.commitment == CommitmentLevel::Processed {
+        return CommitmentConfig::confirmed();
+    }
+    commitment
+}
+
+/// Runs `op` until it succeeds, retrying transient failures up to `retries` times with
+/// exponential backoff plus jitter. Permanent errors are returned immediately.
+pub async fn retry_rpc<T, F, Fut>(retries: u32, mut op: F) -> Result<T, ClientError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ClientError>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < retries && is_retryable(&e) => {
+                let backoff = RPC_RETRY_BASE_DELAY * 2u32.pow(attempt);
+                let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..=backoff.as_millis() as u64 / 2));
+                attempt += 1;
+                warn!("Transient RPC error (retry {}/{} in {:?}): {}", attempt, retries, backoff + jitter, e);
+                tokio::time::sleep(backoff + jitter).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Fetches a transaction with [`retry_rpc`]. When the node reports it missing although `slot`,
+/// the slot its signature was listed at, is within [`RECENT_SLOT_MARGIN`] of the finalized slot,
+/// the node is likely lagging, so the fetch is retried a few more times before giving up.
+/// Transactions from older slots, or without a known slot, are genuinely missing and fail at once.
+pub async fn fetch_transaction(
+    client: &RpcClient,
+    signature: &Signature,
+    slot: Option<u64>,
+    config: RpcTransactionConfig,
+    retries: u32,
+) -> Result<EncodedConfirmedTransactionWithStatusMeta, ClientError> {
+    let mut attempt = 0;
+    loop {
+        let error = match retry_rpc(retries, || client.get_transaction_with_config(signature, config)).await {
+            Ok(transaction) => return Ok(transaction),
+            Err(e) if attempt < NOT_FOUND_RETRIES && is_transaction_not_found(&e) => e,
+            Err(e) => return Err(e),
+        };
+        let Some(slot) = slot else {
+            return Err(error);
+        };
+        let finalized_slot = retry_rpc(retries, || client.get_slot_with_commitment(CommitmentConfig::finalized())).await?;
+        if slot.saturating_add(RECENT_SLOT_MARGIN) < finalized_slot {
+            return Err(error);
+        }
+        attempt += 1;
+        debug!(
+            %signature, slot, finalized_slot,
+            "Recent transaction not found yet (retry {}/{} in {:?})", attempt, NOT_FOUND_RETRIES, NOT_FOUND_RETRY_DELAY
+        );
+        tokio::time::sleep(NOT_FOUND_RETRY_DELAY).await;
+    }
+}
+
+/// Whether a `getTransaction` failure means the node has no such transaction: it answers `null`,
+/// which fails to deserialize
+pub fn is_transaction_not_found(error: &ClientError) -> bool {
+    matches!(error.kind(), ClientErrorKind::SerdeJson(e) if e.to_string().starts_with("invalid type: null"))
+}
+
+/// Whether an RPC failure is transient (timeouts, rate limits, overloaded nodes) rather than
+/// permanent, such as a transaction that does not exist
+pub fn is_retryable(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || e.status().is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            // Some providers report rate limiting as a JSON-RPC error code rather than HTTP 429
+            *code == 429 || *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        }
+        _ => false,
+    }
+}
+
+/// Whether a `getBlock` failure means the slot has no block: skipped by its leader, or the node
+/// does not have it
+pub fn is_skipped_slot(error: &ClientError) -> bool {
+    matches!(
+        error.kind(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. })
+            if *code == JSON_RPC_SERVER_ERROR_SLOT_SKIPPED
+                || *code == JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED
+                || *code == JSON_RPC_SERVER_ERROR_BLOCK_NOT_AVAILABLE
+    )
+}
+
+/// Addresses of fetched lookup tables keyed by table account, shared across transactions
+pub type LookupCache = HashMap<Pubkey, Vec<Pubkey>>;
+
+/// Full ordered account list of a v0 message, as indexed by its instructions: static keys, then
+/// the lookup table addresses (see [`resolve_account_keys`]). Tables missing from `cache` are
+/// fetched and cached first. Tables are append-only, so a cached table is only fetched again
+/// when the message indexes past its end, i.e. the table was extended since it was cached.
+pub async fn resolve_accounts(client: &RpcClient, msg: &v0::Message, cache: &mut LookupCache) -> Result<Vec<Pubkey>> {
+    for lookup in &msg.address_table_lookups {
+        let needed = lookup.writable_indexes.iter().chain(&lookup.readonly_indexes).max().map_or(0, |index| *index as usize + 1);
+        if cache.get(&lookup.account_key).is_some_and(|addresses| addresses.len() < needed) {
+            debug!(table = %lookup.account_key, "Lookup table was extended since it was cached; fetching it again");
+            cache.remove(&lookup.account_key);
+        }
+        if let Entry::Vacant(entry) = cache.entry(lookup.account_key) {
+            let account = client
+                .get_account(&lookup.account_key)
+                .await
+                .with_context(|| format!("failed to fetch lookup table {}", lookup.account_key))?;
+            let Some(address_data) = account.data.get(LOOKUP_TABLE_META_SIZE..) else {
+                warn!(
+                    table = %lookup.account_key, len = account.data.len(),
+                    "Lookup table data is shorter than its {}-byte header", LOOKUP_TABLE_META_SIZE
+                );
+                bail!("account {} is too short to be a lookup table", lookup.account_key);
+            };
+            let addresses = address_data.chunks_exact(32);
+            if !addresses.remainder().is_empty() {
+                warn!(
+                    table = %lookup.account_key, len = account.data.len(),
+                    "Lookup table has {} trailing bytes after its addresses; ignoring them", addresses.remainder().len()
+                );
+            }
+            entry.insert(addresses.map(|chunk| Pubkey::new_from_array(chunk.try_into().unwrap())).collect());
+        }
+    }
+    resolve_account_keys(msg, cache).context("lookup table index out of range")
+}
+
+/// Reads the `decimals` field of an SPL token mint account.
+pub async fn fetch_mint_decimals(client: &RpcClient, mint: &Pubkey) -> Result<u8> {
+    let account = client.get_account(mint).await?;
+    account
+        .data
+        .get(SPL_MINT_DECIMALS_OFFSET)
+        .copied()
+        .with_context(|| format!("account {} is too short to be an SPL mint", mint))
+}
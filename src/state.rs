@@ -0,0 +1,132 @@
+//! Decoding of Kamino Lend reserve and obligation accounts, used to split repays into principal
+//! and interest with `--decode-state`. The account layouts live in
+//! [`crate::reserve`] and [`crate::obligation`].
+//!
+//! Kamino folds accrued interest into an obligation's debt rather than tracking the original
+//! principal, so the split is an estimate from state fetched at scan time, not at the repay's
+//! slot: the interest is what the obligation's recorded debt in the reserve has accrued since its
+//! last refresh, the growth of the reserve's cumulative borrow rate over the rate the obligation
+//! last snapshotted. The rest of the repay counts as principal.
+
+pub use crate::obligation::ObligationBorrow;
+
+use crate::obligation::Obligation;
+use crate::reserve::Reserve;
+use crate::{ParseSummary, TransactionType};
+use anyhow::Result;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use tracing::{debug, info, warn};
+
+// Scaled fractions (`_sf`) and big fractions (`_bsf`) carry 60 fractional bits
+const FRACTION_BITS: i32 = 60;
+
+// getMultipleAccounts takes at most this many addresses per call
+const MULTIPLE_ACCOUNTS_LIMIT: usize = 100;
+
+/// A repay's amount split into principal and interest, in the repaid mint's raw units
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RepaySplit {
+    pub principal: u64,
+    pub interest: u64,
+}
+
+/// Active borrows of an obligation account
+pub fn decode_obligation_borrows(data: &[u8]) -> Result<Vec<ObligationBorrow>> {
+    Ok(Obligation::from_layout(data)?.borrows)
+}
+
+/// Current cumulative borrow rate of a reserve account
+pub fn decode_reserve_cumulative_borrow_rate(data: &[u8]) -> Result<f64> {
+    Ok(Reserve::from_layout(data)?.liquidity.cumulative_borrow_rate())
+}
+
+/// Splits a repay of `amount` given the obligation's debt in the repaid reserve and the reserve's
+/// current cumulative borrow rate. Interest never exceeds the repay.
+pub fn split_repay(amount: u64, borrow: &ObligationBorrow, reserve_rate: f64) -> Option<RepaySplit> {
+    if borrow.cumulative_borrow_rate <= 0.0 || reserve_rate < borrow.cumulative_borrow_rate {
+        return None;
+    }
+    let accrued = borrow.borrowed_amount * (reserve_rate / borrow.cumulative_borrow_rate - 1.0);
+    let interest = (accrued.round() as u64).min(amount);
+    Some(RepaySplit { principal: amount - interest, interest })
+}
+
+/// Fetches the obligation and reserve of every repay in the summary and fills in their splits.
+/// Repays whose accounts cannot be fetched or decoded, or whose obligation no longer borrows from
+/// the reserve, keep only their raw amount.
+pub async fn fill_repay_splits(client: &RpcClient, summary: &mut ParseSummary) {
+    let mut addresses: Vec<Pubkey> = summary
+        .records
+        .iter()
+        .filter_map(|record| match record.transaction_type {
+            TransactionType::Repay { obligation, reserve, .. } => Some([obligation, reserve]),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    addresses.sort();
+    addresses.dedup();
+    if addresses.is_empty() {
+        return;
+    }
+
+    let accounts = fetch_account_data(client, &addresses).await;
+    let mut split_count = 0;
+    for record in &mut summary.records {
+        let TransactionType::Repay { amount, obligation, reserve, ref mut split } = record.transaction_type else {
+            continue;
+        };
+        let decoded = (|| -> Result<Option<RepaySplit>> {
+            let (Some(obligation_data), Some(reserve_data)) = (accounts.get(&obligation), accounts.get(&reserve)) else {
+                return Ok(None);
+            };
+            let borrows = decode_obligation_borrows(obligation_data)?;
+            let reserve_rate = decode_reserve_cumulative_borrow_rate(reserve_data)?;
+            Ok(borrows
+                .iter()
+                .find(|borrow| borrow.reserve == reserve)
+                .and_then(|borrow| split_repay(amount, borrow, reserve_rate)))
+        })();
+        match decoded {
+            Ok(decoded) => *split = decoded,
+            Err(e) => debug!(signature = %record.signature, %obligation, %reserve, "Cannot decode repay state: {}", e),
+        }
+        split_count += usize::from(split.is_some());
+    }
+    info!("Split {} repays into principal and interest", split_count);
+}
+
+/// Data of the accounts that exist, fetched in batches; failed batches are left out with a warning
+async fn fetch_account_data(client: &RpcClient, addresses: &[Pubkey]) -> HashMap<Pubkey, Vec<u8>> {
+    let mut accounts = HashMap::new();
+    for chunk in addresses.chunks(MULTIPLE_ACCOUNTS_LIMIT) {
+        match client.get_multiple_accounts(chunk).await {
+            Ok(fetched) => {
+                for (address, account) in chunk.iter().zip(fetched) {
+                    if let Some(account) = account {
+                        accounts.insert(*address, account.data);
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to fetch {} reserve and obligation accounts: {}", chunk.len(), e),
+        }
+    }
+    accounts
+}
+
+pub(crate) fn scaled_fraction(bytes: &[u8]) -> f64 {
+    let value = u128::from_le_bytes(bytes[..16].try_into().expect("slice is 16 bytes"));
+    value as f64 / 2f64.powi(FRACTION_BITS)
+}
+
+/// A little-endian 256-bit big fraction, as four u64 limbs
+pub(crate) fn big_fraction(bytes: &[u8]) -> f64 {
+    let value = bytes[..32]
+        .chunks_exact(8)
+        .enumerate()
+        .map(|(i, limb)| u64::from_le_bytes(limb.try_into().expect("chunk is 8 bytes")) as f64 * 2f64.powi(64 * i as i32))
+        .sum::<f64>();
+    value / 2f64.powi(FRACTION_BITS)
+}
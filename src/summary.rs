@@ -0,0 +1,1324 @@
+//! Aggregation of parsed records into per-mint totals and instruction counts.
+
+use crate::instruction::{hex, AccountFilter, KaminoTransaction, TransactionType};
+use crate::mint::MintRegistry;
+use crate::rpc::LookupCache;
+use crate::{ParseOptions, SkippedTransaction};
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use comfy_table::{CellAlignment, Table};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use solana_sdk::pubkey::Pubkey;
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::cmp::Ordering;
+use std::fmt;
+use std::time::{Duration, UNIX_EPOCH};
+use tracing::debug;
+
+/// Order of the per-token lines of the text and table output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MintOrder {
+    /// By mint address in base58, so repeated runs over the same data print identically
+    #[default]
+    Mint,
+    /// By volume in UI units, largest first; ties by mint address
+    Volume,
+}
+
+/// Distribution of the raw amounts of one instruction type for one mint
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AmountStats {
+    pub count: usize,
+    pub mean: f64,
+    pub median: u64,
+    pub min: u64,
+    pub max: u64,
+    /// Nearest-rank 90th percentile
+    pub p90: u64,
+}
+
+impl AmountStats {
+    /// Stats of `amounts`, or `None` if there are none
+    pub fn from_amounts(mut amounts: Vec<u64>) -> Option<Self> {
+        if amounts.is_empty() {
+            return None;
+        }
+        amounts.sort_unstable();
+        let count = amounts.len();
+        let mid = count / 2;
+        let median = if count.is_multiple_of(2) {
+            ((amounts[mid - 1] as u128 + amounts[mid] as u128) / 2) as u64
+        } else {
+            amounts[mid]
+        };
+        let p90_rank = (count * 90).div_ceil(100);
+        Some(Self {
+            count,
+            mean: amounts.iter().map(|amount| *amount as f64).sum::<f64>() / count as f64,
+            median,
+            min: amounts[0],
+            max: amounts[count - 1],
+            p90: amounts[p90_rank - 1],
+        })
+    }
+}
+
+/// Liquidity moved in and out of one mint's reserves over the window, in raw units
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MintFlow {
+    /// Obligation borrows; flash loans are repaid in the same transaction and net to zero
+    pub borrowed: u64,
+    /// Obligation repays, including the debt repaid by liquidators
+    pub repaid: u64,
+    /// Liquidity deposits, including those posted straight to an obligation as collateral
+    pub deposited: u64,
+    /// Redeemed collateral. Redeems are in collateral (cToken) units, which trade above par as
+    /// interest accrues, so this slightly understates outflows; collateral withdrawn from
+    /// obligations is not counted until it is redeemed, as it carries no mint.
+    pub withdrawn: u64,
+}
+
+impl MintFlow {
+    /// Liquidity that flowed into the reserves: positive for net inflow, negative for net outflow
+    pub fn net(&self) -> i128 {
+        self.deposited as i128 + self.repaid as i128 - self.borrowed as i128 - self.withdrawn as i128
+    }
+}
+
+/// Borrow volume of one mint split by where it was borrowed, in raw units
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BorrowMix {
+    pub flash_loan: u64,
+    pub obligation: u64,
+}
+
+impl BorrowMix {
+    /// Share of the volume borrowed through flash loans, between 0 and 1; `None` without borrows
+    pub fn flash_loan_share(&self) -> Option<f64> {
+        let total = self.flash_loan as f64 + self.obligation as f64;
+        (total > 0.0).then(|| self.flash_loan as f64 / total)
+    }
+}
+
+/// Activity within one bucket of [`ParseSummary::time_buckets`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TimeBucket {
+    /// Transactions with at least one decoded instruction in the bucket
+    pub transactions: usize,
+    /// Flash loan and obligation borrows
+    pub borrows: usize,
+    /// Flash loan and obligation borrow volume per mint, in raw units
+    pub borrowed: HashMap<Pubkey, u64>,
+}
+
+/// Flash loan fees of one mint in basis points of the borrowed amount, see
+/// [`ParseSummary::flash_loan_fee_rates`]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeeRate {
+    /// Paired flash loans the rate is computed from
+    pub loans: usize,
+    /// Total fees over the total borrowed by those loans
+    pub average_bps: f64,
+    /// Typical rate of a single loan, which outliers are measured against
+    pub median_bps: f64,
+    /// Signatures and rates of the loans whose fee strays from the median by more than
+    /// [`FEE_RATE_TOLERANCE`] of it, beyond the one raw unit of fee rounding
+    pub outliers: Vec<(String, f64)>,
+}
+
+/// Share of the median fee rate a flash loan's own rate may differ by before it is flagged
+pub const FEE_RATE_TOLERANCE: f64 = 0.5;
+
+/// Borrow volume of one mint per minute of block time, in UI units, see
+/// [`ParseSummary::borrow_rates`]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BorrowRate {
+    pub flash_loan_per_minute: f64,
+    pub obligation_per_minute: f64,
+}
+
+impl BorrowRate {
+    pub fn flash_loan_per_hour(&self) -> f64 {
+        self.flash_loan_per_minute * 60.0
+    }
+
+    pub fn obligation_per_hour(&self) -> f64 {
+        self.obligation_per_minute * 60.0
+    }
+}
+
+/// Random share of the signatures a scan processes, for quick estimates over long histories, see
+/// [`crate::ParseOptions::sample`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// Share of the signatures processed, above 0 and at most 1
+    pub rate: f64,
+    /// Seed of the random selection; the same seed picks the same signatures from the same list
+    pub seed: u64,
+}
+
+impl Sample {
+    /// What the sampled totals are multiplied by to estimate the full ones
+    pub fn factor(&self) -> f64 {
+        1.0 / self.rate
+    }
+
+    /// Keeps each item with probability `rate`, in order
+    pub fn select<T>(&self, items: Vec<T>) -> Vec<T> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        items.into_iter().filter(|_| rng.gen_bool(self.rate)).collect()
+    }
+}
+
+/// Activity of one obligation over the window. Liquidations are not included, as the parser does
+/// not extract the liquidated obligation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ObligationActivity {
+    /// Borrowed amounts per mint, in raw units
+    pub borrowed: HashMap<Pubkey, u64>,
+    /// Repaid amounts per mint, in raw units
+    pub repaid: HashMap<Pubkey, u64>,
+    /// Collateral deposits, from cTokens or straight from liquidity
+    pub collateral_deposits: u64,
+    pub collateral_withdrawals: u64,
+}
+
+/// Result of a scan: every parsed record plus per-mint totals, keyed by reserve liquidity mint
+#[derive(Clone, Debug, Default)]
+pub struct ParseSummary {
+    /// Successful transactions fetched for the scan
+    pub transactions_scanned: usize,
+    /// Reserve and market filter the records were selected with
+    pub account_filter: AccountFilter,
+    /// The scan hit `--max-transactions`, so older transactions in the window were not fetched
+    pub truncated_to: Option<usize>,
+    /// The scan only processed a random sample of the signatures, and how many it was drawn
+    /// from; totals and counts are extrapolated, see [`ParseSummary::extrapolate`]
+    pub sample: Option<(Sample, usize)>,
+    /// The scan was stopped by Ctrl-C, so the totals only cover the transactions fetched until then
+    pub interrupted: bool,
+    /// Signatures whose transaction could not be fetched, even after retries
+    pub fetch_errors: Vec<String>,
+    /// Signatures skipped because some of their lookup tables could not be resolved
+    pub lookup_errors: Vec<String>,
+    /// Transactions skipped because their encoding could not be decoded
+    pub decode_errors: usize,
+    /// Lookup tables that could not be fetched
+    pub unresolved_lookup_tables: BTreeSet<Pubkey>,
+    /// Oldest and newest block time of the scanned signatures, when both are known
+    pub block_time_window: Option<(i64, i64)>,
+    pub records: Vec<KaminoTransaction>,
+    /// Decimals of every mint seen in `records`, filled in by [`crate::fill_mint_decimals`]
+    pub mints: MintRegistry,
+    /// Lookup tables resolved during the scan, on top of the ones it started from
+    pub lookup_tables: LookupCache,
+
+    pub flash_loan_borrow_amounts: HashMap<Pubkey, u64>,
+    pub flash_loan_repay_amounts: HashMap<Pubkey, u64>,
+    pub flash_loan_fee_amounts: HashMap<Pubkey, u64>,
+    pub loan_borrow_amounts: HashMap<Pubkey, u64>,
+    /// Borrowed amounts per obligation owner, then per mint
+    pub borrows_by_owner: HashMap<Pubkey, HashMap<Pubkey, u64>>,
+    /// Borrowed amounts per referrer token state account, then per mint; `None` collects borrows
+    /// without a referrer
+    pub borrows_by_referrer: HashMap<Option<Pubkey>, HashMap<Pubkey, u64>>,
+    /// Fee payers of the transactions that took flash loans
+    pub flash_loan_initiators: HashSet<Pubkey>,
+    /// Flash loan and obligation borrow volume per lending market, then per mint
+    pub borrows_by_market: HashMap<Pubkey, HashMap<Pubkey, BorrowMix>>,
+    /// Borrows, repays and collateral movements per obligation; one owner can have several
+    /// obligations, e.g. one per lending market
+    pub by_obligation: HashMap<Pubkey, ObligationActivity>,
+    pub repay_amounts: HashMap<Pubkey, u64>,
+    pub deposit_amounts: HashMap<Pubkey, u64>,
+    /// Liquidity deposited straight into obligations as collateral, keyed by mint
+    pub deposit_and_collateralize_amounts: HashMap<Pubkey, u64>,
+    /// Keyed by reserve and in raw collateral units, since these deposits carry no mint account
+    pub deposit_collateral_by_reserve: HashMap<Pubkey, u64>,
+    pub redeem_amounts: HashMap<Pubkey, u64>,
+    pub liquidation_amounts: HashMap<Pubkey, u64>,
+    /// Minimum collateral liquidators accepted, keyed by collateral liquidity mint
+    pub liquidation_collateral_amounts: HashMap<Pubkey, u64>,
+    /// Keyed by reserve and in raw collateral units, since withdrawals carry no mint account
+    pub withdraw_collateral_by_reserve: HashMap<Pubkey, u64>,
+    /// Borrows, repays, deposits and withdrawals of every mint combined
+    pub flows: HashMap<Pubkey, MintFlow>,
+
+    pub flash_loan_txs_count: u64,
+    pub flash_loan_repay_txs_count: u64,
+    pub loan_txs_count: u64,
+    pub repay_txs_count: u64,
+    pub deposit_txs_count: u64,
+    pub deposit_collateral_txs_count: u64,
+    pub deposit_and_collateralize_txs_count: u64,
+    pub withdraw_txs_count: u64,
+    pub redeem_txs_count: u64,
+    pub liquidation_txs_count: u64,
+    /// Refreshes move no funds, but count towards the attempts of their type
+    pub refresh_reserve_txs_count: u64,
+    pub refresh_obligation_txs_count: u64,
+    /// Unrecognised Kamino instructions, keyed by discriminator, to spot what is not handled yet
+    pub unknown_instruction_counts: HashMap<[u8; 8], u64>,
+    /// Instructions of failed transactions, keyed by [`TransactionType::name`]; the counts and
+    /// totals above only cover successful ones
+    pub failed_counts: HashMap<&'static str, u64>,
+}
+
+impl ParseSummary {
+    /// An empty summary starting from the filter, mints and lookup tables known upfront in `opts`
+    pub fn for_options(opts: &ParseOptions) -> Self {
+        ParseSummary {
+            account_filter: opts.account_filter.clone(),
+            mints: opts.mints.clone(),
+            lookup_tables: opts.lookup_tables.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Widens `block_time_window` to cover a transaction or block with this block time
+    pub fn observe_block_time(&mut self, block_time: i64) {
+        self.block_time_window = Some(match self.block_time_window {
+            Some((oldest, newest)) => (oldest.min(block_time), newest.max(block_time)),
+            None => (block_time, block_time),
+        });
+    }
+
+    /// Adds the records of one fetched transaction, or notes why it was skipped
+    pub fn add_parsed(&mut self, parsed: Result<Vec<KaminoTransaction>, SkippedTransaction>) {
+        match parsed {
+            Ok(records) => {
+                for record in records {
+                    self.add_record(record);
+                }
+            }
+            Err(SkippedTransaction::Undecodable { .. }) => self.decode_errors += 1,
+            Err(SkippedTransaction::UnresolvedLookupTables(unresolved)) => {
+                self.lookup_errors.push(unresolved.signature);
+                self.unresolved_lookup_tables.extend(unresolved.tables);
+            }
+        }
+    }
+
+    /// Transactions that were scanned but could not be parsed, for fetch, decode or lookup table
+    /// errors
+    pub fn skipped_count(&self) -> usize {
+        self.fetch_errors.len() + self.lookup_errors.len() + self.decode_errors
+    }
+
+    /// Share of the scanned transactions that were skipped, 0 when nothing was scanned
+    pub fn skipped_fraction(&self) -> f64 {
+        if self.transactions_scanned == 0 {
+            return 0.0;
+        }
+        self.skipped_count() as f64 / self.transactions_scanned as f64
+    }
+
+    /// Folds one record into the per-mint totals and keeps it in `records`. Records of failed
+    /// transactions are only counted in `failed_counts`.
+    pub fn add_record(&mut self, record: KaminoTransaction) {
+        if record.failed {
+            *self.failed_counts.entry(record.transaction_type.name()).or_insert(0) += 1;
+            debug!(signature = %record.signature, "Failed {} attempt", record.transaction_type.name());
+            self.records.push(record);
+            return;
+        }
+        if let Some(obligation) = record.transaction_type.obligation() {
+            let activity = self.by_obligation.entry(obligation).or_default();
+            match (record.transaction_type, record.reserve_token) {
+                (TransactionType::Borrow { amount, .. }, Some(mint)) => *activity.borrowed.entry(mint).or_insert(0) += amount,
+                (TransactionType::Repay { amount, .. }, Some(mint)) => *activity.repaid.entry(mint).or_insert(0) += amount,
+                (TransactionType::DepositCollateral { .. } | TransactionType::DepositAndCollateralize { .. }, _) => activity.collateral_deposits += 1,
+                (TransactionType::Withdraw { .. }, _) => activity.collateral_withdrawals += 1,
+                _ => {}
+            }
+        }
+        if let (Some(market), Some(mint)) = (record.lending_market, record.reserve_token) {
+            match record.transaction_type {
+                TransactionType::FlashBorrow { amount, .. } => {
+                    self.borrows_by_market.entry(market).or_default().entry(mint).or_default().flash_loan += amount
+                }
+                TransactionType::Borrow { amount, .. } => {
+                    self.borrows_by_market.entry(market).or_default().entry(mint).or_default().obligation += amount
+                }
+                _ => {}
+            }
+        }
+        let (amounts, label) = match record.transaction_type {
+            TransactionType::FlashBorrow { initiator, .. } => {
+                self.flash_loan_txs_count += 1;
+                self.flash_loan_initiators.insert(initiator);
+                (&mut self.flash_loan_borrow_amounts, "Flash loan borrow")
+            }
+            TransactionType::FlashRepay { fee, .. } => {
+                self.flash_loan_repay_txs_count += 1;
+                if let (Some(reserve_token), Some(fee)) = (record.reserve_token, fee) {
+                    *self.flash_loan_fee_amounts.entry(reserve_token).or_insert(0) += fee;
+                }
+                (&mut self.flash_loan_repay_amounts, "Flash loan repay")
+            }
+            TransactionType::Borrow { amount, owner, referrer, .. } => {
+                self.loan_txs_count += 1;
+                if let Some(reserve_token) = record.reserve_token {
+                    *self.borrows_by_owner.entry(owner).or_default().entry(reserve_token).or_insert(0) += amount;
+                    *self.borrows_by_referrer.entry(referrer).or_default().entry(reserve_token).or_insert(0) += amount;
+                }
+                (&mut self.loan_borrow_amounts, "Borrow obligation")
+            }
+            TransactionType::Repay { .. } => {
+                self.repay_txs_count += 1;
+                (&mut self.repay_amounts, "Repay obligation")
+            }
+            TransactionType::Deposit { .. } => {
+                self.deposit_txs_count += 1;
+                (&mut self.deposit_amounts, "Deposit reserve liquidity")
+            }
+            TransactionType::DepositCollateral { .. } => {
+                self.deposit_collateral_txs_count += 1;
+                (&mut self.deposit_collateral_by_reserve, "Deposit obligation collateral to reserve")
+            }
+            TransactionType::DepositAndCollateralize { .. } => {
+                self.deposit_and_collateralize_txs_count += 1;
+                (&mut self.deposit_and_collateralize_amounts, "Deposit reserve liquidity as obligation collateral")
+            }
+            TransactionType::Withdraw { .. } => {
+                self.withdraw_txs_count += 1;
+                (&mut self.withdraw_collateral_by_reserve, "Withdraw obligation collateral from reserve")
+            }
+            TransactionType::Redeem { .. } => {
+                self.redeem_txs_count += 1;
+                (&mut self.redeem_amounts, "Redeem reserve collateral")
+            }
+            TransactionType::Liquidate { collateral_seized, liquidator, collateral_mint, .. } => {
+                self.liquidation_txs_count += 1;
+                *self.liquidation_collateral_amounts.entry(collateral_mint).or_insert(0) += collateral_seized;
+                debug!(
+                    signature = %record.signature, %liquidator, %collateral_mint,
+                    "Liquidation seized at least {} collateral", collateral_seized
+                );
+                (&mut self.liquidation_amounts, "Liquidate obligation")
+            }
+            TransactionType::RefreshReserve { reserve } => {
+                debug!(
+                    signature = %record.signature, instruction_index = record.instruction_index, inner_index = ?record.inner_index,
+                    %reserve, "Reserve refreshed"
+                );
+                self.refresh_reserve_txs_count += 1;
+                self.records.push(record);
+                return;
+            }
+            TransactionType::RefreshObligation { obligation } => {
+                debug!(
+                    signature = %record.signature, instruction_index = record.instruction_index, inner_index = ?record.inner_index,
+                    %obligation, "Obligation refreshed"
+                );
+                self.refresh_obligation_txs_count += 1;
+                self.records.push(record);
+                return;
+            }
+            TransactionType::Unknown { discriminator } => {
+                *self.unknown_instruction_counts.entry(discriminator).or_insert(0) += 1;
+                debug!(signature = %record.signature, "Unknown Kamino instruction {}", hex(&discriminator));
+                self.records.push(record);
+                return;
+            }
+        };
+        if let (Some(reserve_token), Some(amount)) = (record.reserve_token, record.transaction_type.amount()) {
+            *amounts.entry(reserve_token).or_insert(0) += amount;
+            let flow = match record.transaction_type {
+                TransactionType::Borrow { .. } => Some(&mut self.flows.entry(reserve_token).or_default().borrowed),
+                TransactionType::Repay { .. } | TransactionType::Liquidate { .. } => Some(&mut self.flows.entry(reserve_token).or_default().repaid),
+                TransactionType::Deposit { .. } | TransactionType::DepositAndCollateralize { .. } => {
+                    Some(&mut self.flows.entry(reserve_token).or_default().deposited)
+                }
+                TransactionType::Redeem { .. } => Some(&mut self.flows.entry(reserve_token).or_default().withdrawn),
+                _ => None,
+            };
+            if let Some(flow) = flow {
+                *flow += amount;
+            }
+            debug!(
+                signature = %record.signature, slot = record.slot, block_time = ?record.block_time,
+                "{} {}: {}", label, reserve_token, amount
+            );
+        }
+        self.records.push(record);
+    }
+
+    /// Distinct obligation owners that borrowed, from `borrows_by_owner`
+    pub fn unique_borrowers(&self) -> usize {
+        self.borrows_by_owner.len()
+    }
+
+    /// Distinct obligations that borrowed, repaid or moved collateral, from `by_obligation`
+    pub fn unique_obligations(&self) -> usize {
+        self.by_obligation.len()
+    }
+
+    /// Successful plus failed instructions of the given [`TransactionType::name`]
+    pub fn attempted_count(&self, name: &str) -> u64 {
+        let succeeded = match name {
+            "flash_borrow" => self.flash_loan_txs_count,
+            "flash_repay" => self.flash_loan_repay_txs_count,
+            "borrow" => self.loan_txs_count,
+            "repay" => self.repay_txs_count,
+            "deposit" => self.deposit_txs_count,
+            "deposit_collateral" => self.deposit_collateral_txs_count,
+            "deposit_and_collateralize" => self.deposit_and_collateralize_txs_count,
+            "withdraw" => self.withdraw_txs_count,
+            "redeem" => self.redeem_txs_count,
+            "liquidate" => self.liquidation_txs_count,
+            "refresh_reserve" => self.refresh_reserve_txs_count,
+            "refresh_obligation" => self.refresh_obligation_txs_count,
+            _ => self.unknown_instruction_counts.values().sum(),
+        };
+        succeeded + self.failed_counts.get(name).copied().unwrap_or(0)
+    }
+
+    /// Per-mint [`AmountStats`] of the successful instructions of the given
+    /// [`TransactionType::name`], computed from the individual amounts in `records`
+    pub fn amount_stats(&self, name: &str) -> HashMap<Pubkey, AmountStats> {
+        let mut amounts: HashMap<Pubkey, Vec<u64>> = HashMap::new();
+        for record in self.records.iter().filter(|record| !record.failed && record.transaction_type.name() == name) {
+            if let (Some(mint), Some(amount)) = (record.reserve_token, record.transaction_type.amount()) {
+                amounts.entry(mint).or_default().push(amount);
+            }
+        }
+        amounts
+            .into_iter()
+            .filter_map(|(mint, amounts)| Some((mint, AmountStats::from_amounts(amounts)?)))
+            .collect()
+    }
+
+    /// [`FeeRate`] of every mint with paired flash loans in `records`. A reserve's fee rate is
+    /// configured, so every loan should pay about the same; one that does not may be misparsed.
+    /// Fees are rounded up to whole raw units, so small loans may pay up to one unit more.
+    pub fn flash_loan_fee_rates(&self) -> HashMap<Pubkey, FeeRate> {
+        let mut loans: HashMap<Pubkey, Vec<(&str, u64, u64, f64)>> = HashMap::new();
+        for record in self.records.iter().filter(|record| !record.failed) {
+            if let (TransactionType::FlashRepay { amount, fee: Some(fee) }, Some(bps), Some(mint)) =
+                (record.transaction_type, record.transaction_type.flash_loan_fee_bps(), record.reserve_token)
+            {
+                loans.entry(mint).or_default().push((&record.signature, amount - fee, fee, bps));
+            }
+        }
+        loans
+            .into_iter()
+            .map(|(mint, loans)| {
+                let mut rates: Vec<f64> = loans.iter().map(|(_, _, _, bps)| *bps).collect();
+                rates.sort_by(f64::total_cmp);
+                let mid = rates.len() / 2;
+                let median_bps = if rates.len().is_multiple_of(2) { (rates[mid - 1] + rates[mid]) / 2.0 } else { rates[mid] };
+                let borrowed: u64 = loans.iter().map(|(_, borrowed, _, _)| borrowed).sum();
+                let fees: u64 = loans.iter().map(|(_, _, fee, _)| fee).sum();
+                let outliers = loans
+                    .iter()
+                    .filter(|(_, borrowed, _, bps)| {
+                        (bps - median_bps).abs() > median_bps * FEE_RATE_TOLERANCE + 10_000.0 / *borrowed as f64
+                    })
+                    .map(|(signature, _, _, bps)| (signature.to_string(), *bps))
+                    .collect();
+                let rate = FeeRate {
+                    loans: loans.len(),
+                    average_bps: fees as f64 * 10_000.0 / borrowed as f64,
+                    median_bps,
+                    outliers,
+                };
+                (mint, rate)
+            })
+            .collect()
+    }
+
+    /// Activity per `width` of block time, keyed by the bucket's start in Unix seconds; buckets
+    /// are aligned to the epoch, so days run from midnight UTC. Failed, unknown, refresh and undated
+    /// records are left out, and so are buckets without activity.
+    pub fn time_buckets(&self, width: Duration) -> BTreeMap<i64, TimeBucket> {
+        let width = width.as_secs().max(1) as i64;
+        let mut buckets: BTreeMap<i64, TimeBucket> = BTreeMap::new();
+        let mut transactions: HashSet<(i64, &str)> = HashSet::new();
+        for record in &self.records {
+            let Some(block_time) = record.block_time else {
+                continue;
+            };
+            if record.failed || record.transaction_type.is_refresh() || matches!(record.transaction_type, TransactionType::Unknown { .. }) {
+                continue;
+            }
+            let start = block_time - block_time.rem_euclid(width);
+            let bucket = buckets.entry(start).or_default();
+            if transactions.insert((start, &record.signature)) {
+                bucket.transactions += 1;
+            }
+            if let (TransactionType::FlashBorrow { amount, .. } | TransactionType::Borrow { amount, .. }, Some(mint)) =
+                (&record.transaction_type, record.reserve_token)
+            {
+                bucket.borrows += 1;
+                *bucket.borrowed.entry(mint).or_insert(0) += amount;
+            }
+        }
+        buckets
+    }
+
+    /// Histogram of decoded instructions per transaction: how many transactions matched exactly
+    /// that many instructions. Failed, unknown and refresh instructions are not counted, and
+    /// transactions without a match do not appear.
+    pub fn instructions_per_transaction(&self) -> BTreeMap<usize, u64> {
+        let mut per_transaction: HashMap<&str, usize> = HashMap::new();
+        for record in &self.records {
+            if !record.failed && !record.transaction_type.is_refresh() && !matches!(record.transaction_type, TransactionType::Unknown { .. }) {
+                *per_transaction.entry(&record.signature).or_insert(0) += 1;
+            }
+        }
+        let mut histogram = BTreeMap::new();
+        for count in per_transaction.into_values() {
+            *histogram.entry(count).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Multiplies the per-mint totals, per-market borrows, flows and instruction counts by the
+    /// sample's [`Sample::factor`] to estimate those of every signature in the window. Individual
+    /// records, per-owner, per-referrer and per-obligation activity and unique user counts stay as
+    /// sampled, as do the error counts, which are compared against `transactions_scanned`.
+    pub fn extrapolate(&mut self, sample: Sample, population: usize) {
+        let factor = sample.factor();
+        let scale = |amount: &mut u64| *amount = (*amount as f64 * factor).round() as u64;
+        for amounts in [
+            &mut self.flash_loan_borrow_amounts,
+            &mut self.flash_loan_repay_amounts,
+            &mut self.flash_loan_fee_amounts,
+            &mut self.loan_borrow_amounts,
+            &mut self.repay_amounts,
+            &mut self.deposit_amounts,
+            &mut self.deposit_and_collateralize_amounts,
+            &mut self.deposit_collateral_by_reserve,
+            &mut self.redeem_amounts,
+            &mut self.liquidation_amounts,
+            &mut self.liquidation_collateral_amounts,
+            &mut self.withdraw_collateral_by_reserve,
+        ] {
+            amounts.values_mut().for_each(scale);
+        }
+        for mix in self.borrows_by_market.values_mut().flat_map(HashMap::values_mut) {
+            scale(&mut mix.flash_loan);
+            scale(&mut mix.obligation);
+        }
+        for flow in self.flows.values_mut() {
+            [&mut flow.borrowed, &mut flow.repaid, &mut flow.deposited, &mut flow.withdrawn].into_iter().for_each(scale);
+        }
+        [
+            &mut self.flash_loan_txs_count,
+            &mut self.flash_loan_repay_txs_count,
+            &mut self.loan_txs_count,
+            &mut self.repay_txs_count,
+            &mut self.deposit_txs_count,
+            &mut self.deposit_collateral_txs_count,
+            &mut self.deposit_and_collateralize_txs_count,
+            &mut self.withdraw_txs_count,
+            &mut self.redeem_txs_count,
+            &mut self.liquidation_txs_count,
+            &mut self.refresh_reserve_txs_count,
+            &mut self.refresh_obligation_txs_count,
+        ]
+        .into_iter()
+        .for_each(scale);
+        self.unknown_instruction_counts.values_mut().for_each(scale);
+        self.failed_counts.values_mut().for_each(scale);
+        self.sample = Some((sample, population));
+    }
+
+    /// Minutes between the oldest and newest block time scanned; `None` without block times, or
+    /// when they are all the same and a rate would divide by zero
+    pub fn window_minutes(&self) -> Option<f64> {
+        let (oldest, newest) = self.block_time_window?;
+        (newest > oldest).then(|| (newest - oldest) as f64 / 60.0)
+    }
+
+    /// Flash loan and obligation borrow volume per minute of the block time window for every
+    /// borrowed mint, comparable across windows of different lengths; `None` without a window to
+    /// divide by, see [`ParseSummary::window_minutes`]
+    pub fn borrow_rates(&self) -> Option<HashMap<Pubkey, BorrowRate>> {
+        let minutes = self.window_minutes()?;
+        let mut rates: HashMap<Pubkey, BorrowRate> = HashMap::new();
+        for (mint, amount) in &self.flash_loan_borrow_amounts {
+            rates.entry(*mint).or_default().flash_loan_per_minute = self.ui_amount(mint, *amount as i128) / minutes;
+        }
+        for (mint, amount) in &self.loan_borrow_amounts {
+            rates.entry(*mint).or_default().obligation_per_minute = self.ui_amount(mint, *amount as i128) / minutes;
+        }
+        Some(rates)
+    }
+
+    /// Scales a raw amount by the mint's decimals; mints without known decimals stay raw
+    pub fn ui_amount(&self, mint: &Pubkey, amount: i128) -> f64 {
+        self.mints.ui_amount(amount, mint)
+    }
+
+    /// Exact decimal rendering of a raw amount of `mint`
+    pub fn format_ui_amount(&self, mint: &Pubkey, amount: i128) -> String {
+        self.mints.format_ui_amount(amount, mint)
+    }
+}
+
+/// The human-readable breakdown of `--format text`, listing every mint
+impl fmt::Display for ParseSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_text(f, None, MintOrder::Mint)
+    }
+}
+
+/// [`ParseSummary`]'s per-token totals as an aligned table, see [`ParseSummary::display_table`]
+pub struct TokenTableDisplay<'a> {
+    summary: &'a ParseSummary,
+    order: MintOrder,
+}
+
+impl fmt::Display for TokenTableDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let summary = self.summary;
+        let mints: BTreeSet<&Pubkey> = summary
+            .flash_loan_borrow_amounts
+            .keys()
+            .chain(summary.loan_borrow_amounts.keys())
+            .chain(summary.repay_amounts.keys())
+            .chain(summary.flows.keys())
+            .collect();
+        let volume = |mint: &Pubkey| {
+            [&summary.flash_loan_borrow_amounts, &summary.loan_borrow_amounts, &summary.repay_amounts]
+                .iter()
+                .map(|amounts| amounts.get(mint).copied().unwrap_or(0) as i128)
+                .sum::<i128>()
+        };
+        let mut mints: Vec<(&Pubkey, i128)> = mints.into_iter().map(|mint| (mint, volume(mint))).collect();
+        summary.sort_mints(&mut mints, self.order, |(mint, volume)| (**mint, *volume));
+
+        // With raw amounts on, every amount column is followed by its raw integer
+        let raw_amounts = summary.mints.raw_amounts();
+        let mut header = vec!["Token".to_string()];
+        for column in ["Flash borrow", "Obligation borrow", "Repay", "Net flow"] {
+            header.push(column.to_string());
+            if raw_amounts {
+                header.push(format!("{} (raw)", column));
+            }
+        }
+
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL_CONDENSED);
+        table.set_header(header);
+        for (mint, _) in mints {
+            let net = summary.flows.get(mint).map_or(0, MintFlow::net);
+            let amounts = [
+                summary.flash_loan_borrow_amounts.get(mint).copied().unwrap_or(0) as i128,
+                summary.loan_borrow_amounts.get(mint).copied().unwrap_or(0) as i128,
+                summary.repay_amounts.get(mint).copied().unwrap_or(0) as i128,
+                net,
+            ];
+            let mut row = vec![summary.mints.symbol(mint)];
+            for (column, amount) in amounts.into_iter().enumerate() {
+                // Only the net flow is signed
+                let sign = if column == 3 && amount > 0 { "+" } else { "" };
+                row.push(format!("{}{}", sign, summary.format_ui_amount(mint, amount)));
+                if raw_amounts {
+                    row.push(format!("{}{}", sign, amount));
+                }
+            }
+            table.add_row(row);
+        }
+        for column in table.column_iter_mut().skip(1) {
+            column.set_cell_alignment(CellAlignment::Right);
+        }
+        write!(f, "{}", table)
+    }
+}
+
+/// [`ParseSummary`]'s text breakdown in a chosen mint order, optionally with borrow volumes cut
+/// to the largest mints, see [`ParseSummary::display_sorted`]
+pub struct TextDisplay<'a> {
+    summary: &'a ParseSummary,
+    top_n: Option<usize>,
+    order: MintOrder,
+}
+
+impl fmt::Display for TextDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.summary.write_text(f, self.top_n, self.order)
+    }
+}
+
+impl ParseSummary {
+    /// Text breakdown listing only the `top_n` mints with the highest flash loan and obligation
+    /// borrow volume, each category ranked on its own, and how many others were left out
+    pub fn display_top(&self, top_n: usize) -> TextDisplay<'_> {
+        self.display_sorted(MintOrder::Volume, Some(top_n))
+    }
+
+    /// Text breakdown with every per-token list in `order`; with `top_n`, flash loan and
+    /// obligation borrows keep only their `top_n` largest mints, listed in `order` as well
+    pub fn display_sorted(&self, order: MintOrder, top_n: Option<usize>) -> TextDisplay<'_> {
+        TextDisplay { summary: self, top_n, order }
+    }
+
+    /// One table row per token with its flash loan, obligation borrow and repay volume and its
+    /// net flow into the reserves (+ for inflow), like the net flow lines of the text breakdown;
+    /// [`MintOrder::Volume`] ranks rows by the three volumes combined
+    pub fn display_table(&self, order: MintOrder) -> TokenTableDisplay<'_> {
+        TokenTableDisplay { summary: self, order }
+    }
+
+    /// Per-mint volumes and instruction counts of this summary next to those of `baseline`, an
+    /// earlier window, with the change between them
+    pub fn compare<'a>(&'a self, baseline: &'a ParseSummary) -> SummaryComparison<'a> {
+        SummaryComparison { baseline, current: self }
+    }
+
+    /// Flash loan and obligation borrow volume of every borrowed mint
+    pub fn borrow_mix(&self) -> HashMap<Pubkey, BorrowMix> {
+        let mut mix: HashMap<Pubkey, BorrowMix> = HashMap::new();
+        for (mint, amount) in &self.flash_loan_borrow_amounts {
+            mix.entry(*mint).or_default().flash_loan += amount;
+        }
+        for (mint, amount) in &self.loan_borrow_amounts {
+            mix.entry(*mint).or_default().obligation += amount;
+        }
+        mix
+    }
+
+    /// Per-mint `amounts` sorted by volume in UI units, largest first, cut to `top_n` when given;
+    /// also returns how many mints were cut
+    pub fn ranked_mints<'a>(&self, amounts: &'a HashMap<Pubkey, u64>, top_n: Option<usize>) -> (Vec<(&'a Pubkey, u64)>, usize) {
+        let mut ranked = self.sorted_mints(amounts, MintOrder::Volume);
+        let others = top_n.map_or(0, |top_n| ranked.len().saturating_sub(top_n));
+        ranked.truncate(ranked.len() - others);
+        (ranked, others)
+    }
+
+    /// Per-mint `amounts` in `order`
+    pub fn sorted_mints<'a>(&self, amounts: &'a HashMap<Pubkey, u64>, order: MintOrder) -> Vec<(&'a Pubkey, u64)> {
+        let mut sorted: Vec<(&Pubkey, u64)> = amounts.iter().map(|(mint, amount)| (mint, *amount)).collect();
+        self.sort_mints(&mut sorted, order, |(mint, amount)| (**mint, *amount as i128));
+        sorted
+    }
+
+    /// Sorts `entries` in `order` by the mint and amount `key` gives for each; volumes are
+    /// compared in UI units by magnitude, so net amounts rank by how much they moved
+    fn sort_mints<T>(&self, entries: &mut [T], order: MintOrder, key: impl Fn(&T) -> (Pubkey, i128)) {
+        match order {
+            MintOrder::Mint => entries.sort_by_cached_key(|entry| key(entry).0.to_string()),
+            MintOrder::Volume => entries.sort_by(|a, b| {
+                let ((a_mint, a), (b_mint, b)) = (key(a), key(b));
+                self.ui_amount(&b_mint, b.abs())
+                    .total_cmp(&self.ui_amount(&a_mint, a.abs()))
+                    .then_with(|| a_mint.to_string().cmp(&b_mint.to_string()))
+            }),
+        }
+    }
+
+    fn write_text(&self, f: &mut fmt::Formatter<'_>, top_n: Option<usize>, order: MintOrder) -> fmt::Result {
+        match self.block_time_window {
+            Some((oldest, newest)) => writeln!(
+                f,
+                "Breakdown for Kamino loans by mint over {} ({} to {})",
+                humantime::format_duration(Duration::from_secs((newest - oldest).max(0) as u64)),
+                rfc3339(oldest),
+                rfc3339(newest)
+            )?,
+            None => writeln!(f, "Breakdown for Kamino loans by mint")?,
+        }
+        if let Some((sample, population)) = self.sample {
+            writeln!(
+                f,
+                "ESTIMATE from a random {}% sample (seed {}): {} of {} transactions processed, totals and counts extrapolated x{}",
+                sample.rate * 100.0,
+                sample.seed,
+                self.transactions_scanned,
+                population,
+                sample.factor()
+            )?;
+        }
+        if let Some(max_transactions) = self.truncated_to {
+            writeln!(f, "Truncated to the latest {} transactions (--max-transactions); totals are not complete", max_transactions)?;
+        }
+        if self.interrupted {
+            writeln!(f, "Interrupted: partial results over the {} transactions fetched", self.transactions_scanned)?;
+        }
+        if !self.fetch_errors.is_empty() {
+            writeln!(f, "Transactions skipped due to fetch errors: {}", self.fetch_errors.len())?;
+        }
+        if self.decode_errors > 0 {
+            writeln!(f, "Transactions skipped due to decode errors: {}", self.decode_errors)?;
+        }
+        if !self.lookup_errors.is_empty() {
+            writeln!(
+                f,
+                "Transactions skipped due to unresolved lookup tables: {} ({} tables)",
+                self.lookup_errors.len(),
+                self.unresolved_lookup_tables.len()
+            )?;
+        }
+        let filter = &self.account_filter;
+        if !filter.reserves.is_empty() {
+            let reserves: Vec<String> = filter.reserves.iter().map(Pubkey::to_string).collect();
+            writeln!(f, "Filtered to reserves: {}", reserves.join(", "))?;
+        }
+        if !filter.markets.is_empty() {
+            let markets: Vec<String> = filter.markets.iter().map(Pubkey::to_string).collect();
+            writeln!(f, "Filtered to markets: {}", markets.join(", "))?;
+        }
+        if !filter.include_mints.is_empty() {
+            let mints: Vec<String> = filter.include_mints.iter().map(|mint| self.mints.symbol(mint)).collect();
+            writeln!(f, "Filtered to mints: {}", mints.join(", "))?;
+        } else if !filter.exclude_mints.is_empty() {
+            let mints: Vec<String> = filter.exclude_mints.iter().map(|mint| self.mints.symbol(mint)).collect();
+            writeln!(f, "Excluding mints: {}", mints.join(", "))?;
+        }
+        if !filter.instruction_types.is_empty() {
+            writeln!(f, "Filtered to instruction types: {}", filter.instruction_types.join(", "))?;
+        }
+
+        let (mut flash_borrows, others) = self.ranked_mints(&self.flash_loan_borrow_amounts, top_n);
+        self.sort_mints(&mut flash_borrows, order, |(mint, amount)| (**mint, *amount as i128));
+        for (mint, amount) in flash_borrows {
+            writeln!(f, "Flash Loan Borrow {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, amount as i128))?;
+        }
+        if others > 0 {
+            writeln!(f, "Flash Loan Borrow ... and {} others", others)?;
+        }
+        for (mint, amount) in self.sorted_mints(&self.flash_loan_repay_amounts, order) {
+            writeln!(f, "Flash Loan Repay {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, amount as i128))?;
+        }
+        for (mint, amount) in self.sorted_mints(&self.flash_loan_fee_amounts, order) {
+            writeln!(f, "Flash Loan Fees {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, amount as i128))?;
+        }
+        let fee_rates = self.flash_loan_fee_rates();
+        let mut fee_rates: Vec<(&Pubkey, &FeeRate)> = fee_rates.iter().collect();
+        self.sort_mints(&mut fee_rates, order, |(mint, _)| (**mint, self.flash_loan_fee_amounts.get(*mint).copied().unwrap_or(0) as i128));
+        for (mint, rate) in fee_rates {
+            let symbol = self.mints.symbol(mint);
+            writeln!(f, "Flash Loan Fee Rate {}: {:.2} bps average over {} loans", symbol, rate.average_bps, rate.loans)?;
+            for (signature, bps) in &rate.outliers {
+                writeln!(f, "  Unusual fee in {}: {:.2} bps, typically {:.2} bps", signature, bps, rate.median_bps)?;
+            }
+        }
+        let (mut borrows, others) = self.ranked_mints(&self.loan_borrow_amounts, top_n);
+        self.sort_mints(&mut borrows, order, |(mint, amount)| (**mint, *amount as i128));
+        for (mint, amount) in borrows {
+            writeln!(f, "Borrow obligation {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, amount as i128))?;
+        }
+        if others > 0 {
+            writeln!(f, "Borrow obligation ... and {} others", others)?;
+        }
+        for (mint, amount) in self.sorted_mints(&self.repay_amounts, order) {
+            writeln!(f, "Repay obligation {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, amount as i128))?;
+        }
+        for (mint, amount) in self.sorted_mints(&self.deposit_amounts, order) {
+            writeln!(f, "Deposit reserve liquidity {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, amount as i128))?;
+        }
+        for (mint, amount) in self.sorted_mints(&self.deposit_and_collateralize_amounts, order) {
+            writeln!(f, "Deposit liquidity as collateral {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, amount as i128))?;
+        }
+        for (mint, amount) in self.sorted_mints(&self.redeem_amounts, order) {
+            writeln!(f, "Redeem reserve collateral {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, amount as i128))?;
+        }
+        for (mint, amount) in self.sorted_mints(&self.liquidation_amounts, order) {
+            writeln!(f, "Liquidated debt {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, amount as i128))?;
+        }
+        for (mint, amount) in self.sorted_mints(&self.liquidation_collateral_amounts, order) {
+            writeln!(f, "Liquidated collateral (minimum accepted) {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, amount as i128))?;
+        }
+        // Raw collateral units do not compare across reserves, so these go by address either way
+        for (reserve, amount) in self.sorted_mints(&self.deposit_collateral_by_reserve, MintOrder::Mint) {
+            writeln!(f, "Deposit obligation collateral (raw collateral units) to reserve {}: {:?}", reserve, amount)?;
+        }
+        for (reserve, amount) in self.sorted_mints(&self.withdraw_collateral_by_reserve, MintOrder::Mint) {
+            writeln!(f, "Withdraw obligation collateral (raw collateral units) from reserve {}: {:?}", reserve, amount)?;
+        }
+
+        // Types left out with an instruction type filter would always count 0
+        for (label, name, count) in [
+            ("Flash loan txs count", "flash_borrow", self.flash_loan_txs_count),
+            ("Flash loan repay txs count", "flash_repay", self.flash_loan_repay_txs_count),
+            ("Loan txs count", "borrow", self.loan_txs_count),
+            ("Repay txs count", "repay", self.repay_txs_count),
+            ("Deposit txs count", "deposit", self.deposit_txs_count),
+            ("Deposit collateral txs count", "deposit_collateral", self.deposit_collateral_txs_count),
+            ("Deposit and collateralize txs count", "deposit_and_collateralize", self.deposit_and_collateralize_txs_count),
+            ("Withdraw txs count", "withdraw", self.withdraw_txs_count),
+            ("Redeem txs count", "redeem", self.redeem_txs_count),
+            ("Liquidation txs count", "liquidate", self.liquidation_txs_count),
+        ] {
+            if filter.matches_type(name) {
+                writeln!(f, "{}: {:?}", label, count)?;
+            }
+        }
+        // How broad the activity is, beyond its volume
+        writeln!(f, "Unique borrowers: {}", self.unique_borrowers())?;
+        writeln!(f, "Unique flash loan initiators: {}", self.flash_loan_initiators.len())?;
+        writeln!(f, "Unique obligations: {}", self.unique_obligations())?;
+        // Several matched instructions in one transaction usually means a batched or arbitrage flow
+        let histogram = self.instructions_per_transaction();
+        let multi_instruction: u64 = histogram.range(2..).map(|(_, transactions)| transactions).sum();
+        writeln!(f, "Transactions with more than one matched instruction: {}", multi_instruction)?;
+        if let Some(max) = histogram.keys().next_back() {
+            writeln!(f, "Most matched instructions in one transaction: {}", max)?;
+        }
+        for (instructions, transactions) in &histogram {
+            writeln!(f, "Transactions with {} matched instructions: {}", instructions, transactions)?;
+        }
+        let unknown: BTreeMap<_, _> = self.unknown_instruction_counts.iter().collect();
+        for (discriminator, count) in unknown {
+            writeln!(f, "Unknown instruction {} count: {:?}", hex(discriminator), count)?;
+        }
+        let failed_counts: BTreeMap<_, _> = self.failed_counts.iter().collect();
+        for (name, failed) in failed_counts {
+            writeln!(f, "Failed {} attempts: {} of {} attempted", name, failed, self.attempted_count(name))?;
+        }
+
+        // Net outstanding borrows over the window; repays can exceed borrows, so go signed
+        let borrowed_mints: HashSet<&Pubkey> = self.loan_borrow_amounts.keys().chain(self.repay_amounts.keys()).collect();
+        let mut net_borrowed: Vec<(&Pubkey, i128)> = borrowed_mints
+            .into_iter()
+            .map(|mint| {
+                let borrowed = self.loan_borrow_amounts.get(mint).copied().unwrap_or(0) as i128;
+                let repaid = self.repay_amounts.get(mint).copied().unwrap_or(0) as i128;
+                (mint, borrowed - repaid)
+            })
+            .collect();
+        self.sort_mints(&mut net_borrowed, order, |(mint, net)| (**mint, *net));
+        for (mint, net) in net_borrowed {
+            writeln!(f, "Net borrowed {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, net))?;
+        }
+
+        // Redeems are denominated in collateral (cToken) units, which trade above par as interest
+        // accrues, so this slightly understates outflows
+        let liquidity_mints: HashSet<&Pubkey> = self.deposit_amounts.keys().chain(self.redeem_amounts.keys()).collect();
+        let mut net_provided: Vec<(&Pubkey, i128)> = liquidity_mints
+            .into_iter()
+            .map(|mint| {
+                let deposited = self.deposit_amounts.get(mint).copied().unwrap_or(0) as i128;
+                let redeemed = self.redeem_amounts.get(mint).copied().unwrap_or(0) as i128;
+                (mint, deposited - redeemed)
+            })
+            .collect();
+        self.sort_mints(&mut net_provided, order, |(mint, net)| (**mint, *net));
+        for (mint, net) in net_provided {
+            writeln!(f, "Net liquidity provided {}: {}", self.mints.symbol(mint), self.format_ui_amount(mint, net))?;
+        }
+
+        // Every flow of a mint on one line, signed so the direction of liquidity stands out
+        if !self.flows.is_empty() {
+            writeln!(f, "Net flow by mint (into the reserves is +, out of them is -):")?;
+            // By volume, a mint's flows rank by everything that moved, not just the net change
+            let mut flows: Vec<(&Pubkey, &MintFlow)> = self.flows.iter().collect();
+            self.sort_mints(&mut flows, order, |(mint, flow)| {
+                (**mint, flow.borrowed as i128 + flow.repaid as i128 + flow.deposited as i128 + flow.withdrawn as i128)
+            });
+            for (mint, flow) in flows {
+                let net = flow.net();
+                let direction = match net.cmp(&0) {
+                    Ordering::Greater => "inflow",
+                    Ordering::Less => "outflow",
+                    Ordering::Equal => "flat",
+                };
+                writeln!(
+                    f,
+                    "  {}: borrowed {}, repaid {}, deposited {}, withdrawn {}, net {}{} ({})",
+                    self.mints.symbol(mint),
+                    self.format_ui_amount(mint, flow.borrowed as i128),
+                    self.format_ui_amount(mint, flow.repaid as i128),
+                    self.format_ui_amount(mint, flow.deposited as i128),
+                    self.format_ui_amount(mint, flow.withdrawn as i128),
+                    if net > 0 { "+" } else { "" },
+                    self.format_ui_amount(mint, net),
+                    direction
+                )?;
+            }
+        }
+
+        // Volume over time, to compare windows of different lengths
+        let borrowed_mints: BTreeSet<&Pubkey> = self.flash_loan_borrow_amounts.keys().chain(self.loan_borrow_amounts.keys()).collect();
+        if !borrowed_mints.is_empty() {
+            match self.borrow_rates() {
+                Some(rates) => {
+                    writeln!(f, "Borrow rate by mint (per minute, per hour):")?;
+                    let mut rates: Vec<(&Pubkey, &BorrowRate)> = rates.iter().collect();
+                    self.sort_mints(&mut rates, order, |(mint, _)| {
+                        let volume = |amounts: &HashMap<Pubkey, u64>| amounts.get(*mint).copied().unwrap_or(0) as i128;
+                        (**mint, volume(&self.flash_loan_borrow_amounts) + volume(&self.loan_borrow_amounts))
+                    });
+                    for (mint, rate) in rates {
+                        writeln!(
+                            f,
+                            "  {}: flash loans {}/min ({}/h), obligation borrows {}/min ({}/h)",
+                            self.mints.symbol(mint),
+                            self.mints.format_ui_value(rate.flash_loan_per_minute, mint),
+                            self.mints.format_ui_value(rate.flash_loan_per_hour(), mint),
+                            self.mints.format_ui_value(rate.obligation_per_minute, mint),
+                            self.mints.format_ui_value(rate.obligation_per_hour(), mint)
+                        )?;
+                    }
+                }
+                None if self.block_time_window.is_some() => {
+                    writeln!(f, "Borrow rate: not computed, every transaction has the same block time")?;
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Two summaries of the same program over different windows, see [`ParseSummary::compare`]
+pub struct SummaryComparison<'a> {
+    pub baseline: &'a ParseSummary,
+    pub current: &'a ParseSummary,
+}
+
+impl SummaryComparison<'_> {
+    /// Per-mint amounts compared, by label and JSON key
+    fn amounts(summary: &ParseSummary) -> [(&'static str, &'static str, &HashMap<Pubkey, u64>); 6] {
+        [
+            ("Flash Loan Borrow", "flash_loan_borrows", &summary.flash_loan_borrow_amounts),
+            ("Borrow obligation", "obligation_borrows", &summary.loan_borrow_amounts),
+            ("Repay obligation", "repays", &summary.repay_amounts),
+            ("Deposit reserve liquidity", "deposits", &summary.deposit_amounts),
+            ("Redeem reserve collateral", "redeems", &summary.redeem_amounts),
+            ("Liquidated debt", "liquidations", &summary.liquidation_amounts),
+        ]
+    }
+
+    /// Instruction counts compared, by label and JSON key
+    fn counts(summary: &ParseSummary) -> [(&'static str, &'static str, u64); 6] {
+        [
+            ("Flash loan txs count", "flash_loan", summary.flash_loan_txs_count),
+            ("Loan txs count", "borrow", summary.loan_txs_count),
+            ("Repay txs count", "repay", summary.repay_txs_count),
+            ("Deposit txs count", "deposit", summary.deposit_txs_count),
+            ("Redeem txs count", "redeem", summary.redeem_txs_count),
+            ("Liquidation txs count", "liquidate", summary.liquidation_txs_count),
+        ]
+    }
+
+    /// Mints with an amount in either window, by symbol, with their baseline and current raw amounts
+    fn mint_changes(&self, baseline: &HashMap<Pubkey, u64>, current: &HashMap<Pubkey, u64>) -> Vec<(String, Pubkey, u64, u64)> {
+        let mints: BTreeSet<&Pubkey> = baseline.keys().chain(current.keys()).collect();
+        let mut changes: Vec<(String, Pubkey, u64, u64)> = mints
+            .into_iter()
+            .map(|mint| {
+                let amount = |amounts: &HashMap<Pubkey, u64>| amounts.get(mint).copied().unwrap_or(0);
+                (self.current.mints.symbol(mint), *mint, amount(baseline), amount(current))
+            })
+            .collect();
+        changes.sort_by(|(a_symbol, a_mint, ..), (b_symbol, b_mint, ..)| a_symbol.cmp(b_symbol).then(a_mint.cmp(b_mint)));
+        changes
+    }
+}
+
+/// Change from `baseline` to `current` in percent, `None` when there was nothing to start from
+fn percent_change(baseline: f64, current: f64) -> Option<f64> {
+    (baseline != 0.0).then(|| (current - baseline) / baseline * 100.0)
+}
+
+fn format_change(baseline: f64, current: f64) -> String {
+    match percent_change(baseline, current) {
+        Some(change) => format!("{:+.1}%", change),
+        None if current == 0.0 => "unchanged".to_string(),
+        None => "new".to_string(),
+    }
+}
+
+/// The `--compare` text output: every compared amount and count as `baseline -> current (change)`
+impl fmt::Display for SummaryComparison<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let window = |summary: &ParseSummary| match summary.block_time_window {
+            Some((oldest, newest)) => format!("{} to {}", rfc3339(oldest), rfc3339(newest)),
+            None => "unknown window".to_string(),
+        };
+        writeln!(f, "Comparison of {} against {}", window(self.current), window(self.baseline))?;
+        for summary in [self.baseline, self.current] {
+            if summary.truncated_to.is_some() || summary.interrupted || summary.skipped_count() > 0 {
+                writeln!(f, "Totals of {} are not complete", window(summary))?;
+            }
+        }
+        for ((label, _, baseline), (_, _, current)) in Self::amounts(self.baseline).into_iter().zip(Self::amounts(self.current)) {
+            for (symbol, mint, before, after) in self.mint_changes(baseline, current) {
+                writeln!(
+                    f,
+                    "{} {}: {} -> {} ({})",
+                    label,
+                    symbol,
+                    self.baseline.format_ui_amount(&mint, before as i128),
+                    self.current.format_ui_amount(&mint, after as i128),
+                    format_change(before as f64, after as f64)
+                )?;
+            }
+        }
+        for ((label, _, before), (_, _, after)) in Self::counts(self.baseline).into_iter().zip(Self::counts(self.current)) {
+            writeln!(f, "{}: {} -> {} ({})", label, before, after, format_change(before as f64, after as f64))?;
+        }
+        Ok(())
+    }
+}
+
+/// The `--compare` JSON object: both windows, then per-mint `baseline`, `current` and
+/// `change_percent` (null without a baseline amount) under the same keys as the summary's
+impl Serialize for SummaryComparison<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let window = |summary: &ParseSummary| {
+            summary.block_time_window.map(|(oldest, newest)| json!({ "start": rfc3339(oldest), "end": rfc3339(newest) }))
+        };
+        let change = |baseline: f64, current: f64| {
+            json!({ "baseline": baseline, "current": current, "change_percent": percent_change(baseline, current) })
+        };
+        let mut value = serde_json::Map::new();
+        value.insert("baseline_window".to_string(), json!(window(self.baseline)));
+        value.insert("current_window".to_string(), json!(window(self.current)));
+        for ((_, key, baseline), (_, _, current)) in Self::amounts(self.baseline).into_iter().zip(Self::amounts(self.current)) {
+            let mints: serde_json::Map<String, serde_json::Value> = self
+                .mint_changes(baseline, current)
+                .into_iter()
+                .map(|(_, mint, before, after)| {
+                    (mint.to_string(), change(self.baseline.ui_amount(&mint, before as i128), self.current.ui_amount(&mint, after as i128)))
+                })
+                .collect();
+            value.insert(key.to_string(), mints.into());
+        }
+        let counts: serde_json::Map<String, serde_json::Value> = Self::counts(self.baseline)
+            .into_iter()
+            .zip(Self::counts(self.current))
+            .map(|((_, key, before), (_, _, after))| {
+                let change_percent = percent_change(before as f64, after as f64);
+                (key.to_string(), json!({ "baseline": before, "current": after, "change_percent": change_percent }))
+            })
+            .collect();
+        value.insert("counts".to_string(), counts.into());
+        value.serialize(serializer)
+    }
+}
+
+/// The `--format json` object: per-mint totals as UI amounts keyed by mint address, plus counts
+impl Serialize for ParseSummary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let ui_amounts = |amounts: &HashMap<Pubkey, u64>| -> serde_json::Map<String, serde_json::Value> {
+            amounts
+                .iter()
+                .map(|(mint, amount)| (mint.to_string(), self.mints.amount_json(*amount as i128, mint)))
+                .collect()
+        };
+        let value = json!({
+            "filter": (!self.account_filter.is_empty()).then(|| json!({
+                "reserves": self.account_filter.reserves.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+                "markets": self.account_filter.markets.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+                "include_mints": self.account_filter.include_mints.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+                "exclude_mints": self.account_filter.exclude_mints.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+                "instruction_types": self.account_filter.instruction_types,
+            })),
+            "truncated_to": self.truncated_to,
+            "sample": self.sample.map(|(sample, population)| json!({
+                "rate": sample.rate,
+                "seed": sample.seed,
+                "population": population,
+                "extrapolation_factor": sample.factor(),
+            })),
+            "interrupted": self.interrupted,
+            "skipped": {
+                "fetch_errors": self.fetch_errors,
+                "lookup_errors": self.lookup_errors,
+                "decode_errors": self.decode_errors,
+                "unresolved_lookup_tables": self.unresolved_lookup_tables.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+            },
+            "window": self.block_time_window.map(|(oldest, newest)| json!({
+                "first_block_time": oldest,
+                "last_block_time": newest,
+                "start": rfc3339(oldest),
+                "end": rfc3339(newest),
+                "seconds": newest - oldest,
+            })),
+            "flash_loan_borrows": ui_amounts(&self.flash_loan_borrow_amounts),
+            "flash_loan_repays": ui_amounts(&self.flash_loan_repay_amounts),
+            "flash_loan_fees": ui_amounts(&self.flash_loan_fee_amounts),
+            "flash_loan_fee_rates": self.flash_loan_fee_rates()
+                .iter()
+                .map(|(mint, rate)| (mint.to_string(), json!({
+                    "loans": rate.loans,
+                    "average_bps": rate.average_bps,
+                    "median_bps": rate.median_bps,
+                    "outliers": rate.outliers.iter().map(|(signature, bps)| json!({ "signature": signature, "fee_bps": bps })).collect::<Vec<_>>(),
+                })))
+                .collect::<serde_json::Map<_, _>>(),
+            "obligation_borrows": ui_amounts(&self.loan_borrow_amounts),
+            "repays": ui_amounts(&self.repay_amounts),
+            "deposits": ui_amounts(&self.deposit_amounts),
+            "deposits_as_collateral": ui_amounts(&self.deposit_and_collateralize_amounts),
+            "redeems": ui_amounts(&self.redeem_amounts),
+            "liquidations": ui_amounts(&self.liquidation_amounts),
+            "liquidated_collateral_min": ui_amounts(&self.liquidation_collateral_amounts),
+            "deposited_collateral_by_reserve": self.deposit_collateral_by_reserve
+                .iter()
+                .map(|(reserve, amount)| (reserve.to_string(), json!(amount)))
+                .collect::<serde_json::Map<_, _>>(),
+            "net_flows": self.flows
+                .iter()
+                .map(|(mint, flow)| (mint.to_string(), json!({
+                    "borrowed": self.mints.amount_json(flow.borrowed as i128, mint),
+                    "repaid": self.mints.amount_json(flow.repaid as i128, mint),
+                    "deposited": self.mints.amount_json(flow.deposited as i128, mint),
+                    "withdrawn": self.mints.amount_json(flow.withdrawn as i128, mint),
+                    "net": self.mints.amount_json(flow.net(), mint),
+                })))
+                .collect::<serde_json::Map<_, _>>(),
+            "borrow_rates": self.borrow_rates().map(|rates| rates
+                .iter()
+                .map(|(mint, rate)| (mint.to_string(), json!({
+                    "flash_loan_per_minute": rate.flash_loan_per_minute,
+                    "flash_loan_per_hour": rate.flash_loan_per_hour(),
+                    "obligation_per_minute": rate.obligation_per_minute,
+                    "obligation_per_hour": rate.obligation_per_hour(),
+                })))
+                .collect::<serde_json::Map<_, _>>()),
+            "withdrawn_collateral_by_reserve": self.withdraw_collateral_by_reserve
+                .iter()
+                .map(|(reserve, amount)| (reserve.to_string(), json!(amount)))
+                .collect::<serde_json::Map<_, _>>(),
+            "counts": {
+                "flash_loan": self.flash_loan_txs_count,
+                "flash_loan_repay": self.flash_loan_repay_txs_count,
+                "borrow": self.loan_txs_count,
+                "repay": self.repay_txs_count,
+                "deposit": self.deposit_txs_count,
+                "deposit_collateral": self.deposit_collateral_txs_count,
+                "deposit_and_collateralize": self.deposit_and_collateralize_txs_count,
+                "withdraw": self.withdraw_txs_count,
+                "redeem": self.redeem_txs_count,
+                "liquidate": self.liquidation_txs_count,
+                "refresh_reserve": self.refresh_reserve_txs_count,
+                "refresh_obligation": self.refresh_obligation_txs_count,
+            },
+            "unique": {
+                "borrowers": self.unique_borrowers(),
+                "flash_loan_initiators": self.flash_loan_initiators.len(),
+                "obligations": self.unique_obligations(),
+            },
+            "instructions_per_transaction": self.instructions_per_transaction()
+                .iter()
+                .map(|(instructions, transactions)| (instructions.to_string(), json!(transactions)))
+                .collect::<serde_json::Map<_, _>>(),
+            "unknown_instructions": self.unknown_instruction_counts
+                .iter()
+                .map(|(discriminator, count)| (hex(discriminator), json!(count)))
+                .collect::<serde_json::Map<_, _>>(),
+            "failed_attempts": self.failed_counts
+                .iter()
+                .map(|(name, failed)| (name.to_string(), json!({
+                    "attempted": self.attempted_count(name),
+                    "failed": failed,
+                })))
+                .collect::<serde_json::Map<_, _>>(),
+        });
+        value.serialize(serializer)
+    }
+}
+
+/// A unix block time as an RFC3339 UTC timestamp, e.g. `2024-01-23T08:54:00Z`
+pub fn rfc3339(timestamp: i64) -> String {
+    humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(timestamp.max(0) as u64)).to_string()
+}
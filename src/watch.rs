@@ -0,0 +1,376 @@
+//! Live parsing of new Kamino Lend transactions over a `logsSubscribe` WebSocket subscription,
+//! or by polling the signature history over plain HTTP, and live reserve state over
+//! `accountSubscribe`.
+
+use crate::rpc::{fetch_signatures_until, fetch_transaction, history_commitment};
+use crate::reserve::Reserve;
+use crate::{fill_mint_decimals, parse_fetched_transaction, AccountFilter, LookupCache, KaminoTransaction, MintRegistry, ParseSummary};
+use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::UiTransactionEncoding;
+use std::collections::{HashSet, VecDeque};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use url::Url;
+
+// First reconnect delay after the subscription drops, doubled up to RECONNECT_MAX_DELAY
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+// Processed signatures remembered by `follow` to skip ones a later poll lists again
+const FOLLOW_SEEN_SIGNATURES: usize = 10_000;
+
+/// Knobs for [`watch`] and [`follow`]
+#[derive(Clone, Debug)]
+pub struct WatchOptions {
+    /// Retries for transient RPC failures when fetching a new transaction
+    pub rpc_retries: u32,
+    /// How often running totals are reported
+    pub report_interval: Duration,
+    /// Only keep instructions touching these reserves or markets
+    pub account_filter: AccountFilter,
+    /// Mint metadata known upfront, such as `--token-list` symbols; the summary starts from it
+    pub mints: MintRegistry,
+    /// Lookup tables known upfront, such as from the disk cache; the summary starts from them
+    pub lookup_tables: LookupCache,
+}
+
+/// Derives the WebSocket endpoint from an HTTP RPC URL the way the Solana CLI does: `http` becomes
+/// `ws`, `https` becomes `wss`, and an explicit port moves up by one (8899 -> 8900).
+pub fn websocket_url(rpc_url: &str) -> Result<String> {
+    let mut url = Url::parse(rpc_url).with_context(|| format!("RPC URL {:?} is not a valid URL", rpc_url))?;
+    let scheme = match url.scheme() {
+        "https" => "wss",
+        _ => "ws",
+    };
+    url.set_scheme(scheme).ok();
+    if let Some(port) = url.port() {
+        url.set_port(Some(port + 1)).ok();
+    }
+    Ok(url.to_string())
+}
+
+/// Subscribes to the program's logs and parses every successful transaction as it lands,
+/// calling `on_record` for each parsed instruction and `on_report` with the running totals
+/// every `report_interval`. Reconnects with backoff when the subscription drops and returns
+/// the accumulated summary on Ctrl-C.
+pub async fn watch(
+    client: &RpcClient,
+    ws_url: &str,
+    program_id: &Pubkey,
+    opts: WatchOptions,
+    mut on_record: impl FnMut(&KaminoTransaction, &ParseSummary),
+    mut on_report: impl FnMut(&ParseSummary),
+) -> Result<ParseSummary> {
+    let mut summary = ParseSummary {
+        account_filter: opts.account_filter.clone(),
+        mints: opts.mints.clone(),
+        lookup_tables: opts.lookup_tables.clone(),
+        ..Default::default()
+    };
+    let mut reconnect_delay = RECONNECT_BASE_DELAY;
+    let mut report = tokio::time::interval(opts.report_interval);
+    report.tick().await;
+
+    // Notifications arrive at confirmed commitment, before the transaction is finalized
+    let config = RpcTransactionConfig {
+        commitment: CommitmentConfig::confirmed().into(),
+        encoding: UiTransactionEncoding::Base64.into(),
+        max_supported_transaction_version: Some(0),
+    };
+    // One listener for the whole watch, so a Ctrl-C while a transaction is being fetched or
+    // between selects is not lost
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
+
+    loop {
+        let pubsub = match PubsubClient::new(ws_url).await {
+            Ok(pubsub) => pubsub,
+            Err(e) => {
+                warn!("Failed to connect to {} (retrying in {:?}): {}", ws_url, reconnect_delay, e);
+                tokio::select! {
+                    _ = tokio::time::sleep(reconnect_delay) => {}
+                    _ = &mut shutdown => return Ok(summary),
+                }
+                reconnect_delay = (reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
+                continue;
+            }
+        };
+        let filter = RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]);
+        let logs_config = RpcTransactionLogsConfig {
+            commitment: Some(CommitmentConfig::confirmed()),
+        };
+        let (mut notifications, unsubscribe) = match pubsub.logs_subscribe(filter, logs_config).await {
+            Ok(subscription) => subscription,
+            Err(e) => {
+                warn!("Failed to subscribe to program logs (retrying in {:?}): {}", reconnect_delay, e);
+                tokio::select! {
+                    _ = tokio::time::sleep(reconnect_delay) => {}
+                    _ = &mut shutdown => return Ok(summary),
+                }
+                reconnect_delay = (reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
+                continue;
+            }
+        };
+        info!(%program_id, "Watching {} for new transactions", ws_url);
+        reconnect_delay = RECONNECT_BASE_DELAY;
+
+        let stopped = loop {
+            tokio::select! {
+                notification = notifications.next() => {
+                    let Some(notification) = notification else {
+                        break false;
+                    };
+                    let slot = notification.context.slot;
+                    let logs = notification.value;
+                    if logs.err.is_some() {
+                        debug!(signature = %logs.signature, "Skipping failed transaction");
+                        continue;
+                    }
+                    let Ok(signature) = Signature::from_str(&logs.signature) else {
+                        continue;
+                    };
+                    summary.transactions_scanned += 1;
+                    let fetched = tokio::select! {
+                        fetched = fetch_transaction(client, &signature, Some(slot), config, opts.rpc_retries) => fetched,
+                        _ = &mut shutdown => break true,
+                    };
+                    let transaction = match fetched {
+                        Ok(transaction) => transaction,
+                        Err(e) => {
+                            warn!(signature = %logs.signature, "Failed to get transaction: {}", e);
+                            summary.fetch_errors.push(logs.signature.clone());
+                            continue;
+                        }
+                    };
+                    let parsed = parse_fetched_transaction(client, &transaction, &mut summary.lookup_tables, program_id, &opts.account_filter).await;
+                    let first_new = summary.records.len();
+                    summary.add_parsed(parsed);
+                    fill_mint_decimals(client, &mut summary).await;
+                    for record in &summary.records[first_new..] {
+                        on_record(record, &summary);
+                    }
+                }
+                _ = report.tick() => on_report(&summary),
+                _ = &mut shutdown => break true,
+            }
+        };
+
+        drop(notifications);
+        unsubscribe().await;
+        pubsub.shutdown().await.ok();
+        if stopped {
+            return Ok(summary);
+        }
+        warn!("Log subscription closed; reconnecting in {:?}", reconnect_delay);
+        tokio::select! {
+            _ = tokio::time::sleep(reconnect_delay) => {}
+            _ = &mut shutdown => return Ok(summary),
+        }
+        reconnect_delay = (reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
+    }
+}
+
+/// [`watch`] for RPC nodes without PubSub: polls the program's signature history every
+/// `interval` and parses the successful transactions that landed since the last poll, oldest
+/// first. The first poll only marks where to start from. Signatures listed again by a later poll
+/// are skipped, and failed polls are retried at the next interval. Returns the accumulated
+/// summary on Ctrl-C.
+pub async fn follow(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    interval: Duration,
+    opts: WatchOptions,
+    mut on_record: impl FnMut(&KaminoTransaction, &ParseSummary),
+    mut on_report: impl FnMut(&ParseSummary),
+) -> Result<ParseSummary> {
+    let mut summary = ParseSummary {
+        account_filter: opts.account_filter.clone(),
+        mints: opts.mints.clone(),
+        lookup_tables: opts.lookup_tables.clone(),
+        ..Default::default()
+    };
+    let mut report = tokio::time::interval(opts.report_interval);
+    report.tick().await;
+    let mut poll = tokio::time::interval(interval);
+    poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let config = RpcTransactionConfig {
+        commitment: history_commitment(client).into(),
+        encoding: UiTransactionEncoding::Base64.into(),
+        max_supported_transaction_version: Some(0),
+    };
+    let mut last_seen: Option<Signature> = None;
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut seen_order: VecDeque<String> = VecDeque::new();
+    info!(%program_id, "Polling for new transactions every {:?}", interval);
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
+
+    loop {
+        tokio::select! {
+            _ = poll.tick() => {}
+            _ = report.tick() => {
+                on_report(&summary);
+                continue;
+            }
+            _ = &mut shutdown => return Ok(summary),
+        }
+        let polled = tokio::select! {
+            polled = fetch_signatures_until(client, program_id, last_seen, opts.rpc_retries) => polled,
+            _ = &mut shutdown => return Ok(summary),
+        };
+        let signatures = match polled {
+            Ok(signatures) => signatures,
+            Err(e) => {
+                warn!("Failed to poll for new signatures (retrying in {:?}): {}", interval, e);
+                continue;
+            }
+        };
+        let Some(newest) = signatures.first() else {
+            continue;
+        };
+        let starting = last_seen.is_none();
+        last_seen = Some(Signature::from_str(&newest.signature)?);
+        if starting {
+            debug!(signature = %newest.signature, "Following transactions after the newest signature");
+            continue;
+        }
+
+        for sig_info in signatures.iter().rev() {
+            if !seen.insert(sig_info.signature.clone()) {
+                continue;
+            }
+            seen_order.push_back(sig_info.signature.clone());
+            if seen_order.len() > FOLLOW_SEEN_SIGNATURES {
+                if let Some(oldest) = seen_order.pop_front() {
+                    seen.remove(&oldest);
+                }
+            }
+            if sig_info.err.is_some() {
+                debug!(signature = %sig_info.signature, "Skipping failed transaction");
+                continue;
+            }
+            let Ok(signature) = Signature::from_str(&sig_info.signature) else {
+                continue;
+            };
+            summary.transactions_scanned += 1;
+            let fetched = tokio::select! {
+                fetched = fetch_transaction(client, &signature, Some(sig_info.slot), config, opts.rpc_retries) => fetched,
+                _ = &mut shutdown => return Ok(summary),
+            };
+            let transaction = match fetched {
+                Ok(transaction) => transaction,
+                Err(e) => {
+                    warn!(signature = %sig_info.signature, "Failed to get transaction: {}", e);
+                    summary.fetch_errors.push(sig_info.signature.clone());
+                    continue;
+                }
+            };
+            let parsed = parse_fetched_transaction(client, &transaction, &mut summary.lookup_tables, program_id, &opts.account_filter).await;
+            let first_new = summary.records.len();
+            summary.add_parsed(parsed);
+            fill_mint_decimals(client, &mut summary).await;
+            for record in &summary.records[first_new..] {
+                on_record(record, &summary);
+            }
+        }
+    }
+}
+
+/// Subscribes to the `reserves` accounts and calls `on_update` with a reserve's decoded state
+/// and the slot of the change every time one of them changes. Updates that do not decode as a
+/// reserve are skipped with a warning. Reconnects with backoff when the subscriptions drop and
+/// returns on Ctrl-C.
+pub async fn watch_reserves(
+    ws_url: &str,
+    reserves: &[Pubkey],
+    commitment: CommitmentConfig,
+    mut on_update: impl FnMut(&Pubkey, u64, &Reserve),
+) -> Result<()> {
+    let mut reconnect_delay = RECONNECT_BASE_DELAY;
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(commitment),
+        ..Default::default()
+    };
+    let shutdown = tokio::signal::ctrl_c();
+    tokio::pin!(shutdown);
+
+    loop {
+        let pubsub = match PubsubClient::new(ws_url).await {
+            Ok(pubsub) => pubsub,
+            Err(e) => {
+                warn!("Failed to connect to {} (retrying in {:?}): {}", ws_url, reconnect_delay, e);
+                tokio::select! {
+                    _ = tokio::time::sleep(reconnect_delay) => {}
+                    _ = &mut shutdown => return Ok(()),
+                }
+                reconnect_delay = (reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
+                continue;
+            }
+        };
+        let mut subscriptions = Vec::with_capacity(reserves.len());
+        let mut unsubscribes = Vec::with_capacity(reserves.len());
+        for reserve in reserves {
+            match pubsub.account_subscribe(reserve, Some(config.clone())).await {
+                Ok((notifications, unsubscribe)) => {
+                    subscriptions.push(notifications.map(move |notification| (*reserve, notification)));
+                    unsubscribes.push(unsubscribe);
+                }
+                Err(e) => {
+                    warn!(%reserve, "Failed to subscribe to reserve: {}", e);
+                    break;
+                }
+            }
+        }
+
+        let stopped = if subscriptions.len() < reserves.len() {
+            drop(subscriptions);
+            false
+        } else {
+            info!(reserves = reserves.len(), "Watching {} for reserve changes", ws_url);
+            reconnect_delay = RECONNECT_BASE_DELAY;
+            let mut notifications = stream::select_all(subscriptions);
+            let stopped = loop {
+                tokio::select! {
+                    notification = notifications.next() => {
+                        let Some((reserve, notification)) = notification else {
+                            break false;
+                        };
+                        let decoded = notification.value.data.decode().context("account data is not base64").and_then(|data| Reserve::from_account_data(&data));
+                        match decoded {
+                            Ok(state) => on_update(&reserve, notification.context.slot, &state),
+                            Err(e) => warn!(%reserve, "Skipping reserve update: {:#}", e),
+                        }
+                    }
+                    _ = &mut shutdown => break true,
+                }
+            };
+            drop(notifications);
+            stopped
+        };
+
+        for unsubscribe in unsubscribes {
+            unsubscribe().await;
+        }
+        pubsub.shutdown().await.ok();
+        if stopped {
+            return Ok(());
+        }
+        warn!("Reserve subscriptions closed; reconnecting in {:?}", reconnect_delay);
+        tokio::select! {
+            _ = tokio::time::sleep(reconnect_delay) => {}
+            _ = &mut shutdown => return Ok(()),
+        }
+        reconnect_delay = (reconnect_delay * 2).min(RECONNECT_MAX_DELAY);
+    }
+}
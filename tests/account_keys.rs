@@ -0,0 +1,201 @@
+use solana_kamino_rs::instruction::{invoked_program_id, transaction_type_name, FLASH_LOAN_DATA_BORROW_SIG};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_kamino_rs::{parse_transaction, resolve_account_keys, resolve_accounts, AccountFilter, LookupCache, TransactionType};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::v0::{self, MessageAddressTableLookup};
+use solana_sdk::message::{MessageHeader, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::collections::HashMap;
+
+fn keys(n: usize) -> Vec<Pubkey> {
+    (0..n).map(|_| Pubkey::new_unique()).collect()
+}
+
+/// Two lookup tables, each contributing both writable and readonly addresses
+fn two_table_message(static_keys: &[Pubkey], table_a: Pubkey, table_b: Pubkey) -> v0::Message {
+    v0::Message {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 1,
+        },
+        account_keys: static_keys.to_vec(),
+        recent_blockhash: Hash::default(),
+        instructions: vec![],
+        address_table_lookups: vec![
+            MessageAddressTableLookup {
+                account_key: table_a,
+                writable_indexes: vec![2, 0],
+                readonly_indexes: vec![1],
+            },
+            MessageAddressTableLookup {
+                account_key: table_b,
+                writable_indexes: vec![1],
+                readonly_indexes: vec![0, 2],
+            },
+        ],
+    }
+}
+
+#[test]
+fn writable_lookups_of_all_tables_precede_readonly_ones() {
+    let static_keys = keys(3);
+    let (table_a, table_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let a = keys(4);
+    let b = keys(3);
+    let tables = HashMap::from([(table_a, a.clone()), (table_b, b.clone())]);
+
+    let message = two_table_message(&static_keys, table_a, table_b);
+    let resolved = resolve_account_keys(&message, &tables).unwrap();
+
+    let mut expected = static_keys.clone();
+    expected.extend([a[2], a[0], b[1]]);
+    expected.extend([a[1], b[0], b[2]]);
+    assert_eq!(resolved, expected);
+}
+
+#[test]
+fn instruction_accounts_from_the_second_table_resolve_to_the_right_mint() {
+    let program_id = Pubkey::new_unique();
+    let mut static_keys = keys(2);
+    static_keys.push(program_id);
+    let (table_a, table_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let a = keys(4);
+    let b = keys(3);
+    let tables = HashMap::from([(table_a, a), (table_b, b.clone())]);
+
+    // Index 5 is the third writable lookup: table_b's address 1. A per-table interleaving would
+    // put table_a's readonly address there instead.
+    let mut data = FLASH_LOAN_DATA_BORROW_SIG.to_vec();
+    data.extend(1_000u64.to_le_bytes());
+    let mut message = two_table_message(&static_keys, table_a, table_b);
+    message.instructions.push(CompiledInstruction {
+        program_id_index: 2,
+        accounts: vec![0, 1, 0, 1, 5],
+        data,
+    });
+
+    let all_accounts = resolve_account_keys(&message, &tables).unwrap();
+    let tx = VersionedTransaction {
+        signatures: vec![Signature::default()],
+        message: VersionedMessage::V0(message),
+    };
+    let records = parse_transaction(&tx, 1, None, &all_accounts, &program_id);
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].transaction_type, TransactionType::FlashBorrow { amount: 1_000, initiator: static_keys[0] });
+    assert_eq!(records[0].reserve_token, Some(b[1]));
+}
+
+#[test]
+fn program_id_resolves_to_the_static_key_when_lookup_tables_supply_other_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mut static_keys = keys(2);
+    static_keys.push(program_id);
+    let (table_a, table_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    // The program id is also in a table, at resolved index 3, which a top-level instruction
+    // cannot invoke
+    let tables = HashMap::from([(table_a, vec![Pubkey::new_unique(), Pubkey::new_unique(), program_id, Pubkey::new_unique()]), (table_b, keys(3))]);
+
+    let mut data = FLASH_LOAN_DATA_BORROW_SIG.to_vec();
+    data.extend(1_000u64.to_le_bytes());
+    let mut message = two_table_message(&static_keys, table_a, table_b);
+    for program_id_index in [2, 3] {
+        message.instructions.push(CompiledInstruction { program_id_index, accounts: vec![0, 1, 0, 1, 5], data: data.clone() });
+    }
+
+    let all_accounts = resolve_account_keys(&message, &tables).unwrap();
+    assert_eq!(all_accounts[3], program_id);
+    let message = VersionedMessage::V0(message);
+    assert_eq!(invoked_program_id(&message, &message.instructions()[0]), Some(&program_id));
+    assert_eq!(invoked_program_id(&message, &message.instructions()[1]), None);
+
+    let tx = VersionedTransaction { signatures: vec![Signature::default()], message };
+    let records = parse_transaction(&tx, 1, None, &all_accounts, &program_id);
+    let instruction_indexes: Vec<_> = records.iter().map(|record| record.instruction_index).collect();
+    assert_eq!(instruction_indexes, [0]);
+}
+
+#[test]
+fn missing_lookup_table_is_not_resolved() {
+    let static_keys = keys(3);
+    let (table_a, table_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let tables = HashMap::from([(table_a, keys(4))]);
+
+    let message = two_table_message(&static_keys, table_a, table_b);
+    assert_eq!(resolve_account_keys(&message, &tables), None);
+}
+
+#[test]
+fn account_filter_matches_resolved_lookup_addresses() {
+    let static_keys = keys(3);
+    let (table_a, table_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let a = keys(4);
+    let b = keys(3);
+    let tables = HashMap::from([(table_a, a.clone()), (table_b, b.clone())]);
+    let all_accounts = resolve_account_keys(&two_table_message(&static_keys, table_a, table_b), &tables).unwrap();
+
+    // Accounts 0 (static) and 6 (table_a's readonly address 1)
+    let instruction = CompiledInstruction {
+        program_id_index: 2,
+        accounts: vec![0, 6],
+        data: vec![],
+    };
+    let reserve_filter = |reserves: Vec<Pubkey>, markets: Vec<Pubkey>| AccountFilter { reserves, markets, ..AccountFilter::default() };
+
+    assert!(AccountFilter::default().matches(&instruction, &all_accounts));
+    assert!(reserve_filter(vec![b[0], a[1]], vec![]).matches(&instruction, &all_accounts));
+    assert!(!reserve_filter(vec![a[2]], vec![]).matches(&instruction, &all_accounts));
+    assert!(reserve_filter(vec![a[1]], vec![static_keys[0]]).matches(&instruction, &all_accounts));
+    assert!(!reserve_filter(vec![a[1]], vec![static_keys[1]]).matches(&instruction, &all_accounts));
+}
+
+#[test]
+fn include_mints_take_precedence_over_exclude_mints() {
+    let (usdc, usdt, sol) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let filter = |include_mints: Vec<Pubkey>, exclude_mints: Vec<Pubkey>| AccountFilter { include_mints, exclude_mints, ..AccountFilter::default() };
+
+    let excluding = filter(vec![], vec![sol]);
+    assert!(excluding.matches_mint(Some(&usdc)));
+    assert!(!excluding.matches_mint(Some(&sol)));
+    assert!(excluding.matches_mint(None));
+
+    // Once any mint is included, only included mints count, excluded or not
+    let including = filter(vec![usdc, usdt], vec![usdt]);
+    assert!(including.matches_mint(Some(&usdc)));
+    assert!(including.matches_mint(Some(&usdt)));
+    assert!(!including.matches_mint(Some(&sol)));
+    assert!(!including.matches_mint(None));
+}
+
+#[test]
+fn instruction_types_are_named_in_kebab_or_snake_case() {
+    assert_eq!(transaction_type_name("flash-borrow"), Some("flash_borrow"));
+    assert_eq!(transaction_type_name("deposit_and_collateralize"), Some("deposit_and_collateralize"));
+    assert_eq!(transaction_type_name("flash-loan"), None);
+
+    let filter = AccountFilter { instruction_types: vec!["flash_borrow", "flash_repay"], ..AccountFilter::default() };
+    assert!(filter.matches_type("flash_repay"));
+    assert!(!filter.matches_type("borrow"));
+    assert!(AccountFilter::default().matches_type("borrow"));
+}
+
+#[tokio::test]
+async fn resolve_accounts_uses_cached_tables_and_reports_unfetchable_ones() {
+    let static_keys = keys(3);
+    let (table_a, table_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let message = two_table_message(&static_keys, table_a, table_b);
+    // Nothing listens here, so any table missing from the cache fails to fetch
+    let client = RpcClient::new("http://127.0.0.1:1".to_string());
+
+    let mut cache = LookupCache::from([(table_a, keys(4)), (table_b, keys(3))]);
+    let expected = resolve_account_keys(&message, &cache).unwrap();
+    assert_eq!(resolve_accounts(&client, &message, &mut cache).await.unwrap(), expected);
+
+    cache.remove(&table_b);
+    assert!(resolve_accounts(&client, &message, &mut cache).await.is_err());
+    assert!(!cache.contains_key(&table_b));
+}
@@ -0,0 +1,132 @@
+use solana_kamino_rs::{AmountStats, BorrowMix, KaminoTransaction, ParseSummary, TimeBucket, TransactionType};
+use std::collections::HashMap;
+use std::time::Duration;
+use solana_sdk::pubkey::Pubkey;
+
+fn repay(amount: u64) -> TransactionType {
+    TransactionType::Repay { amount, obligation: Pubkey::new_unique(), reserve: Pubkey::new_unique(), split: None }
+}
+
+#[test]
+fn stats_of_an_even_number_of_amounts() {
+    let stats = AmountStats::from_amounts((1..=10).rev().map(|n| n * 100).collect()).unwrap();
+    assert_eq!(
+        stats,
+        AmountStats { count: 10, mean: 550.0, median: 550, min: 100, max: 1000, p90: 900 }
+    );
+}
+
+#[test]
+fn stats_of_a_single_amount() {
+    let stats = AmountStats::from_amounts(vec![42]).unwrap();
+    assert_eq!((stats.median, stats.min, stats.max, stats.p90), (42, 42, 42, 42));
+    assert_eq!(AmountStats::from_amounts(vec![]), None);
+}
+
+#[test]
+fn amount_stats_are_per_mint_and_skip_failed_records() {
+    let (usdc, sol) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut summary = ParseSummary::default();
+    for (mint, amount, failed) in [(usdc, 10, false), (usdc, 30, false), (usdc, 1_000, true), (sol, 5, false)] {
+        summary.add_record(KaminoTransaction {
+            signature: String::new(),
+            slot: 0,
+            block_time: None,
+            transaction_type: TransactionType::FlashBorrow { amount, initiator: Pubkey::default() },
+            reserve_token: Some(mint),
+            instruction_index: 0,
+            inner_index: None,
+            failed,
+            lending_market: None,
+        });
+    }
+
+    let stats = summary.amount_stats("flash_borrow");
+    assert_eq!(stats[&usdc].count, 2);
+    assert_eq!(stats[&usdc].median, 20);
+    assert_eq!(stats[&usdc].max, 30);
+    assert_eq!(stats[&sol].count, 1);
+    assert!(summary.amount_stats("borrow").is_empty());
+}
+
+#[test]
+fn instructions_per_transaction_histogram() {
+    let mint = Pubkey::new_unique();
+    let mut summary = ParseSummary::default();
+    let records = [
+        ("a", TransactionType::FlashBorrow { amount: 1, initiator: Pubkey::default() }, false),
+        ("a", TransactionType::FlashRepay { amount: 1, fee: Some(0) }, false),
+        ("a", TransactionType::Unknown { discriminator: [0; 8] }, false),
+        ("b", repay(1), false),
+        ("c", repay(1), false),
+        ("d", TransactionType::Deposit { amount: 1 }, true),
+    ];
+    for (instruction_index, (signature, transaction_type, failed)) in records.into_iter().enumerate() {
+        summary.add_record(KaminoTransaction {
+            signature: signature.to_string(),
+            slot: 0,
+            block_time: None,
+            transaction_type,
+            reserve_token: Some(mint),
+            instruction_index,
+            inner_index: None,
+            failed,
+            lending_market: None,
+        });
+    }
+
+    let histogram = summary.instructions_per_transaction();
+    assert_eq!(histogram.into_iter().collect::<Vec<_>>(), [(1, 2), (2, 1)]);
+}
+
+#[test]
+fn borrow_mix_splits_each_mint_between_flash_loans_and_obligations() {
+    let (usdc, sol, jup) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut summary = ParseSummary::default();
+    summary.flash_loan_borrow_amounts.extend([(usdc, 900), (sol, 50)]);
+    summary.loan_borrow_amounts.extend([(usdc, 100), (jup, 7)]);
+
+    let mix = summary.borrow_mix();
+    assert_eq!(mix[&usdc], BorrowMix { flash_loan: 900, obligation: 100 });
+    assert_eq!(mix[&usdc].flash_loan_share(), Some(0.9));
+    assert_eq!(mix[&sol].flash_loan_share(), Some(1.0));
+    assert_eq!(mix[&jup].flash_loan_share(), Some(0.0));
+    assert_eq!(BorrowMix::default().flash_loan_share(), None);
+}
+
+#[test]
+fn time_buckets_group_activity_by_block_time() {
+    let usdc = Pubkey::new_unique();
+    let mut summary = ParseSummary::default();
+    // 2024-01-23T09:00:00Z
+    let hour = 1_706_000_400;
+    for (signature, block_time, transaction_type, failed) in [
+        ("a", Some(hour + 10), TransactionType::FlashBorrow { amount: 100, initiator: Pubkey::default() }, false),
+        ("a", Some(hour + 10), TransactionType::FlashRepay { amount: 101, fee: Some(1) }, false),
+        ("b", Some(hour + 3_599), TransactionType::FlashBorrow { amount: 50, initiator: Pubkey::default() }, false),
+        ("c", Some(hour + 3_600), TransactionType::FlashBorrow { amount: 7, initiator: Pubkey::default() }, false),
+        ("d", Some(hour + 3_600), TransactionType::FlashBorrow { amount: 1_000, initiator: Pubkey::default() }, true),
+        ("e", None, TransactionType::FlashBorrow { amount: 1_000, initiator: Pubkey::default() }, false),
+    ] {
+        summary.add_record(KaminoTransaction {
+            signature: signature.to_string(),
+            slot: 0,
+            block_time,
+            transaction_type,
+            reserve_token: Some(usdc),
+            instruction_index: 0,
+            inner_index: None,
+            failed,
+            lending_market: None,
+        });
+    }
+
+    let hourly = summary.time_buckets(Duration::from_secs(3_600));
+    assert_eq!(hourly.keys().copied().collect::<Vec<_>>(), vec![hour, hour + 3_600]);
+    assert_eq!(hourly[&hour], TimeBucket { transactions: 2, borrows: 2, borrowed: HashMap::from([(usdc, 150)]) });
+    assert_eq!(hourly[&(hour + 3_600)], TimeBucket { transactions: 1, borrows: 1, borrowed: HashMap::from([(usdc, 7)]) });
+
+    let daily = summary.time_buckets(Duration::from_secs(86_400));
+    assert_eq!(daily.len(), 1);
+    assert_eq!(daily.values().next().unwrap().transactions, 3);
+}
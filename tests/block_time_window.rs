@@ -0,0 +1,32 @@
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_kamino_rs::{fetch_and_parse, ParseOptions};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::collections::HashMap;
+
+fn signature_info(slot: u64, block_time: Option<i64>) -> Value {
+    json!({ "signature": Signature::new_unique().to_string(), "slot": slot, "err": null, "memo": null, "blockTime": block_time, "confirmationStatus": "finalized" })
+}
+
+async fn window_of(signatures: Value) -> Option<(i64, i64)> {
+    let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), HashMap::from([(RpcRequest::GetSignaturesForAddress, signatures)]));
+    let opts = ParseOptions { max_transactions: Some(4), ..ParseOptions::default() };
+    fetch_and_parse(&client, &Pubkey::new_unique(), opts).await.unwrap().block_time_window
+}
+
+#[tokio::test]
+async fn window_spans_the_signatures_that_have_a_block_time() {
+    // The newest slot is not confirmed yet and the oldest one lost its block time
+    let signatures = json!([
+        signature_info(13, None),
+        signature_info(12, Some(1_706_000_600)),
+        signature_info(11, Some(1_706_000_000)),
+        signature_info(10, None),
+    ]);
+    assert_eq!(window_of(signatures).await, Some((1_706_000_000, 1_706_000_600)));
+
+    let signatures = json!([signature_info(13, None), signature_info(12, None)]);
+    assert_eq!(window_of(signatures).await, None);
+}
@@ -0,0 +1,76 @@
+use solana_kamino_rs::db::Database;
+use solana_kamino_rs::{KaminoTransaction, TransactionType};
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+
+fn record(signature: &str, instruction_index: usize, slot: u64, amount: u64) -> KaminoTransaction {
+    KaminoTransaction {
+        signature: signature.to_string(),
+        slot,
+        block_time: Some(1_706_000_000),
+        transaction_type: TransactionType::Deposit { amount },
+        reserve_token: Some(Pubkey::new_unique()),
+        instruction_index,
+        inner_index: None,
+        failed: false,
+        lending_market: None,
+    }
+}
+
+#[test]
+fn reinserting_stored_records_is_a_no_op() {
+    let mut db = Database::open(Path::new(":memory:")).unwrap();
+    let records = [record("a", 0, 10, 5), record("a", 1, 10, 7), record("b", 0, 12, 9)];
+
+    assert_eq!(db.latest_slot().unwrap(), None);
+    assert_eq!(db.insert_records(&records).unwrap(), 3);
+    assert_eq!(db.insert_records(&records).unwrap(), 0);
+    assert_eq!(db.insert_records(&[record("c", 0, 11, 1)]).unwrap(), 1);
+    assert_eq!(db.latest_slot().unwrap(), Some(12));
+}
+
+#[test]
+fn cpi_records_are_stored_next_to_their_top_level_instruction() {
+    let mut db = Database::open(Path::new(":memory:")).unwrap();
+    let top_level = record("a", 0, 10, 5);
+    let cpi = KaminoTransaction {
+        inner_index: Some(2),
+        ..record("a", 0, 10, 6)
+    };
+
+    assert_eq!(db.insert_records([&top_level, &cpi]).unwrap(), 2);
+}
+
+#[test]
+fn databases_without_inner_index_are_migrated() {
+    let path = std::env::temp_dir().join(format!("kamino-migrate-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    rusqlite::Connection::open(&path)
+        .unwrap()
+        .execute_batch(
+            "CREATE TABLE kamino_transactions (
+                signature TEXT NOT NULL,
+                instruction_index INTEGER NOT NULL,
+                slot INTEGER NOT NULL,
+                block_time INTEGER,
+                type TEXT NOT NULL,
+                mint TEXT,
+                amount INTEGER,
+                failed INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (signature, instruction_index)
+            );
+            INSERT INTO kamino_transactions (signature, instruction_index, slot, type) VALUES ('a', 0, 42, 'deposit');",
+        )
+        .unwrap();
+
+    let mut db = Database::open(&path).unwrap();
+    assert_eq!(db.latest_slot().unwrap(), Some(42));
+    let cpi = KaminoTransaction {
+        inner_index: Some(0),
+        ..record("a", 0, 42, 1)
+    };
+    assert_eq!(db.insert_records([&record("a", 0, 42, 1), &cpi]).unwrap(), 1);
+
+    drop(db);
+    std::fs::remove_file(&path).unwrap();
+}
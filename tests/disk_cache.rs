@@ -0,0 +1,39 @@
+use solana_kamino_rs::cache::DiskCache;
+use solana_kamino_rs::ParseSummary;
+use solana_sdk::pubkey::Pubkey;
+
+#[test]
+fn cache_round_trips_and_keeps_the_longer_lookup_table() {
+    let dir = std::env::temp_dir().join(format!("kamino-cache-test-{}", std::process::id()));
+    let path = dir.join("cluster.json");
+    let (mint, table) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let addresses: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+
+    let mut cache = DiskCache::load(path.clone());
+    assert!(cache.mint_decimals.is_empty() && cache.lookup_tables.is_empty());
+    let mut summary = ParseSummary::default();
+    summary.mints.insert(mint, 6);
+    summary.lookup_tables.insert(table, addresses.clone());
+    cache.update(&summary);
+    cache.save().unwrap();
+
+    // A later scan that only saw the table before it was extended does not shrink it
+    let mut cache = DiskCache::load(path.clone());
+    assert_eq!(cache.mint_decimals[&mint], 6);
+    assert_eq!(cache.lookup_tables[&table], addresses);
+    let mut stale = ParseSummary::default();
+    stale.lookup_tables.insert(table, addresses[..1].to_vec());
+    cache.update(&stale);
+    assert_eq!(cache.lookup_tables[&table], addresses);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn invalid_cache_file_is_ignored() {
+    let path = std::env::temp_dir().join(format!("kamino-cache-invalid-{}.json", std::process::id()));
+    std::fs::write(&path, "not json").unwrap();
+    let cache = DiskCache::load(path.clone());
+    assert!(cache.mint_decimals.is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
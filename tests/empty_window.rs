@@ -0,0 +1,82 @@
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::process::Command;
+
+/// A JSON-RPC endpoint that answers every request with an empty list, so the program has no
+/// signatures in any window
+fn quiet_rpc() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut stream = stream;
+                loop {
+                    let mut content_length = 0;
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        if line == "\r\n" {
+                            break;
+                        }
+                        if let Some((name, value)) = line.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap();
+                            }
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    let request: Value = serde_json::from_slice(&body).unwrap();
+                    let response = json!({ "jsonrpc": "2.0", "result": [], "id": request["id"] }).to_string();
+                    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", response.len(), response).unwrap();
+                }
+            });
+        }
+    });
+    url
+}
+
+/// The binary run from an empty directory against [`quiet_rpc`]
+fn kamino() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_solana-kamino-rs"));
+    command.current_dir(std::env::temp_dir()).env("RPC_URL", quiet_rpc()).arg("--no-cache");
+    command
+}
+
+#[test]
+fn quiet_window_replaces_the_output_file() {
+    let path = std::env::temp_dir().join(format!("kamino-quiet-{}.json", std::process::id()));
+    std::fs::write(&path, "previous run").unwrap();
+
+    let output = kamino().args(["--format", "json", "--output-file"]).arg(&path).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let written: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(written["counts"]["borrow"], 0);
+    assert_eq!(written["window"], Value::Null);
+}
+
+#[test]
+fn quiet_window_prints_an_empty_summary_in_machine_formats() {
+    let output = kamino().args(["--format", "json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(summary["counts"].as_object().unwrap().values().all(|count| count == 0));
+    assert_eq!(summary["window"], Value::Null);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No successful transactions found"));
+
+    // The header alone, matching the rows of a busy window
+    let output = kamino().args(["--format", "csv"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "signature,slot,block_time,instruction_type,inner_index,reserve_mint,raw_amount,ui_amount,principal_amount,interest_amount,failed\n"
+    );
+}
@@ -0,0 +1,51 @@
+use std::process::Command;
+
+/// The binary run from an empty directory, so no `.env` is picked up
+fn kamino() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_solana-kamino-rs"));
+    command.current_dir(std::env::temp_dir()).env_remove("RPC_URL").env("RUST_BACKTRACE", "0");
+    command
+}
+
+#[test]
+fn configuration_errors_exit_with_2() {
+    let output = kamino().output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("RPC_URL environment variable is not set"));
+
+    let output = kamino().args(["--network", "mainnet", "--config", "/nonexistent/kamino.toml"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    // Usage errors, reported by clap
+    assert_eq!(kamino().arg("--no-such-flag").output().unwrap().status.code(), Some(2));
+    let output = kamino().args(["--only", "flash-borrow,flash-loan"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("\"flash-loan\" is not an instruction type; expected one of flash-borrow, "));
+}
+
+#[test]
+fn help_documents_the_exit_codes() {
+    let output = kamino().arg("--help").output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Exit codes:\n  0  the scan completed"));
+}
+
+#[test]
+fn unreachable_rpc_fails_the_scan_with_1() {
+    let output = kamino().env("RPC_URL", "http://127.0.0.1:1").args(["--no-cache", "--rpc-retries", "0"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error: failed to fetch the program's signatures"));
+}
+
+#[test]
+fn check_reports_each_failed_check_and_exits_with_1() {
+    // Nothing listens on port 1, so every check fails without retrying
+    let output = kamino().env("RPC_URL", "http://127.0.0.1:1").args(["--check", "--no-cache", "--rpc-retries", "0"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for check in ["RPC health", "RPC version", "Program", "Recent transaction"] {
+        assert!(stdout.contains(&format!("FAIL  {}: ", check)), "{}", stdout);
+    }
+    assert!(String::from_utf8_lossy(&output.stderr).contains("4 of 4 checks failed"));
+}
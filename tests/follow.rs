@@ -0,0 +1,22 @@
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_kamino_rs::rpc::fetch_signatures_until;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use std::collections::HashMap;
+
+fn signature_info(signature: &Signature, slot: u64) -> Value {
+    json!({ "signature": signature.to_string(), "slot": slot, "err": null, "memo": null, "blockTime": null, "confirmationStatus": "finalized" })
+}
+
+#[tokio::test]
+async fn polls_return_the_signatures_since_the_last_seen_one() {
+    let (newer, older) = (Signature::new_unique(), Signature::new_unique());
+    let page = json!([signature_info(&newer, 12), signature_info(&older, 11)]);
+    let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), HashMap::from([(RpcRequest::GetSignaturesForAddress, page)]));
+
+    let signatures = fetch_signatures_until(&client, &Pubkey::new_unique(), Some(Signature::new_unique()), 0).await.unwrap();
+    let signatures: Vec<_> = signatures.iter().map(|sig_info| (sig_info.signature.clone(), sig_info.slot)).collect();
+    assert_eq!(signatures, [(newer.to_string(), 12), (older.to_string(), 11)]);
+}
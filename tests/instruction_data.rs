@@ -0,0 +1,469 @@
+use solana_kamino_rs::instruction::{
+    instruction_name, read_u64_le, AMOUNT_OFFSET, BORROW_OBLIGATION_DATA_SIG, BORROW_OBLIGATION_V1_DATA_SIG, DEPOSIT_AND_COLLATERALIZE_DATA_SIG, DEPOSIT_AND_COLLATERALIZE_V2_DATA_SIG,
+    DEPOSIT_OBLIGATION_COLLATERAL_DATA_SIG, DISCRIMINATORS, FLASH_LOAN_DATA_BORROW_SIG, MIN_INSTRUCTION_DATA_LEN, REDEEM_RESERVE_COLLATERAL_DATA_SIG, REFRESH_DATA_SIGS, REFRESH_OBLIGATION_DATA_SIG,
+    REFRESH_RESERVE_DATA_SIG, REPAY_OBLIGATION_DATA_SIG, WITHDRAW_OBLIGATION_COLLATERAL_DATA_SIG,
+};
+use solana_kamino_rs::market::market_label;
+use solana_kamino_rs::{parse_inner_instructions, parse_transaction, BorrowMix, ParseSummary, TransactionType};
+use solana_sdk::hash::{hash, Hash};
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::{v0, MessageHeader, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+use std::str::FromStr;
+
+/// A v0 transaction without lookup tables, signed by its first account key, whose last
+/// `readonly_unsigned` keys are read-only, such as the invoked programs
+fn v0_transaction(account_keys: &[Pubkey], readonly_unsigned: u8, instructions: Vec<CompiledInstruction>) -> VersionedTransaction {
+    VersionedTransaction {
+        signatures: vec![Signature::default()],
+        message: VersionedMessage::V0(v0::Message {
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: readonly_unsigned,
+            },
+            account_keys: account_keys.to_vec(),
+            recent_blockhash: Hash::default(),
+            instructions,
+            address_table_lookups: vec![],
+        }),
+    }
+}
+
+#[test]
+fn read_u64_le_bounds_checks() {
+    let data = [0xaa, 1, 0, 0, 0, 0, 0, 0, 0];
+    assert_eq!(read_u64_le(&data, 1), Some(1));
+    assert_eq!(read_u64_le(&data, 2), None);
+    assert_eq!(read_u64_le(&data, usize::MAX), None);
+}
+
+#[test]
+fn truncated_amount_is_skipped_instead_of_panicking() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+
+    // Borrow discriminator followed by only four of the eight amount bytes
+    let mut data = BORROW_OBLIGATION_DATA_SIG.to_vec();
+    data.extend([1, 2, 3, 4]);
+    let tx = v0_transaction(
+        &account_keys,
+        1,
+        vec![CompiledInstruction {
+            program_id_index: 6,
+            accounts: vec![0, 1, 2, 3, 4, 5],
+            data,
+        }],
+    );
+
+    assert!(parse_transaction(&tx, 1, None, &account_keys, &program_id).is_empty());
+}
+
+#[test]
+fn instructions_shorter_than_their_amount_are_skipped() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..12).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+
+    let instruction = |data: Vec<u8>| CompiledInstruction { program_id_index: 12, accounts: (0..12).collect(), data };
+    // Every decoded discriminator with an amount, bare and cut off inside it, then data too short
+    // for any discriminator
+    let mut instructions: Vec<_> = DISCRIMINATORS
+        .iter()
+        .filter(|(_, discriminator)| !REFRESH_DATA_SIGS.contains(discriminator))
+        .flat_map(|(_, discriminator)| {
+            let cut = [discriminator.to_vec(), vec![0xff; MIN_INSTRUCTION_DATA_LEN - AMOUNT_OFFSET - 1]].concat();
+            [instruction(discriminator.to_vec()), instruction(cut)]
+        })
+        .collect();
+    instructions.push(instruction(vec![]));
+    instructions.push(instruction(vec![0xa1, 0x80]));
+    let tx = v0_transaction(&account_keys, 1, instructions);
+
+    let types: Vec<_> = parse_transaction(&tx, 1, None, &account_keys, &program_id)
+        .into_iter()
+        .map(|record| record.transaction_type)
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            TransactionType::Unknown { discriminator: [0; 8] },
+            TransactionType::Unknown { discriminator: [0xa1, 0x80, 0, 0, 0, 0, 0, 0] },
+        ]
+    );
+}
+
+#[test]
+fn flash_borrow_amount_follows_the_discriminator() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+    let flash_borrow = |data: Vec<u8>| v0_transaction(&account_keys, 1, vec![CompiledInstruction { program_id_index: 5, accounts: vec![0, 1, 2, 3, 4], data }]);
+
+    // Exactly the discriminator and the u64 amount
+    let mut data = FLASH_LOAN_DATA_BORROW_SIG.to_vec();
+    data.extend(1_234_567u64.to_le_bytes());
+    assert_eq!(data.len(), AMOUNT_OFFSET + 8);
+    let records = parse_transaction(&flash_borrow(data.clone()), 1, None, &account_keys, &program_id);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].transaction_type, TransactionType::FlashBorrow { amount: 1_234_567, initiator: account_keys[0] });
+    assert_eq!(records[0].reserve_token, Some(account_keys[4]));
+
+    data.pop();
+    assert!(parse_transaction(&flash_borrow(data), 1, None, &account_keys, &program_id).is_empty());
+}
+
+#[test]
+fn discriminators_are_anchor_sighashes() {
+    for (name, discriminator) in DISCRIMINATORS {
+        let sighash = hash(format!("global:{}", name).as_bytes());
+        assert_eq!(&sighash.as_ref()[..8], discriminator, "{}", name);
+        assert_eq!(instruction_name(discriminator), Some(*name));
+    }
+}
+
+#[test]
+fn shared_four_byte_prefix_is_not_a_match() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+
+    let mut data = BORROW_OBLIGATION_DATA_SIG[..4].to_vec();
+    data.extend([0xff; 4]);
+    data.extend(1_000u64.to_le_bytes());
+    let tx = v0_transaction(
+        &account_keys,
+        1,
+        vec![CompiledInstruction {
+            program_id_index: 6,
+            accounts: vec![0, 1, 2, 3, 4, 5],
+            data,
+        }],
+    );
+
+    let records = parse_transaction(&tx, 1, None, &account_keys, &program_id);
+    assert_eq!(records.len(), 1);
+    assert!(matches!(records[0].transaction_type, TransactionType::Unknown { .. }));
+}
+
+#[test]
+fn borrow_invoked_through_cpi_is_marked_as_such() {
+    let program_id = Pubkey::new_unique();
+    let aggregator = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    account_keys.extend([program_id, aggregator]);
+
+    let mut data = BORROW_OBLIGATION_DATA_SIG.to_vec();
+    data.extend(2_500u64.to_le_bytes());
+    let borrow = CompiledInstruction {
+        program_id_index: 6,
+        accounts: vec![0, 1, 2, 3, 4, 5],
+        data,
+    };
+    // The aggregator's instruction is top-level; the borrow only shows up in the meta
+    let tx = v0_transaction(
+        &account_keys,
+        2,
+        vec![CompiledInstruction {
+            program_id_index: 7,
+            accounts: vec![0, 1, 2, 3, 4, 5, 6],
+            data: vec![1, 2, 3],
+        }],
+    );
+    let token_transfer = CompiledInstruction {
+        program_id_index: 4,
+        accounts: vec![5, 3],
+        data: vec![3],
+    };
+    let inner = [(0, vec![token_transfer, borrow])];
+
+    assert!(parse_transaction(&tx, 1, None, &account_keys, &program_id).is_empty());
+    let records = parse_inner_instructions(&tx, 1, None, &inner, &account_keys, &program_id);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].transaction_type.amount(), Some(2_500));
+    assert_eq!(records[0].reserve_token, Some(account_keys[5]));
+    assert_eq!((records[0].instruction_index, records[0].inner_index), (0, Some(1)));
+    assert!(records[0].is_cpi());
+}
+
+#[test]
+fn discriminator_on_another_program_is_not_a_match() {
+    let program_id = Pubkey::new_unique();
+    let aggregator = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    account_keys.extend([program_id, aggregator]);
+
+    // An aggregator instruction whose data happens to start with Kamino's flash borrow bytes
+    let mut data = FLASH_LOAN_DATA_BORROW_SIG.to_vec();
+    data.extend(1_000u64.to_le_bytes());
+    let wrapped = CompiledInstruction {
+        program_id_index: 7,
+        accounts: vec![0, 1, 2, 3, 4, 5, 6],
+        data,
+    };
+    let tx = v0_transaction(&account_keys, 2, vec![wrapped.clone()]);
+    let inner = [(0, vec![wrapped])];
+
+    assert!(parse_transaction(&tx, 1, None, &account_keys, &program_id).is_empty());
+    assert!(parse_inner_instructions(&tx, 1, None, &inner, &account_keys, &program_id).is_empty());
+}
+
+#[test]
+fn only_instructions_invoking_the_program_are_matched() {
+    let program_id = Pubkey::new_unique();
+    let other_program = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    account_keys.extend([program_id, other_program]);
+
+    let mut data = BORROW_OBLIGATION_DATA_SIG.to_vec();
+    data.extend(7_000u64.to_le_bytes());
+    let instruction = |program_id_index| CompiledInstruction {
+        program_id_index,
+        accounts: vec![0, 1, 2, 3, 4, 5],
+        data: data.clone(),
+    };
+    let tx = v0_transaction(&account_keys, 2, vec![instruction(7), instruction(6), instruction(7)]);
+
+    let records = parse_transaction(&tx, 1, None, &account_keys, &program_id);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].instruction_index, 1);
+    assert_eq!(records[0].transaction_type.amount(), Some(7_000));
+}
+
+#[test]
+fn borrow_referrer_is_optional() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..10).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+
+    let mut data = BORROW_OBLIGATION_DATA_SIG.to_vec();
+    data.extend(1_000u64.to_le_bytes());
+    let borrow = |accounts: Vec<u8>| CompiledInstruction {
+        program_id_index: 10,
+        accounts,
+        data: data.clone(),
+    };
+    // With a referrer, with the program id standing in for none, and without the account at all
+    let instructions = vec![
+        borrow((0..10).collect()),
+        borrow([0, 1, 2, 3, 4, 5, 6, 7, 8, 10].to_vec()),
+        borrow((0..6).collect()),
+    ];
+    let tx = v0_transaction(&account_keys, 1, instructions);
+
+    let mut summary = ParseSummary::default();
+    for record in parse_transaction(&tx, 1, None, &account_keys, &program_id) {
+        summary.add_record(record);
+    }
+    let referrers: Vec<_> = summary
+        .records
+        .iter()
+        .map(|record| match record.transaction_type {
+            TransactionType::Borrow { referrer, .. } => referrer,
+            _ => panic!("not a borrow"),
+        })
+        .collect();
+    assert_eq!(referrers, vec![Some(account_keys[9]), None, None]);
+
+    let mint = account_keys[5];
+    assert_eq!(summary.borrows_by_referrer[&Some(account_keys[9])][&mint], 1_000);
+    assert_eq!(summary.borrows_by_referrer[&None][&mint], 2_000);
+}
+
+#[test]
+fn legacy_and_current_borrow_variants_are_both_decoded() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..10).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+
+    let borrow = |discriminator: [u8; 8], amount: u64, accounts: u8| {
+        let mut data = discriminator.to_vec();
+        data.extend(amount.to_le_bytes());
+        CompiledInstruction { program_id_index: 10, accounts: (0..accounts).collect(), data }
+    };
+    // v1 ends at the referrer account; v2 appends the farm accounts after it
+    let instructions = vec![borrow(BORROW_OBLIGATION_V1_DATA_SIG, 1_000, 10), borrow(BORROW_OBLIGATION_DATA_SIG, 2_500, 10)];
+    let tx = v0_transaction(&account_keys, 1, instructions);
+
+    let borrows: Vec<_> = parse_transaction(&tx, 1, None, &account_keys, &program_id)
+        .into_iter()
+        .map(|record| match record.transaction_type {
+            TransactionType::Borrow { amount, obligation, .. } => (amount, obligation, record.reserve_token),
+            _ => panic!("not a borrow"),
+        })
+        .collect();
+    let expected = |amount| (amount, account_keys[1], Some(account_keys[5]));
+    assert_eq!(borrows, vec![expected(1_000), expected(2_500)]);
+    assert_eq!(instruction_name(&BORROW_OBLIGATION_V1_DATA_SIG), Some("borrow_obligation_liquidity"));
+}
+
+#[test]
+fn lending_market_is_read_per_instruction_layout() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..10).map(|_| Pubkey::new_unique()).collect();
+    account_keys[2] = Pubkey::from_str("7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF").unwrap();
+    account_keys.push(program_id);
+
+    let instruction = |discriminator: [u8; 8], amount: u64| {
+        let mut data = discriminator.to_vec();
+        data.extend(amount.to_le_bytes());
+        CompiledInstruction { program_id_index: 10, accounts: (0..10).collect(), data }
+    };
+    let instructions = vec![
+        instruction(BORROW_OBLIGATION_DATA_SIG, 1_000),
+        instruction(REDEEM_RESERVE_COLLATERAL_DATA_SIG, 500),
+        instruction([0xee; 8], 0),
+    ];
+    let tx = v0_transaction(&account_keys, 1, instructions);
+
+    let mut summary = ParseSummary::default();
+    for record in parse_transaction(&tx, 1, None, &account_keys, &program_id) {
+        summary.add_record(record);
+    }
+    // Borrows carry the market at 2, redeems right after the owner at 1
+    let markets: Vec<_> = summary.records.iter().map(|record| record.lending_market).collect();
+    assert_eq!(markets, vec![Some(account_keys[2]), Some(account_keys[1]), None]);
+
+    let main = &summary.borrows_by_market[&account_keys[2]];
+    assert_eq!(main[&account_keys[5]], BorrowMix { flash_loan: 0, obligation: 1_000 });
+    assert_eq!(summary.borrows_by_market.len(), 1);
+    assert_eq!(market_label(&account_keys[2]), "Main");
+    assert_eq!(market_label(&account_keys[1]), account_keys[1].to_string());
+}
+
+#[test]
+fn refreshes_are_recorded_in_sequence_without_volume() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..10).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+
+    let instruction = |data: Vec<u8>, accounts: Vec<u8>| CompiledInstruction { program_id_index: 10, accounts, data };
+    let mut borrow = BORROW_OBLIGATION_DATA_SIG.to_vec();
+    borrow.extend(1_000u64.to_le_bytes());
+    // The usual sequence: refresh the reserve, then the obligation, then borrow; refresh_reserve
+    // takes the reserve and market, refresh_obligation the market and obligation
+    let instructions = vec![
+        instruction(REFRESH_RESERVE_DATA_SIG.to_vec(), vec![3, 2, 7]),
+        instruction(REFRESH_OBLIGATION_DATA_SIG.to_vec(), vec![2, 1, 3]),
+        instruction(borrow, (0..10).collect()),
+    ];
+    let tx = v0_transaction(&account_keys, 1, instructions);
+
+    let mut summary = ParseSummary::default();
+    for record in parse_transaction(&tx, 1, None, &account_keys, &program_id) {
+        summary.add_record(record);
+    }
+    let sequence: Vec<_> = summary.records.iter().map(|record| (record.instruction_index, record.transaction_type.name(), record.lending_market)).collect();
+    let market = Some(account_keys[2]);
+    assert_eq!(sequence, vec![(0, "refresh_reserve", market), (1, "refresh_obligation", market), (2, "borrow", market)]);
+    assert_eq!(summary.records[0].transaction_type, TransactionType::RefreshReserve { reserve: account_keys[3] });
+    assert_eq!(summary.records[1].transaction_type, TransactionType::RefreshObligation { obligation: account_keys[1] });
+    assert_eq!(summary.records[0].reserve_token, None);
+
+    // Only the borrow moves funds or counts as an instruction of the transaction
+    assert_eq!(summary.loan_borrow_amounts[&account_keys[5]], 1_000);
+    assert_eq!(summary.loan_borrow_amounts.len(), 1);
+    assert_eq!(summary.by_obligation.len(), 1);
+    assert_eq!(summary.instructions_per_transaction()[&1], 1);
+}
+
+#[test]
+fn activity_is_aggregated_per_obligation() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..8).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+
+    let instruction = |discriminator: [u8; 8], amount: u64, accounts: Vec<u8>| {
+        let mut data = discriminator.to_vec();
+        data.extend(amount.to_le_bytes());
+        CompiledInstruction {
+            program_id_index: 8,
+            accounts,
+            data,
+        }
+    };
+    // Two obligations of the same owner (0): 1 and 7
+    let instructions = vec![
+        instruction(BORROW_OBLIGATION_DATA_SIG, 1_000, vec![0, 1, 2, 3, 4, 5]),
+        instruction(BORROW_OBLIGATION_DATA_SIG, 500, vec![0, 7, 2, 3, 4, 5]),
+        instruction(REPAY_OBLIGATION_DATA_SIG, 300, vec![0, 1, 2, 3, 5]),
+        instruction(WITHDRAW_OBLIGATION_COLLATERAL_DATA_SIG, 40, vec![0, 7, 2, 3, 6]),
+    ];
+    let tx = v0_transaction(&account_keys, 1, instructions);
+
+    let mut summary = ParseSummary::default();
+    for record in parse_transaction(&tx, 1, None, &account_keys, &program_id) {
+        summary.add_record(record);
+    }
+    let mint = account_keys[5];
+    let (first, second) = (&summary.by_obligation[&account_keys[1]], &summary.by_obligation[&account_keys[7]]);
+    assert_eq!((first.borrowed[&mint], first.repaid[&mint], first.collateral_withdrawals), (1_000, 300, 0));
+    assert_eq!((second.borrowed[&mint], second.repaid.get(&mint), second.collateral_withdrawals), (500, None, 1));
+    assert_eq!(summary.borrows_by_owner[&account_keys[0]][&mint], 1_500);
+}
+
+#[test]
+fn collateral_deposits_are_aggregated_per_token() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..17).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+
+    let instruction = |discriminator: [u8; 8], amount: u64, accounts: u8| {
+        let mut data = discriminator.to_vec();
+        data.extend(amount.to_le_bytes());
+        CompiledInstruction {
+            program_id_index: 17,
+            accounts: (0..accounts).collect(),
+            data,
+        }
+    };
+    let instructions = vec![
+        instruction(DEPOSIT_OBLIGATION_COLLATERAL_DATA_SIG, 700, 8),
+        instruction(DEPOSIT_AND_COLLATERALIZE_V2_DATA_SIG, 1_000, 17),
+        instruction(DEPOSIT_AND_COLLATERALIZE_DATA_SIG, 500, 14),
+    ];
+    let tx = v0_transaction(&account_keys, 1, instructions);
+
+    let mut summary = ParseSummary::default();
+    for record in parse_transaction(&tx, 1, None, &account_keys, &program_id) {
+        summary.add_record(record);
+    }
+    assert_eq!(
+        summary.records[0].transaction_type,
+        TransactionType::DepositCollateral { amount: 700, obligation: account_keys[1], reserve: account_keys[3] }
+    );
+    assert_eq!(summary.records[0].reserve_token, Some(account_keys[3]));
+    assert_eq!(
+        summary.records[1].transaction_type,
+        TransactionType::DepositAndCollateralize { amount: 1_000, obligation: account_keys[1], reserve: account_keys[4] }
+    );
+    assert_eq!(summary.deposit_collateral_by_reserve[&account_keys[3]], 700);
+    assert_eq!(summary.deposit_and_collateralize_amounts[&account_keys[5]], 1_500);
+    assert_eq!(summary.attempted_count("deposit_and_collateralize"), 2);
+}
+
+#[test]
+fn out_of_bounds_accounts_skip_the_instruction() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+
+    let mut data = BORROW_OBLIGATION_DATA_SIG.to_vec();
+    data.extend(1_000u64.to_le_bytes());
+    let borrow = |accounts: Vec<u8>| CompiledInstruction {
+        program_id_index: 6,
+        accounts,
+        data: data.clone(),
+    };
+    // Too few accounts for the mint, an index past the account list, then a well-formed borrow
+    let instructions = vec![borrow(vec![0, 1, 2]), borrow(vec![0, 1, 2, 3, 4, 42]), borrow(vec![0, 1, 2, 3, 4, 5])];
+    let tx = v0_transaction(&account_keys, 1, instructions);
+
+    let records = parse_transaction(&tx, 1, None, &account_keys, &program_id);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].instruction_index, 2);
+    assert_eq!(records[0].reserve_token, Some(account_keys[5]));
+}
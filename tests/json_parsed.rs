@@ -0,0 +1,71 @@
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_kamino_rs::instruction::FLASH_LOAN_DATA_BORROW_SIG;
+use solana_kamino_rs::{parse_fetched_transaction, AccountFilter, LookupCache, TransactionType};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::TransactionVersion;
+use solana_transaction_status::parse_accounts::ParsedAccount;
+use solana_transaction_status::parse_instruction::ParsedInstruction;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, EncodedTransactionWithStatusMeta,
+    UiInstruction, UiMessage, UiParsedInstruction, UiParsedMessage, UiPartiallyDecodedInstruction, UiTransaction,
+};
+
+#[tokio::test]
+async fn json_parsed_transaction_needs_no_lookup_tables() {
+    let program_id = Pubkey::new_unique();
+    let token_program = Pubkey::new_unique();
+    // The last two accounts came from a lookup table, which jsonParsed lists like the others
+    let account_keys: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let mut listed = account_keys.clone();
+    listed.extend([program_id, token_program]);
+    listed.swap(4, 7);
+
+    let mut data = FLASH_LOAN_DATA_BORROW_SIG.to_vec();
+    data.extend(7_000u64.to_le_bytes());
+    let instructions = vec![
+        UiInstruction::Parsed(UiParsedInstruction::Parsed(ParsedInstruction {
+            program: "spl-token".to_string(),
+            program_id: token_program.to_string(),
+            parsed: serde_json::json!({"type": "transfer"}),
+            stack_height: None,
+        })),
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(UiPartiallyDecodedInstruction {
+            program_id: program_id.to_string(),
+            accounts: account_keys[..5].iter().map(Pubkey::to_string).collect(),
+            data: bs58::encode(data).into_string(),
+            stack_height: None,
+        })),
+    ];
+    let transaction = EncodedConfirmedTransactionWithStatusMeta {
+        slot: 1,
+        block_time: None,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: EncodedTransaction::Json(UiTransaction {
+                signatures: vec![Signature::default().to_string()],
+                message: UiMessage::Parsed(UiParsedMessage {
+                    account_keys: listed
+                        .iter()
+                        .map(|key| ParsedAccount { pubkey: key.to_string(), writable: false, signer: false, source: None })
+                        .collect(),
+                    recent_blockhash: String::new(),
+                    instructions,
+                    address_table_lookups: None,
+                }),
+            }),
+            meta: None,
+            version: Some(TransactionVersion::Number(0)),
+        },
+    };
+
+    // Nothing listens here, so fetching a lookup table would fail
+    let client = RpcClient::new("http://127.0.0.1:1".to_string());
+    let records = parse_fetched_transaction(&client, &transaction, &mut LookupCache::new(), &program_id, &AccountFilter::default())
+        .await
+        .unwrap();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].transaction_type, TransactionType::FlashBorrow { amount: 7_000, initiator: account_keys[0] });
+    assert_eq!(records[0].reserve_token, Some(account_keys[4]));
+    assert_eq!(records[0].instruction_index, 1);
+}
@@ -0,0 +1,61 @@
+use base64::Engine;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_kamino_rs::instruction::FLASH_LOAN_DATA_BORROW_SIG;
+use solana_kamino_rs::{parse_fetched_transaction, AccountFilter, TransactionType};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::{legacy, MessageHeader, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{TransactionVersion, VersionedTransaction};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, EncodedTransactionWithStatusMeta,
+    TransactionBinaryEncoding,
+};
+use std::collections::HashMap;
+
+#[tokio::test]
+async fn legacy_transaction_is_parsed_against_its_static_keys() {
+    let program_id = Pubkey::new_unique();
+    let mut account_keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+
+    let mut data = FLASH_LOAN_DATA_BORROW_SIG.to_vec();
+    data.extend(5_000u64.to_le_bytes());
+    let tx = VersionedTransaction {
+        signatures: vec![Signature::default()],
+        message: VersionedMessage::Legacy(legacy::Message {
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys: account_keys.clone(),
+            recent_blockhash: Hash::default(),
+            instructions: vec![CompiledInstruction {
+                program_id_index: 5,
+                accounts: vec![0, 1, 2, 3, 4],
+                data,
+            }],
+        }),
+    };
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bincode::serialize(&tx).unwrap());
+    let transaction = EncodedConfirmedTransactionWithStatusMeta {
+        slot: 1,
+        block_time: None,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: EncodedTransaction::Binary(encoded, TransactionBinaryEncoding::Base64),
+            meta: None,
+            version: Some(TransactionVersion::LEGACY),
+        },
+    };
+
+    // Legacy transactions have no lookup tables, so the client is never called
+    let client = RpcClient::new("http://127.0.0.1:1".to_string());
+    let records =
+        parse_fetched_transaction(&client, &transaction, &mut HashMap::new(), &program_id, &AccountFilter::default()).await.unwrap();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].transaction_type, TransactionType::FlashBorrow { amount: 5_000, initiator: account_keys[0] });
+    assert_eq!(records[0].reserve_token, Some(account_keys[4]));
+}
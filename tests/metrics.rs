@@ -0,0 +1,45 @@
+use solana_kamino_rs::metrics::Metrics;
+use solana_kamino_rs::{KaminoTransaction, ParseSummary, TransactionType};
+use solana_sdk::pubkey::Pubkey;
+
+#[test]
+fn metrics_count_instructions_and_volume_in_ui_units() {
+    let mint = Pubkey::new_unique();
+    let mut summary = ParseSummary::default();
+    summary.mints.insert(mint, 6);
+    summary.mints.insert_symbol(mint, "USDX".to_string());
+    summary.transactions_scanned = 3;
+
+    let metrics = Metrics::new().unwrap();
+    for (transaction_type, failed) in [
+        (TransactionType::FlashBorrow { amount: 2_500_000, initiator: Pubkey::default() }, false),
+        (TransactionType::FlashBorrow { amount: 1_500_000, initiator: Pubkey::default() }, false),
+        (TransactionType::FlashBorrow { amount: 9_000_000, initiator: Pubkey::default() }, true),
+        (TransactionType::Deposit { amount: 1_000_000 }, false),
+    ] {
+        let record = KaminoTransaction {
+            signature: String::new(),
+            slot: 0,
+            block_time: None,
+            transaction_type,
+            reserve_token: Some(mint),
+            instruction_index: 0,
+            inner_index: None,
+            failed,
+            lending_market: None,
+        };
+        metrics.observe(&record, &summary);
+    }
+
+    let rendered = metrics.render().unwrap();
+    assert!(rendered.contains("kamino_transactions_scanned 3"), "{}", rendered);
+    assert!(rendered.contains(r#"kamino_instructions_total{status="success",type="flash_borrow"} 2"#), "{}", rendered);
+    assert!(rendered.contains(r#"kamino_instructions_total{status="failed",type="flash_borrow"} 1"#), "{}", rendered);
+    assert!(rendered.contains(r#"kamino_instructions_total{status="success",type="deposit"} 1"#), "{}", rendered);
+    assert!(
+        rendered.contains(&format!(r#"kamino_flash_loan_volume_total{{mint="{}",symbol="USDX"}} 4"#, mint)),
+        "{}",
+        rendered
+    );
+    assert!(!rendered.contains("kamino_borrow_volume_total{"), "{}", rendered);
+}
@@ -0,0 +1,98 @@
+use solana_kamino_rs::mint::{NATIVE_MINT, NATIVE_MINT_DECIMALS};
+use solana_kamino_rs::{KaminoTransaction, MintRegistry, ParseSummary, TransactionType};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+#[test]
+fn formats_amounts_with_the_mint_decimals() {
+    let usdc = Pubkey::new_unique();
+    let mut mints = MintRegistry::new();
+    mints.insert(usdc, 6);
+
+    assert_eq!(mints.format_ui_amount(1_500_000, &usdc), "1.5");
+    assert_eq!(mints.format_ui_amount(1_000_000, &usdc), "1");
+    assert_eq!(mints.format_ui_amount(1, &usdc), "0.000001");
+    assert_eq!(mints.format_ui_amount(-2_250_000, &usdc), "-2.25");
+}
+
+#[test]
+fn precision_rounds_to_fixed_decimal_places_without_floats() {
+    let usdc = Pubkey::new_unique();
+    let mut mints = MintRegistry::new();
+    mints.insert(usdc, 6);
+    mints.set_precision(Some(2));
+
+    // Beyond 2^53 raw units, where an f64 can no longer hold every amount
+    assert_eq!(mints.format_ui_amount(12_345_678_901_234_567, &usdc), "12345678901.23");
+    assert_eq!(mints.format_ui_amount(1_500_000, &usdc), "1.50");
+    assert_eq!(mints.format_ui_amount(2_675_000, &usdc), "2.68");
+    assert_eq!(mints.format_ui_amount(-2_675_000, &usdc), "-2.68");
+    assert_eq!(mints.format_ui_amount(-1_000, &usdc), "0.00");
+    assert_eq!(mints.format_ui_amount(42, &Pubkey::new_unique()), "42.00");
+    assert_eq!(mints.format_ui_value(1.0 / 3.0, &usdc), "0.33");
+
+    mints.set_precision(Some(0));
+    assert_eq!(mints.format_ui_amount(2_500_000, &usdc), "3");
+    mints.set_precision(None);
+    assert_eq!(mints.format_ui_value(0.1 + 0.2, &usdc), "0.3");
+}
+
+#[test]
+fn unknown_mints_stay_raw_and_native_mint_needs_no_lookup() {
+    let mints = MintRegistry::new();
+
+    assert_eq!(mints.format_ui_amount(1_234, &Pubkey::new_unique()), "1234");
+    assert_eq!(mints.get(&NATIVE_MINT), Some(NATIVE_MINT_DECIMALS));
+    assert_eq!(mints.format_ui_amount(2_500_000_000, &NATIVE_MINT), "2.5");
+}
+
+#[test]
+fn liquid_staking_sol_is_not_counted_as_sol() {
+    let msol = Pubkey::from_str("mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So").unwrap();
+    let jitosol = Pubkey::from_str("J1toso1uCk3RLmjorhTtrVwY9HJ7X8V9yYac6Y7kGCPn").unwrap();
+    let mut summary = ParseSummary::default();
+    for (mint, amount) in [(NATIVE_MINT, 1_000), (msol, 20_000), (jitosol, 300_000)] {
+        summary.add_record(KaminoTransaction {
+            signature: String::new(),
+            slot: 0,
+            block_time: None,
+            transaction_type: TransactionType::Borrow {
+                amount,
+                obligation: Pubkey::new_unique(),
+                owner: Pubkey::new_unique(),
+                referrer: None,
+            },
+            reserve_token: Some(mint),
+            instruction_index: 0,
+            inner_index: None,
+            failed: false,
+            lending_market: None,
+        });
+    }
+
+    assert_eq!(summary.loan_borrow_amounts[&NATIVE_MINT], 1_000);
+    assert_eq!(summary.loan_borrow_amounts[&msol], 20_000);
+    assert_eq!(summary.loan_borrow_amounts[&jitosol], 300_000);
+    // Their decimals have to be fetched like any other mint's
+    assert_eq!(summary.mints.get(&msol), None);
+    assert_eq!(summary.mints.get(&jitosol), None);
+}
+
+#[test]
+fn symbols_come_from_the_token_list_then_the_built_in_table() {
+    let usdc = Pubkey::from_str("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
+    let unknown = Pubkey::from_str("7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF").unwrap();
+    let custom = Pubkey::new_unique();
+    let token_list = std::env::temp_dir().join(format!("kamino-token-list-{}.json", std::process::id()));
+    std::fs::write(&token_list, format!(r#"{{"{}": "CUSTOM", "{}": "USDC.e"}}"#, custom, usdc)).unwrap();
+
+    let mut mints = MintRegistry::new();
+    assert_eq!(mints.symbol(&NATIVE_MINT), "SOL");
+    assert_eq!(mints.symbol(&usdc), "USDC");
+    assert_eq!(mints.symbol(&unknown), "7u3H…5PfF");
+
+    mints.load_token_list(&token_list).unwrap();
+    std::fs::remove_file(&token_list).unwrap();
+    assert_eq!(mints.symbol(&custom), "CUSTOM");
+    assert_eq!(mints.symbol(&usdc), "USDC.e");
+}
@@ -0,0 +1,51 @@
+use base64::Engine;
+use solana_kamino_rs::obligation::{Obligation, ObligationDeposit};
+use solana_kamino_rs::reserve::RESERVE_DISCRIMINATOR;
+use solana_kamino_rs::state::decode_obligation_borrows;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+const SOL_RESERVE: &str = "d4A2prbA2whesmvHaL88BH6Ewn5N4bTSU2Ze8P6Bc4Q";
+const USDC_RESERVE: &str = "D6q6wuQSrifJKZYpR1M8R4YawnLDtDsMmWM1NbBmgJ59";
+
+/// An obligation in the main market borrowing USDC against SOL collateral, see
+/// `tests/fixtures/README.md`
+fn sol_usdc_obligation() -> Vec<u8> {
+    let encoded = include_str!("fixtures/sol_usdc_obligation.b64");
+    base64::engine::general_purpose::STANDARD.decode(encoded.trim()).unwrap()
+}
+
+#[test]
+fn decodes_an_obligation_account() {
+    let obligation = Obligation::from_account_data(&sol_usdc_obligation()).unwrap();
+
+    assert_eq!(obligation.last_update_slot, 300_000_123);
+    assert_eq!(obligation.lending_market.to_string(), "7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF");
+    assert_eq!(obligation.owner.to_string(), "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM");
+    // The first deposit slot was emptied by a full withdrawal
+    assert_eq!(
+        obligation.deposits,
+        vec![ObligationDeposit { reserve: Pubkey::from_str(SOL_RESERVE).unwrap(), deposited_amount: 12_500_000_000, market_value: 2_000.0 }]
+    );
+    assert_eq!(obligation.borrows.len(), 1);
+    assert_eq!(obligation.borrows[0].reserve.to_string(), USDC_RESERVE);
+    assert_eq!(obligation.borrows[0].cumulative_borrow_rate, 1.125);
+    assert_eq!(obligation.borrows[0].borrowed_amount, 800_000_000.0);
+    assert_eq!(obligation.deposited_value, 2_000.0);
+    assert_eq!(obligation.allowed_borrow_value, 1_500.0);
+    assert_eq!(obligation.health_factor(), Some(2.0));
+    assert_eq!(obligation.referrer, None);
+
+    // Repay splitting reads the same borrows
+    assert_eq!(decode_obligation_borrows(&sol_usdc_obligation()).unwrap(), obligation.borrows);
+}
+
+#[test]
+fn other_accounts_are_not_obligations() {
+    let mut data = sol_usdc_obligation();
+    assert_eq!(data.len(), 3344);
+    assert!(Obligation::from_account_data(&data[..1000]).is_err());
+
+    data[..8].copy_from_slice(&RESERVE_DISCRIMINATOR);
+    assert!(Obligation::from_account_data(&data).is_err());
+}
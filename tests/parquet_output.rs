@@ -0,0 +1,68 @@
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Int32Type, TimestampSecondType, UInt64Type};
+use arrow_array::StringArray;
+use arrow_schema::{DataType, TimeUnit};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use solana_kamino_rs::columnar::write_parquet;
+use solana_kamino_rs::state::RepaySplit;
+use solana_kamino_rs::{KaminoTransaction, ParseSummary, TransactionType};
+use solana_sdk::pubkey::Pubkey;
+use std::fs::File;
+
+#[test]
+fn records_round_trip_with_typed_columns() {
+    let mint = Pubkey::new_unique();
+    let mut summary = ParseSummary::default();
+    summary.mints.insert(mint, 6);
+    let record = |signature: &str, transaction_type, reserve_token| KaminoTransaction {
+        signature: signature.to_string(),
+        slot: 250_000_000,
+        block_time: Some(1_706_000_000),
+        transaction_type,
+        reserve_token,
+        instruction_index: 2,
+        inner_index: None,
+        failed: false,
+        lending_market: None,
+    };
+    summary.add_record(record(
+        "a",
+        TransactionType::Repay {
+            amount: 2_500_000,
+            obligation: Pubkey::new_unique(),
+            reserve: Pubkey::new_unique(),
+            split: Some(RepaySplit { principal: 2_000_000, interest: 500_000 }),
+        },
+        Some(mint),
+    ));
+    summary.add_record(record("b", TransactionType::Unknown { discriminator: [0; 8] }, None));
+
+    let path = std::env::temp_dir().join(format!("kamino-records-{}.parquet", std::process::id()));
+    write_parquet(File::create(&path).unwrap(), &summary).unwrap();
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap().build().unwrap();
+    let batches: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let batch = &batches[0];
+    assert_eq!(batch.num_rows(), 2);
+    let schema = batch.schema();
+    assert_eq!(schema.field_with_name("slot").unwrap().data_type(), &DataType::UInt64);
+    assert_eq!(
+        schema.field_with_name("block_time").unwrap().data_type(),
+        &DataType::Timestamp(TimeUnit::Second, Some("UTC".into()))
+    );
+    assert!(matches!(schema.field_with_name("instruction_type").unwrap().data_type(), DataType::Dictionary(..)));
+
+    let column = |name: &str| batch.column(schema.index_of(name).unwrap()).clone();
+    assert_eq!(column("slot").as_primitive::<UInt64Type>().value(0), 250_000_000);
+    assert_eq!(column("block_time").as_primitive::<TimestampSecondType>().value(0), 1_706_000_000);
+    let types = column("instruction_type");
+    let types = types.as_dictionary::<Int32Type>();
+    let names: Vec<_> = types.downcast_dict::<StringArray>().unwrap().into_iter().collect();
+    assert_eq!(names, [Some("repay"), Some("unknown")]);
+    let amounts = column("raw_amount");
+    assert_eq!(amounts.as_primitive::<UInt64Type>().value(0), 2_500_000);
+    assert!(amounts.is_null(1));
+    assert_eq!(column("interest_amount").as_primitive::<UInt64Type>().value(0), 500_000);
+    assert!(column("reserve_mint").is_null(1));
+}
@@ -0,0 +1,89 @@
+use base64::Engine;
+use futures::StreamExt;
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_kamino_rs::instruction::BORROW_OBLIGATION_DATA_SIG;
+use solana_kamino_rs::{parse_stream, ParseOptions, TransactionType, KAMINO_LEND_PROGRAM_ID};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::{v0, MessageHeader, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{TransactionVersion, VersionedTransaction};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, EncodedTransactionWithStatusMeta, TransactionBinaryEncoding,
+    TransactionStatusMeta,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// A successful transaction with one obligation borrow of `amount`, as getTransaction returns it
+fn borrow_transaction(program_id: Pubkey, amount: u64) -> EncodedConfirmedTransactionWithStatusMeta {
+    let mut account_keys: Vec<Pubkey> = (0..10).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+    let mut data = BORROW_OBLIGATION_DATA_SIG.to_vec();
+    data.extend(amount.to_le_bytes());
+    let tx = VersionedTransaction {
+        signatures: vec![Signature::new_unique()],
+        message: VersionedMessage::V0(v0::Message {
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys,
+            recent_blockhash: Hash::default(),
+            instructions: vec![CompiledInstruction { program_id_index: 10, accounts: (0..10).collect(), data }],
+            address_table_lookups: vec![],
+        }),
+    };
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bincode::serialize(&tx).unwrap());
+    EncodedConfirmedTransactionWithStatusMeta {
+        slot: 250_000_000,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: EncodedTransaction::Binary(encoded, TransactionBinaryEncoding::Base64),
+            meta: Some(TransactionStatusMeta::default().into()),
+            version: Some(TransactionVersion::Number(0)),
+        },
+        block_time: Some(1_706_000_000),
+    }
+}
+
+#[tokio::test]
+async fn stream_yields_the_records_of_the_scanned_transactions() {
+    let program_id = Pubkey::from_str(KAMINO_LEND_PROGRAM_ID).unwrap();
+    let signature_info = |slot: u64| {
+        json!({ "signature": Signature::new_unique().to_string(), "slot": slot, "err": null, "memo": null, "blockTime": 1_706_000_000, "confirmationStatus": "finalized" })
+    };
+    // Mocks answer once: the first transaction is the borrow, the second the mock's default
+    // system transfer, which has no Kamino instructions
+    let mocks = HashMap::from([
+        (RpcRequest::GetSignaturesForAddress, json!([signature_info(12), signature_info(11)])),
+        (RpcRequest::GetTransaction, serde_json::to_value(borrow_transaction(program_id, 2_500_000)).unwrap()),
+    ]);
+    let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+
+    let opts = ParseOptions { max_transactions: Some(2), ..ParseOptions::default() };
+    let records: Vec<_> = parse_stream(&client, &program_id, opts).collect().await;
+    let amounts: Vec<_> = records
+        .into_iter()
+        .map(|record| match record.unwrap().transaction_type {
+            TransactionType::Borrow { amount, .. } => amount,
+            other => panic!("not a borrow: {:?}", other),
+        })
+        .collect();
+    assert_eq!(amounts, [2_500_000]);
+}
+
+#[tokio::test]
+async fn stream_yields_the_signature_scan_error_and_ends() {
+    let program_id = Pubkey::from_str(KAMINO_LEND_PROGRAM_ID).unwrap();
+    let client = RpcClient::new_mock("fails".to_string());
+
+    let opts = ParseOptions { rpc_retries: 0, ..ParseOptions::default() };
+    let records: Vec<_> = parse_stream(&client, &program_id, opts).collect().await;
+    assert_eq!(records.len(), 1);
+    let error = records.into_iter().next().unwrap().unwrap_err();
+    assert!(format!("{:#}", error).starts_with("failed to fetch the program's signatures"), "{:#}", error);
+}
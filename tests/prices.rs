@@ -0,0 +1,27 @@
+use solana_kamino_rs::mint::NATIVE_MINT;
+use solana_kamino_rs::price::PriceTable;
+use solana_kamino_rs::MintRegistry;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+
+#[test]
+fn usd_volume_scales_by_decimals_and_skips_unpriced_mints() {
+    let usdc = Pubkey::new_unique();
+    let unpriced = Pubkey::new_unique();
+    let prices = PriceTable::from_json(&format!(r#"{{"{}": 1.0, "{}": 150.0}}"#, usdc, NATIVE_MINT)).unwrap();
+    let mut mints = MintRegistry::new();
+    mints.insert(usdc, 6);
+
+    let amounts = HashMap::from([(usdc, 2_500_000), (NATIVE_MINT, 2_000_000_000), (unpriced, 7)]);
+    let volume = prices.usd_volume(&amounts, &mints);
+
+    assert_eq!(volume.by_mint[&usdc], 2.5);
+    assert_eq!(volume.by_mint[&NATIVE_MINT], 300.0);
+    assert_eq!(volume.total, 302.5);
+    assert_eq!(volume.unpriced, vec![unpriced]);
+}
+
+#[test]
+fn invalid_mint_is_rejected() {
+    assert!(PriceTable::from_json(r#"{"not-a-mint": 1.0}"#).is_err());
+}
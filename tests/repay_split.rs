@@ -0,0 +1,44 @@
+use solana_kamino_rs::state::{
+    decode_obligation_borrows, decode_reserve_cumulative_borrow_rate, split_repay, ObligationBorrow, RepaySplit,
+};
+use solana_sdk::pubkey::Pubkey;
+
+const ONE: u128 = 1 << 60;
+
+fn obligation_with_borrow(slot: usize, reserve: Pubkey, rate: u128, borrowed: u128) -> Vec<u8> {
+    let mut data = vec![0; 3344];
+    let entry = 1208 + slot * 200;
+    data[entry..entry + 32].copy_from_slice(reserve.as_ref());
+    data[entry + 32..entry + 48].copy_from_slice(&rate.to_le_bytes());
+    data[entry + 88..entry + 104].copy_from_slice(&borrowed.to_le_bytes());
+    data
+}
+
+#[test]
+fn decodes_obligation_borrows_and_reserve_rate() {
+    let reserve = Pubkey::new_unique();
+    let obligation = obligation_with_borrow(2, reserve, ONE + ONE / 10, 1_000 * ONE);
+    let borrows = decode_obligation_borrows(&obligation).unwrap();
+    assert_eq!(
+        borrows,
+        vec![ObligationBorrow { reserve, cumulative_borrow_rate: 1.1, borrowed_amount: 1_000.0 }]
+    );
+
+    let mut reserve_data = vec![0; 8624];
+    reserve_data[296..312].copy_from_slice(&(ONE * 3 / 2).to_le_bytes());
+    assert_eq!(decode_reserve_cumulative_borrow_rate(&reserve_data).unwrap(), 1.5);
+
+    assert!(decode_obligation_borrows(&obligation[..100]).is_err());
+    assert!(decode_reserve_cumulative_borrow_rate(&reserve_data[..100]).is_err());
+}
+
+#[test]
+fn interest_is_the_growth_of_the_recorded_debt_capped_at_the_repay() {
+    let borrow = ObligationBorrow { reserve: Pubkey::new_unique(), cumulative_borrow_rate: 1.0, borrowed_amount: 1_000.0 };
+
+    assert_eq!(split_repay(500, &borrow, 1.02), Some(RepaySplit { principal: 480, interest: 20 }));
+    assert_eq!(split_repay(10, &borrow, 1.02), Some(RepaySplit { principal: 0, interest: 10 }));
+    assert_eq!(split_repay(500, &borrow, 1.0), Some(RepaySplit { principal: 500, interest: 0 }));
+    // A reserve rate behind the obligation's snapshot means mismatched state
+    assert_eq!(split_repay(500, &borrow, 0.9), None);
+}
@@ -0,0 +1,94 @@
+use base64::Engine;
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_filter::RpcFilterType;
+use solana_client::rpc_request::RpcRequest;
+use solana_kamino_rs::reserve::{fetch_market_reserves, market_reserve_filters, Reserve};
+use solana_kamino_rs::state::decode_reserve_cumulative_borrow_rate;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// The main market's USDC reserve, see `tests/fixtures/README.md`
+fn usdc_reserve() -> Vec<u8> {
+    let encoded = include_str!("fixtures/usdc_reserve.b64");
+    base64::engine::general_purpose::STANDARD.decode(encoded.trim()).unwrap()
+}
+
+#[test]
+fn decodes_a_reserve_account() {
+    let reserve = Reserve::from_account_data(&usdc_reserve()).unwrap();
+
+    assert_eq!(reserve.version, 1);
+    assert_eq!(reserve.last_update_slot, 300_000_000);
+    assert_eq!(reserve.lending_market.to_string(), "7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF");
+    assert_eq!(reserve.liquidity.mint.to_string(), "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+    assert_eq!(reserve.liquidity.supply_vault.to_string(), "JCdAwUu36ka4C9BjeZfMRSx549PmBSqEMzppjjzsMQRZ");
+    assert_eq!(reserve.liquidity.fee_vault.to_string(), "FRhpLGAS3sYQLevt7tqkrkT8GT2BYNBnwcjM3Zbyqixq");
+    assert_eq!(reserve.liquidity.mint_decimals, 6);
+    assert_eq!(reserve.liquidity.available_amount, 31_415_926_535_897);
+    assert_eq!(reserve.liquidity.borrowed_amount(), 180_000_000_000_000.25);
+    assert_eq!(reserve.liquidity.market_price(), 0.9999);
+    assert_eq!(reserve.liquidity.cumulative_borrow_rate(), 1.125);
+    assert!((reserve.liquidity.utilization() - 180_000_000_000_000.25 / 211_415_926_535_897.25).abs() < 1e-12);
+    assert_eq!(reserve.collateral.mint.to_string(), "847kVN2ycaJxTMz3XDjFKGpVRhE2PdwmDrugMBg7C318");
+    assert_eq!(reserve.collateral.mint_total_supply, 190_000_000_000_000);
+    assert_eq!(reserve.collateral.supply_vault.to_string(), "CeHP7ew8VbF3a4QyEqsVntnrZsKdR9zcY1jXid9hyZDq");
+    assert_eq!((reserve.config.loan_to_value_pct, reserve.config.liquidation_threshold_pct), (80, 85));
+    assert_eq!(reserve.config.borrow_factor_pct, 100);
+    assert_eq!((reserve.config.deposit_limit, reserve.config.borrow_limit), (500_000_000_000_000, 450_000_000_000_000));
+    assert_eq!(reserve.config.token_name, "USDC");
+}
+
+#[test]
+fn other_accounts_are_rejected() {
+    let mut data = usdc_reserve();
+    assert!(Reserve::from_account_data(&data[..300]).is_err());
+
+    // An obligation, or any other account, carries a different discriminator
+    data[..8].copy_from_slice(&[168, 206, 141, 106, 88, 76, 172, 167]);
+    assert!(Reserve::from_account_data(&data).is_err());
+    // The lenient decoders used for --decode-state only check the size
+    assert_eq!(decode_reserve_cumulative_borrow_rate(&data).unwrap(), 1.125);
+}
+
+#[test]
+fn market_filters_match_the_reserves_of_that_market_only() {
+    let data = usdc_reserve();
+    let main = Pubkey::from_str("7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF").unwrap();
+    let matches = |market: &Pubkey| {
+        market_reserve_filters(market).iter().all(|filter| match filter {
+            RpcFilterType::Memcmp(memcmp) => memcmp.bytes_match(&data),
+            other => panic!("unexpected filter {:?}", other),
+        })
+    };
+
+    assert!(matches(&main));
+    assert!(!matches(&Pubkey::new_unique()));
+}
+
+#[tokio::test]
+async fn market_reserves_are_listed_by_address() {
+    let reserve = Pubkey::new_unique();
+    let account = json!({
+        "pubkey": reserve.to_string(),
+        "account": {
+            "lamports": 62_000_000,
+            "data": [base64::engine::general_purpose::STANDARD.encode(usdc_reserve()), "base64"],
+            "owner": "KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD",
+            "executable": false,
+            "rentEpoch": 0,
+            "space": 8624,
+        },
+    });
+    let mocks = HashMap::from([(RpcRequest::GetProgramAccounts, json!([account]))]);
+    let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+
+    let program_id = Pubkey::from_str("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD").unwrap();
+    let main = Pubkey::from_str("7u3HeHxYDLhnCoErrtycNokbQYbWGzLs6JSDqGAv5PfF").unwrap();
+    let reserves = fetch_market_reserves(&client, &program_id, &main).await.unwrap();
+    assert_eq!(reserves.len(), 1);
+    assert_eq!(reserves[0].0, reserve);
+    assert_eq!(reserves[0].1.liquidity.mint.to_string(), "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+    assert_eq!(reserves[0].1.config.token_name, "USDC");
+}
@@ -0,0 +1,80 @@
+use base64::Engine;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_kamino_rs::instruction::FLASH_LOAN_DATA_BORROW_SIG;
+use solana_kamino_rs::{fetch_and_parse_signature, fetch_and_parse_signatures_with, read_signature_list, ParseOptions, TransactionType};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::{v0, MessageHeader, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{TransactionVersion, VersionedTransaction};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, EncodedTransactionWithStatusMeta, TransactionBinaryEncoding,
+    TransactionStatusMeta,
+};
+use std::collections::HashMap;
+
+#[tokio::test]
+async fn signature_that_does_not_touch_the_program_is_rejected() {
+    // The mock RPC answers getTransaction with a system program transfer
+    let client = RpcClient::new_mock("succeeds".to_string());
+    let program_id = Pubkey::new_unique();
+    let error = fetch_and_parse_signature(&client, &program_id, &Signature::default(), ParseOptions::default()).await.unwrap_err();
+    assert!(error.to_string().contains("does not invoke program"), "{}", error);
+}
+
+#[tokio::test]
+async fn missing_signature_is_reported() {
+    let client = RpcClient::new_mock("fails".to_string());
+    let error = fetch_and_parse_signature(&client, &Pubkey::new_unique(), &Signature::default(), ParseOptions::default()).await.unwrap_err();
+    assert!(error.to_string().contains("not found"), "{}", error);
+}
+
+#[tokio::test]
+async fn listed_signatures_are_parsed_and_invalid_lines_skipped() {
+    let program_id = Pubkey::new_unique();
+    let (listed, other) = (Signature::new_unique(), Signature::new_unique());
+    let signatures = read_signature_list(format!("{}\n\nnot a signature\n  {}  \n", listed, other).as_bytes()).unwrap();
+    assert_eq!(signatures, [listed, other]);
+
+    let mut account_keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+    account_keys.push(program_id);
+    let mut data = FLASH_LOAN_DATA_BORROW_SIG.to_vec();
+    data.extend(3_000u64.to_le_bytes());
+    let tx = VersionedTransaction {
+        signatures: vec![listed],
+        message: VersionedMessage::V0(v0::Message {
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys: account_keys.clone(),
+            recent_blockhash: Hash::default(),
+            instructions: vec![CompiledInstruction { program_id_index: 5, accounts: vec![0, 1, 2, 3, 4], data }],
+            address_table_lookups: vec![],
+        }),
+    };
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bincode::serialize(&tx).unwrap());
+    let transaction = EncodedConfirmedTransactionWithStatusMeta {
+        slot: 250_000_000,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: EncodedTransaction::Binary(encoded, TransactionBinaryEncoding::Base64),
+            meta: Some(TransactionStatusMeta::default().into()),
+            version: Some(TransactionVersion::Number(0)),
+        },
+        block_time: Some(1_706_000_000),
+    };
+
+    // Mocks answer once: the first signature gets the flash borrow, the second the mock's default
+    // system transfer, which does not invoke the program and is skipped
+    let mocks = HashMap::from([(RpcRequest::GetTransaction, serde_json::to_value(transaction).unwrap())]);
+    let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+    let opts = ParseOptions { concurrency: 1.try_into().unwrap(), ..ParseOptions::default() };
+    let summary = fetch_and_parse_signatures_with(&client, &program_id, signatures, opts, |_, _| {}).await.unwrap();
+    assert_eq!(summary.transactions_scanned, 1);
+    assert_eq!(summary.block_time_window, Some((1_706_000_000, 1_706_000_000)));
+    let records: Vec<_> = summary.records.iter().map(|record| (record.signature.clone(), record.transaction_type)).collect();
+    assert_eq!(records, [(listed.to_string(), TransactionType::FlashBorrow { amount: 3_000, initiator: account_keys[0] })]);
+}
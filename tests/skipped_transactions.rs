@@ -0,0 +1,96 @@
+use base64::Engine;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_kamino_rs::{parse_fetched_transaction, AccountFilter, LookupCache, ParseSummary, SkippedTransaction};
+use solana_sdk::hash::Hash;
+use solana_sdk::message::v0::{self, MessageAddressTableLookup};
+use solana_sdk::message::{MessageHeader, VersionedMessage};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::{TransactionVersion, VersionedTransaction};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, EncodedTransactionWithStatusMeta,
+    TransactionBinaryEncoding,
+};
+
+#[tokio::test]
+async fn unresolved_lookup_tables_are_tallied_with_fetch_errors() {
+    let (cached_table, missing_table) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let lookup = |account_key| MessageAddressTableLookup {
+        account_key,
+        writable_indexes: vec![0],
+        readonly_indexes: vec![],
+    };
+    let tx = VersionedTransaction {
+        signatures: vec![Signature::default()],
+        message: VersionedMessage::V0(v0::Message {
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 0,
+            },
+            account_keys: vec![Pubkey::new_unique()],
+            recent_blockhash: Hash::default(),
+            instructions: vec![],
+            address_table_lookups: vec![lookup(cached_table), lookup(missing_table)],
+        }),
+    };
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bincode::serialize(&tx).unwrap());
+    let transaction = EncodedConfirmedTransactionWithStatusMeta {
+        slot: 1,
+        block_time: None,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: EncodedTransaction::Binary(encoded, TransactionBinaryEncoding::Base64),
+            meta: None,
+            version: Some(TransactionVersion::Number(0)),
+        },
+    };
+
+    // Nothing listens here, so the table missing from the cache fails to fetch
+    let client = RpcClient::new("http://127.0.0.1:1".to_string());
+    let mut cache = LookupCache::from([(cached_table, vec![Pubkey::new_unique()])]);
+    let parsed = parse_fetched_transaction(&client, &transaction, &mut cache, &Pubkey::new_unique(), &AccountFilter::default()).await;
+    let Err(SkippedTransaction::UnresolvedLookupTables(unresolved)) = &parsed else {
+        panic!("expected unresolved lookup tables, got {:?}", parsed);
+    };
+    assert_eq!(unresolved.tables, vec![missing_table]);
+
+    let mut summary = ParseSummary {
+        transactions_scanned: 4,
+        ..Default::default()
+    };
+    summary.add_parsed(parsed);
+    summary.fetch_errors.push("fetch-failed".to_string());
+
+    assert_eq!(summary.lookup_errors, vec![Signature::default().to_string()]);
+    assert!(summary.unresolved_lookup_tables.contains(&missing_table));
+    assert_eq!(summary.skipped_count(), 2);
+    assert_eq!(summary.skipped_fraction(), 0.5);
+    assert!(summary.to_string().contains("Transactions skipped due to unresolved lookup tables: 1 (1 tables)\n"));
+}
+
+#[tokio::test]
+async fn undecodable_transaction_is_skipped_and_counted() {
+    let transaction = EncodedConfirmedTransactionWithStatusMeta {
+        slot: 7,
+        block_time: None,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: EncodedTransaction::Binary("not base64!".to_string(), TransactionBinaryEncoding::Base64),
+            meta: None,
+            version: Some(TransactionVersion::Number(0)),
+        },
+    };
+
+    let client = RpcClient::new("http://127.0.0.1:1".to_string());
+    let parsed = parse_fetched_transaction(&client, &transaction, &mut LookupCache::new(), &Pubkey::new_unique(), &AccountFilter::default()).await;
+    assert!(matches!(parsed, Err(SkippedTransaction::Undecodable { slot: 7 })), "{:?}", parsed);
+
+    let mut summary = ParseSummary {
+        transactions_scanned: 2,
+        ..Default::default()
+    };
+    summary.add_parsed(parsed);
+    assert_eq!(summary.decode_errors, 1);
+    assert_eq!(summary.skipped_count(), 1);
+    assert!(summary.to_string().contains("Transactions skipped due to decode errors: 1\n"));
+    assert_eq!(serde_json::to_value(&summary).unwrap()["skipped"]["decode_errors"], 1);
+}
@@ -0,0 +1,31 @@
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_custom_error::{JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY, JSON_RPC_SERVER_ERROR_SLOT_SKIPPED};
+use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
+use solana_kamino_rs::rpc::is_skipped_slot;
+use solana_kamino_rs::{fetch_and_parse_slots, ParseOptions};
+use solana_sdk::pubkey::Pubkey;
+
+fn rpc_error(code: i64) -> ClientError {
+    ClientErrorKind::RpcError(RpcError::RpcResponseError {
+        code,
+        message: String::new(),
+        data: RpcResponseErrorData::Empty,
+    })
+    .into()
+}
+
+#[test]
+fn only_missing_block_errors_count_as_skipped_slots() {
+    assert!(is_skipped_slot(&rpc_error(JSON_RPC_SERVER_ERROR_SLOT_SKIPPED)));
+    assert!(!is_skipped_slot(&rpc_error(JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY)));
+    assert!(!is_skipped_slot(&ClientErrorKind::Custom("connection reset".to_string()).into()));
+}
+
+#[tokio::test]
+async fn reversed_slot_range_is_rejected_before_fetching() {
+    // Nothing listens here, so reaching the RPC would fail differently
+    let client = RpcClient::new("http://127.0.0.1:1".to_string());
+    let error = fetch_and_parse_slots(&client, &Pubkey::new_unique(), 200, 100, ParseOptions::default()).await.unwrap_err();
+    assert!(error.to_string().contains("after --to-slot"), "{}", error);
+}
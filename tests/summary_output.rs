@@ -0,0 +1,374 @@
+use solana_kamino_rs::{KaminoTransaction, MintOrder, ParseSummary, Sample, TransactionType};
+use solana_sdk::pubkey::Pubkey;
+
+fn summary_with_borrow(mint: Pubkey) -> ParseSummary {
+    let mut summary = ParseSummary::default();
+    summary.mints.insert(mint, 6);
+    summary.add_record(KaminoTransaction {
+        signature: "a".to_string(),
+        slot: 1,
+        block_time: None,
+        transaction_type: TransactionType::Borrow {
+            amount: 1_500_000,
+            obligation: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            referrer: None,
+        },
+        reserve_token: Some(mint),
+        instruction_index: 0,
+        inner_index: None,
+        failed: false,
+        lending_market: None,
+    });
+    summary
+}
+
+#[test]
+fn display_renders_the_text_breakdown() {
+    let mint = Pubkey::new_unique();
+    let mut summary = summary_with_borrow(mint);
+    summary.mints.insert_symbol(mint, "USDX".to_string());
+    let text = summary.to_string();
+
+    assert!(text.starts_with("Breakdown for Kamino loans by mint\n"));
+    assert!(text.contains("Borrow obligation USDX: 1.5\n"));
+    assert!(text.contains("Loan txs count: 1\n"));
+    assert!(text.contains("Net borrowed USDX: 1.5\n"));
+}
+
+#[test]
+fn serializes_ui_amounts_keyed_by_mint() {
+    let mint = Pubkey::new_unique();
+    let value = serde_json::to_value(summary_with_borrow(mint)).unwrap();
+
+    assert_eq!(value["obligation_borrows"][mint.to_string()], 1.5);
+    assert_eq!(value["counts"]["borrow"], 1);
+    assert_eq!(value["window"], serde_json::Value::Null);
+}
+
+#[test]
+fn raw_amounts_are_output_next_to_ui_amounts() {
+    let mint = Pubkey::new_unique();
+    let mut summary = summary_with_borrow(mint);
+    summary.mints.insert_symbol(mint, "USDX".to_string());
+    summary.mints.set_raw_amounts(true);
+
+    let value = serde_json::to_value(&summary).unwrap();
+    assert_eq!(value["obligation_borrows"][mint.to_string()], serde_json::json!({ "raw_amount": 1_500_000, "ui_amount": 1.5 }));
+    assert_eq!(value["net_flows"][mint.to_string()]["net"], serde_json::json!({ "raw_amount": -1_500_000, "ui_amount": -1.5 }));
+
+    let table = summary.display_table(MintOrder::Mint).to_string();
+    let cells = |line: &str| -> Vec<String> { line.split(['│', '┆']).map(str::trim).filter(|cell| !cell.is_empty()).map(String::from).collect() };
+    let header = table.lines().find(|line| line.contains("Token")).unwrap();
+    assert_eq!(cells(header)[1..3], ["Flash borrow", "Flash borrow (raw)"]);
+    let row = table.lines().find(|line| line.contains("USDX")).unwrap();
+    assert_eq!(cells(row), ["USDX", "0", "0", "1.5", "1500000", "0", "0", "-1.5", "-1500000"]);
+}
+
+#[test]
+fn block_time_window_is_a_readable_duration_between_timestamps() {
+    let mut summary = summary_with_borrow(Pubkey::new_unique());
+    summary.block_time_window = Some((1_706_000_040, 1_706_000_040 + 3_847));
+
+    assert!(summary
+        .to_string()
+        .starts_with("Breakdown for Kamino loans by mint over 1h 4m 7s (2024-01-23T08:54:00Z to 2024-01-23T09:58:07Z)\n"));
+    let value = serde_json::to_value(&summary).unwrap();
+    assert_eq!(value["window"]["seconds"], 3_847);
+    assert_eq!(value["window"]["start"], "2024-01-23T08:54:00Z");
+}
+
+#[test]
+fn borrow_rate_divides_volume_by_the_window() {
+    let mint = Pubkey::new_unique();
+    let mut summary = summary_with_borrow(mint);
+    summary.mints.insert_symbol(mint, "USDX".to_string());
+    summary.block_time_window = Some((1_706_000_000, 1_706_000_000 + 30 * 60));
+
+    let rate = summary.borrow_rates().unwrap()[&mint];
+    assert_eq!((rate.obligation_per_minute, rate.obligation_per_hour()), (0.05, 3.0));
+    assert!(summary.to_string().contains("  USDX: flash loans 0/min (0/h), obligation borrows 0.05/min (3/h)\n"));
+    assert_eq!(serde_json::to_value(&summary).unwrap()["borrow_rates"][mint.to_string()]["obligation_per_hour"], 3.0);
+
+    // A window within one block has no duration to divide by
+    summary.block_time_window = Some((1_706_000_000, 1_706_000_000));
+    assert_eq!(summary.borrow_rates(), None);
+    assert!(summary.to_string().contains("Borrow rate: not computed, every transaction has the same block time\n"));
+    assert_eq!(serde_json::to_value(&summary).unwrap()["borrow_rates"], serde_json::Value::Null);
+}
+
+#[test]
+fn sampled_totals_are_extrapolated_and_labelled_as_estimates() {
+    let sample = Sample { rate: 0.25, seed: 7 };
+    let signatures: Vec<u32> = (0..1_000).collect();
+    let selected = sample.select(signatures.clone());
+    assert_eq!(selected, sample.select(signatures));
+    assert!((150..350).contains(&selected.len()), "{} selected", selected.len());
+
+    let mint = Pubkey::new_unique();
+    let mut summary = summary_with_borrow(mint);
+    summary.transactions_scanned = 1;
+    summary.extrapolate(sample, 4);
+    assert_eq!((summary.loan_borrow_amounts[&mint], summary.loan_txs_count), (6_000_000, 4));
+    assert_eq!(summary.flows[&mint].borrowed, 6_000_000);
+    assert_eq!(summary.records.len(), 1);
+    assert!(summary.to_string().contains("ESTIMATE from a random 25% sample (seed 7): 1 of 4 transactions processed, totals and counts extrapolated x4\n"));
+    assert_eq!(serde_json::to_value(&summary).unwrap()["sample"]["extrapolation_factor"], 4.0);
+}
+
+#[test]
+fn top_n_lists_the_largest_borrowed_mints() {
+    let (small, large, medium) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut summary = ParseSummary::default();
+    for (mint, symbol, decimals, amount) in [(small, "SMALL", 6, 5_000_000), (large, "LARGE", 9, 90_000_000_000), (medium, "MEDIUM", 0, 20)] {
+        summary.mints.insert(mint, decimals);
+        summary.mints.insert_symbol(mint, symbol.to_string());
+        summary.loan_borrow_amounts.insert(mint, amount);
+    }
+
+    let text = summary.display_top(2).to_string();
+    let large_at = text.find("Borrow obligation LARGE: 90\n").unwrap();
+    let medium_at = text.find("Borrow obligation MEDIUM: 20\n").unwrap();
+    assert!(large_at < medium_at);
+    assert!(!text.contains("Borrow obligation SMALL"));
+    assert!(text.contains("Borrow obligation ... and 1 others\n"));
+    assert!(summary.to_string().contains("Borrow obligation SMALL: 5\n"));
+}
+
+#[test]
+fn per_token_lines_are_sorted_by_mint_address_or_volume() {
+    let mints: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    // Mint i deposits i + 1 tokens, the records added in the given order
+    let summary = |order: &[usize]| {
+        let mut summary = ParseSummary::default();
+        for &i in order {
+            summary.mints.insert(mints[i], 0);
+            summary.add_record(KaminoTransaction {
+                signature: "a".to_string(),
+                slot: 1,
+                block_time: None,
+                transaction_type: TransactionType::Deposit { amount: i as u64 + 1 },
+                reserve_token: Some(mints[i]),
+                instruction_index: 0,
+                inner_index: None,
+                failed: false,
+                lending_market: None,
+            });
+        }
+        summary
+    };
+    let deposits = |text: &str| -> Vec<String> {
+        text.lines().filter_map(|line| line.strip_prefix("Deposit reserve liquidity ")).map(String::from).collect()
+    };
+
+    // The same records added in another order print the same, by base58 address
+    let (forward, reversed) = (summary(&[0, 1, 2, 3, 4, 5]), summary(&[5, 4, 3, 2, 1, 0]));
+    assert_eq!(forward.to_string(), reversed.to_string());
+    assert_eq!(forward.display_table(MintOrder::Mint).to_string(), reversed.display_table(MintOrder::Mint).to_string());
+    let mut by_address: Vec<(String, usize)> = mints.iter().enumerate().map(|(i, mint)| (mint.to_string(), i)).collect();
+    by_address.sort();
+    let expected: Vec<String> = by_address.iter().map(|(_, i)| format!("{}: {}", forward.mints.symbol(&mints[*i]), i + 1)).collect();
+    assert_eq!(deposits(&forward.to_string()), expected);
+
+    // By volume, the largest deposit comes first
+    let by_volume = deposits(&reversed.display_sorted(MintOrder::Volume, None).to_string());
+    let expected: Vec<String> = (0..6).rev().map(|i| format!("{}: {}", forward.mints.symbol(&mints[i]), i + 1)).collect();
+    assert_eq!(by_volume, expected);
+}
+
+#[test]
+fn unique_users_count_each_borrower_initiator_and_obligation_once() {
+    let mint = Pubkey::new_unique();
+    let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (alice_main, alice_alt, bob_main) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut summary = ParseSummary::default();
+    for transaction_type in [
+        TransactionType::Borrow { amount: 1, obligation: alice_main, owner: alice, referrer: None },
+        TransactionType::Borrow { amount: 2, obligation: alice_alt, owner: alice, referrer: None },
+        TransactionType::Borrow { amount: 3, obligation: bob_main, owner: bob, referrer: None },
+        TransactionType::Repay { amount: 1, obligation: alice_main, reserve: Pubkey::new_unique(), split: None },
+        TransactionType::FlashBorrow { amount: 4, initiator: alice },
+        TransactionType::FlashBorrow { amount: 5, initiator: alice },
+    ] {
+        summary.add_record(KaminoTransaction {
+            signature: "a".to_string(),
+            slot: 1,
+            block_time: None,
+            transaction_type,
+            reserve_token: Some(mint),
+            instruction_index: 0,
+            inner_index: None,
+            failed: false,
+            lending_market: None,
+        });
+    }
+
+    assert_eq!((summary.unique_borrowers(), summary.flash_loan_initiators.len(), summary.unique_obligations()), (2, 1, 3));
+    let text = summary.to_string();
+    assert!(text.contains("Unique borrowers: 2\nUnique flash loan initiators: 1\nUnique obligations: 3\n"));
+    let unique = &serde_json::to_value(&summary).unwrap()["unique"];
+    assert_eq!(*unique, serde_json::json!({ "borrowers": 2, "flash_loan_initiators": 1, "obligations": 3 }));
+}
+
+#[test]
+fn flash_loan_fee_rates_flag_loans_off_the_typical_rate() {
+    let mint = Pubkey::new_unique();
+    let mut summary = ParseSummary::default();
+    summary.mints.insert_symbol(mint, "USDX".to_string());
+    // Three loans at 1 bps, one at 5 bps, and a small one whose fee is rounded up to 1 raw unit
+    for (signature, borrowed, fee) in [("a", 1_000_000_000, 100_000), ("b", 2_000_000_000, 200_000), ("c", 1_000_000_000, 100_000), ("d", 1_000_000_000, 500_000), ("e", 1_000, 1)] {
+        summary.add_record(KaminoTransaction {
+            signature: signature.to_string(),
+            slot: 1,
+            block_time: None,
+            transaction_type: TransactionType::FlashRepay { amount: borrowed + fee, fee: Some(fee) },
+            reserve_token: Some(mint),
+            instruction_index: 1,
+            inner_index: None,
+            failed: false,
+            lending_market: None,
+        });
+    }
+
+    let rate = &summary.flash_loan_fee_rates()[&mint];
+    assert_eq!((rate.loans, rate.median_bps), (5, 1.0));
+    assert!((rate.average_bps - 900_001.0 * 10_000.0 / 5_000_001_000.0).abs() < 1e-9);
+    assert_eq!(rate.outliers, [("d".to_string(), 5.0)]);
+
+    let text = summary.to_string();
+    assert!(text.contains("Flash Loan Fee Rate USDX: 1.80 bps average over 5 loans\n  Unusual fee in d: 5.00 bps, typically 1.00 bps\n"), "{}", text);
+    let json = serde_json::to_value(&summary).unwrap();
+    assert_eq!(json["flash_loan_fee_rates"][mint.to_string()]["outliers"], serde_json::json!([{ "signature": "d", "fee_bps": 5.0 }]));
+}
+
+#[test]
+fn net_flow_combines_every_instruction_type_per_mint() {
+    let mint = Pubkey::new_unique();
+    let mut summary = ParseSummary::default();
+    summary.mints.insert(mint, 6);
+    summary.mints.insert_symbol(mint, "USDX".to_string());
+    let obligation = Pubkey::new_unique();
+    for transaction_type in [
+        TransactionType::Deposit { amount: 10_000_000 },
+        TransactionType::Borrow { amount: 4_000_000, obligation, owner: Pubkey::new_unique(), referrer: None },
+        TransactionType::Repay { amount: 1_000_000, obligation, reserve: Pubkey::new_unique(), split: None },
+        TransactionType::Redeem { amount: 9_000_000 },
+        TransactionType::FlashBorrow { amount: 50_000_000, initiator: Pubkey::default() },
+    ] {
+        summary.add_record(KaminoTransaction {
+            signature: "a".to_string(),
+            slot: 1,
+            block_time: None,
+            transaction_type,
+            reserve_token: Some(mint),
+            instruction_index: 0,
+            inner_index: None,
+            failed: false,
+            lending_market: None,
+        });
+    }
+
+    let flow = summary.flows[&mint];
+    assert_eq!((flow.borrowed, flow.repaid, flow.deposited, flow.withdrawn), (4_000_000, 1_000_000, 10_000_000, 9_000_000));
+    assert_eq!(flow.net(), -2_000_000);
+    assert!(summary
+        .to_string()
+        .contains("  USDX: borrowed 4, repaid 1, deposited 10, withdrawn 9, net -2 (outflow)\n"));
+
+    // The table shows the same net flow next to the borrow and repay volume
+    let table = summary.display_table(MintOrder::Mint).to_string();
+    let cells = |line: &str| -> Vec<String> { line.split(['│', '┆']).map(str::trim).filter(|cell| !cell.is_empty()).map(String::from).collect() };
+    let header = table.lines().find(|line| line.contains("Token")).unwrap();
+    assert_eq!(cells(header), ["Token", "Flash borrow", "Obligation borrow", "Repay", "Net flow"]);
+    let row = table.lines().find(|line| line.contains("USDX")).unwrap();
+    assert_eq!(cells(row), ["USDX", "50", "4", "1", "-2"]);
+}
+
+#[test]
+fn comparison_shows_the_change_from_the_previous_window() {
+    let (mint, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut baseline = summary_with_borrow(mint);
+    baseline.mints.insert_symbol(mint, "USDX".to_string());
+    let mut current = summary_with_borrow(mint);
+    current.mints.insert_symbol(mint, "USDX".to_string());
+    current.add_record(KaminoTransaction {
+        signature: "b".to_string(),
+        slot: 2,
+        block_time: None,
+        transaction_type: TransactionType::FlashBorrow { amount: 3_000_000, initiator: Pubkey::default() },
+        reserve_token: Some(mint),
+        instruction_index: 0,
+        inner_index: None,
+        failed: false,
+        lending_market: None,
+    });
+    current.add_record(KaminoTransaction {
+        signature: "c".to_string(),
+        slot: 2,
+        block_time: None,
+        transaction_type: TransactionType::Deposit { amount: 5 },
+        reserve_token: Some(other),
+        instruction_index: 0,
+        inner_index: None,
+        failed: false,
+        lending_market: None,
+    });
+    baseline.add_record(KaminoTransaction {
+        signature: "d".to_string(),
+        slot: 0,
+        block_time: None,
+        transaction_type: TransactionType::Borrow {
+            amount: 500_000,
+            obligation: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            referrer: None,
+        },
+        reserve_token: Some(mint),
+        instruction_index: 0,
+        inner_index: None,
+        failed: false,
+        lending_market: None,
+    });
+
+    let comparison = current.compare(&baseline);
+    let text = comparison.to_string();
+    assert!(text.contains("Borrow obligation USDX: 2 -> 1.5 (-25.0%)\n"), "{}", text);
+    assert!(text.contains("Flash Loan Borrow USDX: 0 -> 3 (new)\n"), "{}", text);
+    assert!(text.contains("Loan txs count: 2 -> 1 (-50.0%)\n"), "{}", text);
+    assert!(text.contains("Liquidation txs count: 0 -> 0 (unchanged)\n"), "{}", text);
+
+    let value = serde_json::to_value(&comparison).unwrap();
+    let borrows = &value["obligation_borrows"][mint.to_string()];
+    assert_eq!((borrows["baseline"].as_f64(), borrows["current"].as_f64()), (Some(2.0), Some(1.5)));
+    assert_eq!(borrows["change_percent"], -25.0);
+    assert_eq!(value["deposits"][other.to_string()]["change_percent"], serde_json::Value::Null);
+    assert_eq!(value["counts"]["flash_loan"]["current"], 1);
+}
+
+#[test]
+fn failed_refreshes_count_successful_ones_as_attempted() {
+    let reserve = Pubkey::new_unique();
+    let mut summary = ParseSummary::default();
+    // A successful refresh and one from a failed transaction, as parsed with include_failed
+    for (signature, failed) in [("a", false), ("b", true)] {
+        summary.add_record(KaminoTransaction {
+            signature: signature.to_string(),
+            slot: 1,
+            block_time: None,
+            transaction_type: TransactionType::RefreshReserve { reserve },
+            reserve_token: None,
+            instruction_index: 0,
+            inner_index: None,
+            failed,
+            lending_market: None,
+        });
+    }
+
+    assert_eq!(summary.attempted_count("refresh_reserve"), 2);
+    assert_eq!(summary.attempted_count("refresh_obligation"), 0);
+    let text = summary.to_string();
+    assert!(text.contains("Failed refresh_reserve attempts: 1 of 2 attempted\n"), "{}", text);
+    let value = serde_json::to_value(&summary).unwrap();
+    assert_eq!(value["failed_attempts"]["refresh_reserve"]["attempted"], 2);
+    assert_eq!(value["counts"]["refresh_reserve"], 1);
+}
@@ -0,0 +1,128 @@
+//! End-to-end scan against a local `solana-test-validator`, covering the real RPC client path:
+//! signature paging, transaction fetches and lookup table resolution.
+//!
+//! Ignored by default since it needs the validator binary on PATH; run it with
+//! `cargo test --test test_validator -- --ignored`.
+//!
+//! The validator loads `tests/fixtures/noop_program.so` at the Kamino Lend program id, so the
+//! Kamino instructions succeed without klend's accounts and the scan sees them as it would on
+//! mainnet: successful transactions, found through the program's signatures.
+
+use solana_address_lookup_table_interface::instruction::{create_lookup_table, extend_lookup_table};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_kamino_rs::instruction::{BORROW_OBLIGATION_DATA_SIG, BORROW_OBLIGATION_V1_DATA_SIG};
+use solana_kamino_rs::{fetch_and_parse, ParseOptions, TransactionType, KAMINO_LEND_PROGRAM_ID};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::message::{v0, AddressLookupTableAccount, VersionedMessage};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, VersionedTransaction};
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::time::Duration;
+
+const RPC_PORT: u16 = 18_899;
+
+/// The validator process, killed when the test ends however it ends
+struct TestValidator {
+    process: Child,
+    ledger: std::path::PathBuf,
+}
+
+impl TestValidator {
+    async fn start() -> (Self, RpcClient) {
+        let ledger = std::env::temp_dir().join(format!("kamino-test-ledger-{}", std::process::id()));
+        let noop_program = format!("{}/tests/fixtures/noop_program.so", env!("CARGO_MANIFEST_DIR"));
+        let process = Command::new("solana-test-validator")
+            .args(["--reset", "--quiet", "--rpc-port", &RPC_PORT.to_string(), "--faucet-port", &(RPC_PORT + 1001).to_string()])
+            .args(["--bpf-program", KAMINO_LEND_PROGRAM_ID, &noop_program])
+            .arg("--ledger")
+            .arg(&ledger)
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("solana-test-validator on PATH");
+        let validator = TestValidator { process, ledger };
+        let client = RpcClient::new_with_commitment(format!("http://127.0.0.1:{}", RPC_PORT), CommitmentConfig::confirmed());
+        for _ in 0..120 {
+            if client.get_health().await.is_ok() {
+                return (validator, client);
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        panic!("solana-test-validator did not become healthy");
+    }
+}
+
+impl Drop for TestValidator {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        let _ = std::fs::remove_dir_all(&self.ledger);
+    }
+}
+
+async fn wait_for_next_slot(client: &RpcClient) {
+    let slot = client.get_slot().await.unwrap();
+    while client.get_slot().await.unwrap() <= slot {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs solana-test-validator on PATH"]
+async fn scan_decodes_borrows_resolved_through_a_lookup_table() {
+    let (_validator, client) = TestValidator::start().await;
+    let payer = Keypair::new();
+    let airdrop = client.request_airdrop(&payer.pubkey(), 10 * LAMPORTS_PER_SOL).await.unwrap();
+    while !client.confirm_transaction(&airdrop).await.unwrap() {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+
+    // Borrow accounts past the owner: obligation, market, reserve, liquidity supply, mint, ...
+    let accounts: Vec<Pubkey> = (0..9).map(|_| Pubkey::new_unique()).collect();
+    let (market, mint) = (accounts[1], accounts[4]);
+
+    let recent_slot = client.get_slot_with_commitment(CommitmentConfig::finalized()).await.unwrap();
+    let (create, table) = create_lookup_table(payer.pubkey(), payer.pubkey(), recent_slot);
+    let extend = extend_lookup_table(table, payer.pubkey(), Some(payer.pubkey()), accounts.clone());
+    let blockhash = client.get_latest_blockhash().await.unwrap();
+    let setup = Transaction::new_signed_with_payer(&[create, extend], Some(&payer.pubkey()), &[&payer], blockhash);
+    client.send_and_confirm_transaction(&setup).await.unwrap();
+    // Addresses added to a table can only be looked up from the next slot on
+    wait_for_next_slot(&client).await;
+
+    let program_id = Pubkey::from_str(KAMINO_LEND_PROGRAM_ID).unwrap();
+    let lookup_tables = [AddressLookupTableAccount { key: table, addresses: accounts.clone() }];
+    let mut signatures = Vec::new();
+    for (discriminator, amount) in [(BORROW_OBLIGATION_V1_DATA_SIG, 1_500_000u64), (BORROW_OBLIGATION_DATA_SIG, 2_000_000)] {
+        let mut data = discriminator.to_vec();
+        data.extend(amount.to_le_bytes());
+        let metas = std::iter::once(AccountMeta::new(payer.pubkey(), true))
+            .chain(accounts.iter().map(|account| AccountMeta::new_readonly(*account, false)))
+            .collect();
+        let borrow = Instruction { program_id, accounts: metas, data };
+        let blockhash = client.get_latest_blockhash().await.unwrap();
+        let message = v0::Message::try_compile(&payer.pubkey(), &[borrow], &lookup_tables, blockhash).unwrap();
+        let transaction = VersionedTransaction::try_new(VersionedMessage::V0(message), &[&payer]).unwrap();
+        signatures.push(client.send_and_confirm_transaction(&transaction).await.unwrap().to_string());
+    }
+
+    let summary = fetch_and_parse(&client, &program_id, ParseOptions::default()).await.unwrap();
+    let mut borrows: Vec<_> = summary
+        .records
+        .iter()
+        .filter_map(|record| match record.transaction_type {
+            TransactionType::Borrow { amount, obligation, owner, .. } => {
+                Some((record.signature.clone(), amount, obligation, owner, record.reserve_token, record.lending_market, record.failed))
+            }
+            _ => None,
+        })
+        .collect();
+    borrows.sort_by_key(|borrow| borrow.1);
+    let expected = |signature: &String, amount| (signature.clone(), amount, accounts[0], payer.pubkey(), Some(mint), Some(market), false);
+    assert_eq!(borrows, vec![expected(&signatures[0], 1_500_000), expected(&signatures[1], 2_000_000)]);
+    assert!(summary.failed_counts.is_empty());
+    assert_eq!(summary.loan_txs_count, 2);
+}
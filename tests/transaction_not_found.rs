@@ -0,0 +1,19 @@
+use serde_json::{json, Value};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_client::rpc_request::RpcRequest;
+use solana_kamino_rs::rpc::{fetch_transaction, is_transaction_not_found};
+use solana_sdk::signature::Signature;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn missing_transaction_from_an_old_slot_fails_without_waiting() {
+    let mocks = HashMap::from([(RpcRequest::GetTransaction, Value::Null), (RpcRequest::GetSlot, json!(10_000))]);
+    let client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+
+    let started = Instant::now();
+    let error = fetch_transaction(&client, &Signature::default(), Some(100), RpcTransactionConfig::default(), 0).await.unwrap_err();
+    assert!(is_transaction_not_found(&error), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(1));
+}